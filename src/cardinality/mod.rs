@@ -0,0 +1,735 @@
+//! Cardinality and selectivity estimation.
+//!
+//! The [`Estimator`] computes the selectivity of scalar predicates from the column statistics in
+//! the catalog, and uses those selectivities to estimate the number of rows produced by filters and
+//! joins. The memo table uses it to derive the row count of every group.
+//!
+//! When predicates constrain several columns of a group of correlated columns (see
+//! [`ColumnGroupStatistics`]), the statistics of the group are used instead of assuming that the
+//! columns are independent.
+
+use crate::catalog::{Catalog, ColumnId};
+use crate::expression::logical::JoinType;
+use crate::expression::scalar::{CompareOperator, ScalarExpression};
+use crate::properties::ColumnSet;
+use crate::properties::LogicalProperties;
+use crate::statistics::{ColumnGroupStatistics, ColumnStatistics, FunctionalDependency};
+use crate::value::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+pub mod overrides;
+
+use overrides::CardinalityOverrides;
+
+#[cfg(test)]
+mod tests;
+
+/// The selectivity of an equality predicate when nothing is known about the column.
+pub const DEFAULT_EQUALITY_SELECTIVITY: f64 = 0.005;
+
+/// The selectivity of a range predicate when nothing is known about the column.
+pub const DEFAULT_RANGE_SELECTIVITY: f64 = 1.0 / 3.0;
+
+/// The selectivity of a `LIKE` predicate that does not start with a fixed prefix.
+pub const DEFAULT_LIKE_SELECTIVITY: f64 = 0.1;
+
+/// The fraction of `NULL`s assumed for a nullable column without statistics.
+pub const DEFAULT_NULL_FRACTION: f64 = 0.01;
+
+/// How the selectivities of the predicates in a conjunction are combined.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Correlation {
+    /// Assume that predicates are independent, and multiply their selectivities.
+    #[default]
+    Independent,
+    /// Assume that predicates are correlated, and dampen the effect of each additional predicate
+    /// by applying `s1 * s2^(1/2) * s3^(1/4) * s4^(1/8)` to the four most selective predicates.
+    ExponentialBackoff,
+}
+
+/// The options of an [`Estimator`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EstimatorOptions {
+    pub correlation: Correlation,
+}
+
+/// An estimator of selectivities and row counts.
+#[derive(Debug, Clone)]
+pub struct Estimator {
+    catalog: Arc<Catalog>,
+    options: EstimatorOptions,
+    overrides: CardinalityOverrides,
+}
+
+/// The lower and upper bounds that range predicates place on a single column.
+#[derive(Default)]
+struct Range<'a> {
+    lower: Option<(CompareOperator, &'a Value)>,
+    upper: Option<(CompareOperator, &'a Value)>,
+}
+
+impl Estimator {
+    pub fn new(catalog: Arc<Catalog>) -> Self {
+        Self::with_options(catalog, EstimatorOptions::default())
+    }
+
+    pub fn with_options(catalog: Arc<Catalog>, options: EstimatorOptions) -> Self {
+        Self {
+            catalog,
+            options,
+            overrides: CardinalityOverrides::default(),
+        }
+    }
+
+    /// Pins the row counts of the groups matched by the given overrides.
+    pub fn with_overrides(mut self, overrides: CardinalityOverrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    pub fn overrides(&self) -> &CardinalityOverrides {
+        &self.overrides
+    }
+
+    pub fn catalog(&self) -> &Arc<Catalog> {
+        &self.catalog
+    }
+
+    pub fn options(&self) -> &EstimatorOptions {
+        &self.options
+    }
+
+    /// Estimates the number of rows that pass all of the given filters.
+    pub fn filter_row_count(&self, input_rows: f64, filters: &[Arc<ScalarExpression>]) -> f64 {
+        input_rows * self.conjunction_selectivity(filters)
+    }
+
+    /// Estimates the number of rows produced by a join of two inputs under the given conditions.
+    ///
+    /// Equalities between a column of each side are estimated by assuming containment of the
+    /// distinct values: every value of the side with fewer distinct values has a match on the
+    /// other side. Equalities between groups of correlated columns are estimated together, as if
+    /// they were a single equality between composite values.
+    pub fn join_row_count(
+        &self,
+        join_type: JoinType,
+        left: &LogicalProperties,
+        right: &LogicalProperties,
+        conditions: &[Arc<ScalarExpression>],
+    ) -> f64 {
+        let mut join_selectivities = vec![];
+        let mut semi_selectivities = vec![];
+        let mut pairs = vec![];
+
+        for condition in conditions {
+            let sides = condition.as_column_equality().and_then(|(a, b)| {
+                match (
+                    left.contains(a),
+                    right.contains(b),
+                    left.contains(b),
+                    right.contains(a),
+                ) {
+                    (true, true, _, _) => Some((a, b)),
+                    (_, _, true, true) => Some((b, a)),
+                    _ => None,
+                }
+            });
+
+            match sides {
+                Some(pair) => pairs.push(pair),
+                None => {
+                    let selectivity = self.selectivity(condition);
+                    join_selectivities.push(selectivity);
+                    semi_selectivities.push(selectivity);
+                }
+            }
+        }
+
+        while let Some(matched) = self.correlated_pairs(&pairs) {
+            pairs.retain(|pair| !matched.contains(pair));
+            let (left_columns, right_columns): (ColumnSet, ColumnSet) = matched.into_iter().unzip();
+
+            let left_distinct = self.combined_distinct_count(&left_columns, left.row_count);
+            let right_distinct = self.combined_distinct_count(&right_columns, right.row_count);
+            join_selectivities.push(1.0 / left_distinct.max(right_distinct));
+            semi_selectivities.push((right_distinct / left_distinct).min(1.0));
+        }
+
+        for (left_column, right_column) in pairs {
+            let left_distinct = self.distinct_count(left_column, left.row_count);
+            let right_distinct = self.distinct_count(right_column, right.row_count);
+            join_selectivities.push(1.0 / left_distinct.max(right_distinct));
+            semi_selectivities.push((right_distinct / left_distinct).min(1.0));
+        }
+
+        let inner = left.row_count * right.row_count * self.combine(join_selectivities);
+        let semi = if right.row_count > 0.0 {
+            left.row_count * self.combine(semi_selectivities)
+        } else {
+            0.0
+        };
+
+        match join_type {
+            JoinType::Inner => inner,
+            JoinType::LeftOuter => inner.max(left.row_count),
+            JoinType::RightOuter => inner.max(right.row_count),
+            JoinType::FullOuter => inner.max(left.row_count) + inner.max(right.row_count) - inner,
+            JoinType::LeftSemi => semi,
+            JoinType::LeftAnti => left.row_count - semi,
+        }
+    }
+
+    /// Estimates the fraction of rows that satisfy all of the given predicates.
+    pub fn conjunction_selectivity(&self, predicates: &[Arc<ScalarExpression>]) -> f64 {
+        // Range predicates on the same column are estimated together, since treating the bounds
+        // of `x > 5 AND x < 10` as independent would badly overestimate them.
+        let mut ranges: BTreeMap<ColumnId, Range> = BTreeMap::new();
+        // Equalities with constants are estimated together, so that the statistics of correlated
+        // columns can be used.
+        let mut equalities: BTreeMap<ColumnId, &Value> = BTreeMap::new();
+        let mut selectivities = vec![];
+
+        for predicate in predicates {
+            match predicate.as_column_comparison() {
+                Some((column, CompareOperator::Eq, value))
+                    if !value.is_null() && !equalities.contains_key(&column) =>
+                {
+                    equalities.insert(column, value);
+                }
+                Some((column, op @ (CompareOperator::Gt | CompareOperator::GtEq), value)) => {
+                    let range = ranges.entry(column).or_default();
+                    if range.lower.is_some() {
+                        selectivities.push(self.selectivity(predicate));
+                    } else {
+                        range.lower = Some((op, value));
+                    }
+                }
+                Some((column, op @ (CompareOperator::Lt | CompareOperator::LtEq), value)) => {
+                    let range = ranges.entry(column).or_default();
+                    if range.upper.is_some() {
+                        selectivities.push(self.selectivity(predicate));
+                    } else {
+                        range.upper = Some((op, value));
+                    }
+                }
+                _ => selectivities.push(self.selectivity(predicate)),
+            }
+        }
+
+        for (column, range) in ranges {
+            selectivities.push(self.range_selectivity(column, range));
+        }
+        selectivities.extend(self.equality_selectivities(equalities));
+
+        self.combine(selectivities)
+    }
+
+    /// Estimates the fraction of rows that satisfy a predicate.
+    pub fn selectivity(&self, predicate: &ScalarExpression) -> f64 {
+        let selectivity = match predicate {
+            ScalarExpression::Literal(Value::Boolean(value)) => *value as u8 as f64,
+            ScalarExpression::Literal(_) => 0.0,
+            ScalarExpression::Column(column) => {
+                let true_fraction = self
+                    .statistics(*column)
+                    .and_then(|statistics| statistics.most_common_frequency(&Value::Boolean(true)));
+                true_fraction.unwrap_or(0.5)
+            }
+            ScalarExpression::Compare { op, .. } => {
+                if let Some((column, op, value)) = predicate.as_column_comparison() {
+                    self.comparison_selectivity(column, op, value)
+                } else if let Some((left, right)) = predicate.as_column_equality() {
+                    let distinct = self
+                        .distinct_count(left, f64::INFINITY)
+                        .max(self.distinct_count(right, f64::INFINITY));
+                    1.0 / distinct
+                } else if *op == CompareOperator::Eq {
+                    DEFAULT_EQUALITY_SELECTIVITY
+                } else {
+                    DEFAULT_RANGE_SELECTIVITY
+                }
+            }
+            ScalarExpression::And(children) => self.conjunction_selectivity(children),
+            ScalarExpression::Or(children) => {
+                1.0 - children
+                    .iter()
+                    .map(|child| 1.0 - self.selectivity(child))
+                    .product::<f64>()
+            }
+            ScalarExpression::Not(child) => 1.0 - self.selectivity(child),
+            ScalarExpression::IsNull(child) => self.null_fraction(child),
+            ScalarExpression::IsNotNull(child) => 1.0 - self.null_fraction(child),
+            ScalarExpression::InList {
+                expr,
+                list,
+                negated,
+            } => {
+                let null_fraction = self.null_fraction(expr);
+                let matched = match expr.as_ref() {
+                    ScalarExpression::Column(column) => list
+                        .iter()
+                        .map(|item| match item.as_ref() {
+                            ScalarExpression::Literal(value) => {
+                                self.comparison_selectivity(*column, CompareOperator::Eq, value)
+                            }
+                            _ => DEFAULT_EQUALITY_SELECTIVITY,
+                        })
+                        .sum::<f64>(),
+                    _ => DEFAULT_EQUALITY_SELECTIVITY * list.len() as f64,
+                };
+                let matched = matched.min(1.0 - null_fraction);
+
+                if *negated {
+                    1.0 - null_fraction - matched
+                } else {
+                    matched
+                }
+            }
+            ScalarExpression::Like {
+                expr,
+                pattern,
+                negated,
+            } => {
+                let matched = match expr.as_ref() {
+                    ScalarExpression::Column(column) => self.like_selectivity(*column, pattern),
+                    _ => DEFAULT_LIKE_SELECTIVITY,
+                };
+
+                if *negated {
+                    1.0 - self.null_fraction(expr) - matched
+                } else {
+                    matched
+                }
+            }
+        };
+
+        selectivity.clamp(0.0, 1.0)
+    }
+
+    /// Combines the selectivities of independent (or correlated) predicates.
+    fn combine(&self, mut selectivities: Vec<f64>) -> f64 {
+        match self.options.correlation {
+            Correlation::Independent => selectivities.iter().product(),
+            Correlation::ExponentialBackoff => {
+                selectivities.sort_by(f64::total_cmp);
+                selectivities
+                    .iter()
+                    .take(4)
+                    .zip([1.0, 0.5, 0.25, 0.125])
+                    .map(|(selectivity, exponent)| selectivity.powf(exponent))
+                    .product()
+            }
+        }
+    }
+
+    fn statistics(&self, column: ColumnId) -> Option<&ColumnStatistics> {
+        self.catalog.column_statistics(column)
+    }
+
+    /// Returns the statistics of all of the column groups of the scans of the given columns, over
+    /// the columns of those scans.
+    fn column_groups(
+        &self,
+        columns: impl IntoIterator<Item = ColumnId>,
+    ) -> Vec<ColumnGroupStatistics> {
+        // Every column of a scan has the same instance of the first column of the table.
+        let scans: BTreeSet<(usize, ColumnId)> = columns
+            .into_iter()
+            .filter_map(|column| {
+                let table = self.catalog.table(self.catalog.table_of(column)?)?;
+                let first = self.catalog.scan_column(column, table.columns.first()?.id);
+                Some((table.id, first))
+            })
+            .collect();
+
+        scans
+            .into_iter()
+            .flat_map(|(table, first)| {
+                self.catalog.column_groups(table).iter().map(move |group| {
+                    group.rename_columns(|column| self.catalog.scan_column(first, column))
+                })
+            })
+            .collect()
+    }
+
+    /// Estimates the selectivities of a set of `column = constant` predicates.
+    ///
+    /// Column groups whose columns are all constrained are used first (the largest ones first),
+    /// using their most common combinations of values or their distinct count. The remaining
+    /// columns are estimated one by one, where a column that functionally depends on other
+    /// constrained columns has its selectivity dampened by the degree of the dependency.
+    fn equality_selectivities(&self, mut equalities: BTreeMap<ColumnId, &Value>) -> Vec<f64> {
+        let groups = self.column_groups(equalities.keys().copied());
+        let mut selectivities = vec![];
+
+        loop {
+            let best = groups
+                .iter()
+                .filter(|group| {
+                    group.columns.len() >= 2
+                        && group
+                            .columns
+                            .iter()
+                            .all(|column| equalities.contains_key(column))
+                })
+                .filter_map(|group| {
+                    let values: Vec<Value> = group
+                        .columns
+                        .iter()
+                        .map(|column| equalities[column].clone())
+                        .collect();
+                    Some((group, self.group_equality_selectivity(group, &values)?))
+                })
+                .max_by_key(|(group, _)| group.columns.len());
+
+            let Some((group, selectivity)) = best else {
+                break;
+            };
+
+            // The columns are correlated, but the combination can never be more common than any of
+            // the individual values.
+            let bound = group
+                .columns
+                .iter()
+                .map(|column| self.equality_selectivity(*column, equalities[column]))
+                .fold(1.0, f64::min);
+            selectivities.push(selectivity.min(bound));
+
+            for column in &group.columns {
+                equalities.remove(column);
+            }
+        }
+
+        let mut individual: BTreeMap<ColumnId, f64> = equalities
+            .iter()
+            .map(|(&column, value)| (column, self.equality_selectivity(column, value)))
+            .collect();
+
+        let mut dependencies: Vec<&FunctionalDependency> = groups
+            .iter()
+            .flat_map(|group| &group.dependencies)
+            .filter(|dependency| {
+                dependency
+                    .determinant
+                    .iter()
+                    .chain([&dependency.dependent])
+                    .all(|column| equalities.contains_key(column))
+            })
+            .collect();
+        dependencies.sort_by(|left, right| right.degree.total_cmp(&left.degree));
+
+        // Like PostgreSQL, `P(a = 1 AND b = 2)` is `P(a = 1) * (f + (1 - f) * P(b = 2))` when `b`
+        // depends on `a` with degree `f`. A column is only dampened once, and never after it was
+        // used to dampen another column, so that cyclic dependencies cannot cancel each other out.
+        let mut dependents = BTreeSet::new();
+        let mut determinants = BTreeSet::new();
+        for dependency in dependencies {
+            if dependents.contains(&dependency.dependent)
+                || determinants.contains(&dependency.dependent)
+                || dependency
+                    .determinant
+                    .iter()
+                    .any(|column| dependents.contains(column))
+            {
+                continue;
+            }
+
+            let degree = dependency.degree.clamp(0.0, 1.0);
+            let selectivity = individual
+                .get_mut(&dependency.dependent)
+                .expect("the dependent column is constrained");
+            *selectivity = degree + (1.0 - degree) * *selectivity;
+
+            dependents.insert(dependency.dependent);
+            determinants.extend(dependency.determinant.iter().copied());
+        }
+
+        selectivities.extend(individual.into_values());
+        selectivities
+    }
+
+    /// Estimates the fraction of rows where the columns of a group have the given values, if the
+    /// group has enough statistics to do so.
+    fn group_equality_selectivity(
+        &self,
+        group: &ColumnGroupStatistics,
+        values: &[Value],
+    ) -> Option<f64> {
+        if let Some(frequency) = group.most_common_frequency(values) {
+            return Some(frequency);
+        }
+
+        // The remaining rows are spread evenly over the remaining combinations.
+        let distinct_count = group.distinct_count?;
+        let null_fraction = group
+            .columns
+            .iter()
+            .map(|column| self.null_fraction(&ScalarExpression::Column(*column)))
+            .fold(0.0, f64::max);
+        let remaining_rows = 1.0 - null_fraction - group.most_common_total();
+        let remaining_values = distinct_count - group.most_common_values.len() as f64;
+        Some((remaining_rows / remaining_values.max(1.0)).max(0.0))
+    }
+
+    /// Finds the largest set of join equalities whose columns on one side are covered by a
+    /// column group that knows their combined distinct count.
+    fn correlated_pairs(
+        &self,
+        pairs: &[(ColumnId, ColumnId)],
+    ) -> Option<Vec<(ColumnId, ColumnId)>> {
+        let mut candidates = vec![];
+
+        for left_side in [true, false] {
+            let side = |&(left, right): &(ColumnId, ColumnId)| if left_side { left } else { right };
+
+            for group in self.column_groups(pairs.iter().map(side)) {
+                let matched: Vec<(ColumnId, ColumnId)> = pairs
+                    .iter()
+                    .copied()
+                    .filter(|pair| group.columns.contains(&side(pair)))
+                    .collect();
+                let columns: ColumnSet = matched.iter().map(side).collect();
+
+                if columns.len() >= 2 && self.group_distinct_count(&columns).is_some() {
+                    candidates.push(matched);
+                }
+            }
+        }
+
+        candidates.into_iter().max_by_key(|matched| matched.len())
+    }
+
+    /// The number of distinct combinations of values of a set of columns of the same table, if a
+    /// column group knows it (or can derive it from a functional dependency).
+    fn group_distinct_count(&self, columns: &ColumnSet) -> Option<f64> {
+        let groups = self.column_groups(columns.iter().copied());
+
+        let declared = groups
+            .iter()
+            .filter(|group| {
+                group.columns.len() == columns.len()
+                    && group.columns.iter().all(|column| columns.contains(column))
+            })
+            .find_map(|group| group.distinct_count);
+        if declared.is_some() {
+            return declared;
+        }
+
+        // Every combination of the determinant comes with `d^(1 - f)` values of the dependent
+        // column on average, where `d` is its distinct count and `f` the degree of the dependency.
+        groups
+            .iter()
+            .flat_map(|group| &group.dependencies)
+            .filter(|dependency| {
+                dependency.determinant.len() + 1 == columns.len()
+                    && columns.contains(&dependency.dependent)
+                    && dependency
+                        .determinant
+                        .iter()
+                        .all(|column| columns.contains(column))
+            })
+            .max_by(|left, right| left.degree.total_cmp(&right.degree))
+            .map(|dependency| {
+                let determinant: ColumnSet = dependency.determinant.iter().copied().collect();
+                let degree = dependency.degree.clamp(0.0, 1.0);
+                self.combined_distinct_count(&determinant, f64::INFINITY)
+                    * self
+                        .distinct_count(dependency.dependent, f64::INFINITY)
+                        .powf(1.0 - degree)
+            })
+    }
+
+    /// The number of distinct combinations of values of a set of columns in an input with `rows`
+    /// rows, assuming independence unless a column group says otherwise.
+    fn combined_distinct_count(&self, columns: &ColumnSet, rows: f64) -> f64 {
+        if let [column] = columns.iter().copied().collect::<Vec<_>>()[..] {
+            return self.distinct_count(column, rows);
+        }
+
+        let distinct = self.group_distinct_count(columns).unwrap_or_else(|| {
+            columns
+                .iter()
+                .map(|&column| self.distinct_count(column, f64::INFINITY))
+                .product()
+        });
+
+        distinct.min(rows).max(1.0)
+    }
+
+    /// The fraction of rows where the given expression is `NULL`.
+    fn null_fraction(&self, expr: &ScalarExpression) -> f64 {
+        let ScalarExpression::Column(column) = expr else {
+            return DEFAULT_NULL_FRACTION;
+        };
+
+        if let Some(statistics) = self.statistics(*column) {
+            return statistics.null_fraction;
+        }
+
+        match self.catalog.column(*column) {
+            Some(column) if column.nullable => DEFAULT_NULL_FRACTION,
+            _ => 0.0,
+        }
+    }
+
+    /// The number of distinct values of a column in an input with `rows` rows.
+    pub fn distinct_count(&self, column: ColumnId, rows: f64) -> f64 {
+        let distinct = match self.statistics(column) {
+            Some(statistics) => statistics.distinct_count,
+            None => {
+                let column = self.catalog.table_column(column);
+                let table = self
+                    .catalog
+                    .table_of(column)
+                    .and_then(|table| self.catalog.table(table));
+                match table {
+                    // A column that is unique by itself has a distinct value per row.
+                    Some(table) if table.unique_keys.contains(&vec![column]) => {
+                        table.estimated_row_count()
+                    }
+                    _ => 1.0 / DEFAULT_EQUALITY_SELECTIVITY,
+                }
+            }
+        };
+
+        distinct.min(rows).max(1.0)
+    }
+
+    fn comparison_selectivity(&self, column: ColumnId, op: CompareOperator, value: &Value) -> f64 {
+        if value.is_null() {
+            // Comparisons with `NULL` are never true.
+            return 0.0;
+        }
+
+        let null_fraction = self.null_fraction(&ScalarExpression::Column(column));
+        match op {
+            CompareOperator::Eq => self.equality_selectivity(column, value),
+            CompareOperator::NotEq => {
+                1.0 - null_fraction - self.equality_selectivity(column, value)
+            }
+            CompareOperator::Lt | CompareOperator::LtEq => self.range_selectivity(
+                column,
+                Range {
+                    lower: None,
+                    upper: Some((op, value)),
+                },
+            ),
+            CompareOperator::Gt | CompareOperator::GtEq => self.range_selectivity(
+                column,
+                Range {
+                    lower: Some((op, value)),
+                    upper: None,
+                },
+            ),
+        }
+    }
+
+    fn equality_selectivity(&self, column: ColumnId, value: &Value) -> f64 {
+        let Some(statistics) = self.statistics(column) else {
+            return 1.0 / self.distinct_count(column, f64::INFINITY);
+        };
+
+        let out_of_bounds = statistics.min.as_ref().is_some_and(|min| value < min)
+            || statistics.max.as_ref().is_some_and(|max| value > max);
+        if out_of_bounds {
+            return 0.0;
+        }
+
+        if let Some(frequency) = statistics.most_common_frequency(value) {
+            return frequency;
+        }
+
+        // The remaining rows are spread evenly over the remaining distinct values.
+        let remaining_rows = 1.0 - statistics.null_fraction - statistics.most_common_total();
+        let remaining_values =
+            statistics.distinct_count - statistics.most_common_values.len() as f64;
+        (remaining_rows / remaining_values.max(1.0)).max(0.0)
+    }
+
+    /// Estimates the fraction of rows of a column that fall within a (possibly half-open) range.
+    fn range_selectivity(&self, column: ColumnId, range: Range) -> f64 {
+        let Some(statistics) = self.statistics(column) else {
+            return match (range.lower, range.upper) {
+                (Some(_), Some(_)) => DEFAULT_RANGE_SELECTIVITY * DEFAULT_RANGE_SELECTIVITY,
+                (None, None) => 1.0,
+                _ => DEFAULT_RANGE_SELECTIVITY,
+            };
+        };
+
+        let non_null = 1.0 - statistics.null_fraction;
+
+        // The fraction of non-null rows below a value.
+        let below = |value: &Value, inclusive: bool| -> f64 {
+            if let Some(histogram) = &statistics.histogram {
+                return histogram.fraction_below(value, inclusive);
+            }
+
+            let bounds = statistics
+                .min
+                .as_ref()
+                .and_then(Value::as_f64)
+                .zip(statistics.max.as_ref().and_then(Value::as_f64));
+            match (bounds, value.as_f64()) {
+                (Some((min, max)), Some(value)) if max > min => {
+                    ((value - min) / (max - min)).clamp(0.0, 1.0)
+                }
+                _ => 0.5,
+            }
+        };
+
+        let upper = range
+            .upper
+            .map_or(1.0, |(op, value)| below(value, op == CompareOperator::LtEq));
+        let lower = range
+            .lower
+            .map_or(0.0, |(op, value)| below(value, op == CompareOperator::Gt));
+
+        ((upper - lower) * non_null).max(0.0)
+    }
+
+    /// Estimates the selectivity of a `LIKE` pattern by treating its fixed prefix as a range.
+    fn like_selectivity(&self, column: ColumnId, pattern: &str) -> f64 {
+        let prefix: String = pattern
+            .chars()
+            .take_while(|&c| c != '%' && c != '_')
+            .collect();
+        let prefix_len = prefix.len();
+
+        if prefix.len() == pattern.len() {
+            // There are no wildcards, so this is just an equality.
+            return self.comparison_selectivity(column, CompareOperator::Eq, &Value::from(pattern));
+        }
+
+        if prefix.is_empty() {
+            return DEFAULT_LIKE_SELECTIVITY;
+        }
+
+        // Every string with the prefix is at least the prefix and less than the prefix with its
+        // last character incremented.
+        let mut end: Vec<char> = prefix.chars().collect();
+        let last = end.pop().unwrap();
+        let Some(next) = char::from_u32(last as u32 + 1) else {
+            return DEFAULT_LIKE_SELECTIVITY;
+        };
+        end.push(next);
+        let end: String = end.into_iter().collect();
+
+        let start = Value::Utf8(prefix);
+        let end = Value::Utf8(end);
+        let selectivity = self.range_selectivity(
+            column,
+            Range {
+                lower: Some((CompareOperator::GtEq, &start)),
+                upper: Some((CompareOperator::Lt, &end)),
+            },
+        );
+
+        // A pattern with more than a single trailing `%` after the prefix is even more selective.
+        if pattern.len() > prefix_len + 1 {
+            selectivity * DEFAULT_LIKE_SELECTIVITY.sqrt()
+        } else {
+            selectivity
+        }
+    }
+}
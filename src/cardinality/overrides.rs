@@ -0,0 +1,62 @@
+//! Pinned cardinalities, for deterministic optimizer tests.
+//!
+//! Tests that check cost-based decisions (like join orders) should not depend on the details of
+//! the statistics and the estimator. Instead, they can pin the row counts of specific groups with
+//! [`CardinalityOverrides`] before the groups are created:
+//!
+//! ```ignore
+//! let mut overrides = CardinalityOverrides::default();
+//! overrides.set_tables([orders, customers], 10.0);
+//!
+//! let memo = Memo::with_estimator(Estimator::new(catalog).with_overrides(overrides));
+//! ```
+
+use crate::properties::LogicalProperties;
+use crate::Expression;
+use std::collections::{BTreeSet, HashMap};
+
+/// Row counts that replace the estimates of the groups they match.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CardinalityOverrides {
+    /// Row counts keyed by the set of tables that a group reads from.
+    tables: HashMap<BTreeSet<usize>, f64>,
+    /// Row counts keyed by the structural fingerprint of an expression.
+    expressions: HashMap<u64, f64>,
+}
+
+impl CardinalityOverrides {
+    /// Pins the row count of every group that reads from exactly the given set of tables.
+    ///
+    /// Note that this includes all of the groups over those tables, such as both a scan of a table
+    /// and a filter over that scan. Use [`CardinalityOverrides::set_expression`] to pin a single
+    /// sub-expression instead.
+    pub fn set_tables(&mut self, tables: impl IntoIterator<Item = usize>, row_count: f64) {
+        self.tables.insert(tables.into_iter().collect(), row_count);
+    }
+
+    /// Pins the row count of the group created for the given expression.
+    ///
+    /// The override matches by [`Expression::structural_fingerprint`], so it only applies if the
+    /// group is created from an expression with exactly this shape. It takes precedence over any
+    /// table set override.
+    pub fn set_expression(&mut self, expr: &Expression, row_count: f64) {
+        self.expressions
+            .insert(expr.structural_fingerprint(), row_count);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty() && self.expressions.is_empty()
+    }
+
+    /// Returns the pinned row count of a new group created from `expr`, if there is one.
+    pub fn row_count(&self, expr: &Expression, properties: &LogicalProperties) -> Option<f64> {
+        if self.is_empty() {
+            return None;
+        }
+
+        self.expressions
+            .get(&expr.structural_fingerprint())
+            .or_else(|| self.tables.get(&properties.tables))
+            .copied()
+    }
+}
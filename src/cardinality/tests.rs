@@ -0,0 +1,519 @@
+use crate::catalog::{Catalog, ColumnId, DataType};
+use crate::cost::default::DefaultCostModel;
+use crate::engine::SearchEngine;
+use crate::expression::physical::PhysicalExpression;
+use crate::expression::scalar::CompareOperator;
+use crate::statistics::{
+    ColumnGroupStatistics, ColumnStatistics, FunctionalDependency, Histogram, TableStatistics,
+};
+use crate::{Expression, Join, LogicalExpression, Memo, Relation, Scan};
+use std::collections::{BTreeSet, HashMap};
+
+use super::*;
+
+const PEOPLE: usize = 0;
+const CITIES: usize = 1;
+
+const AGE: ColumnId = ColumnId(0);
+const CITY: ColumnId = ColumnId(1);
+const CITY_ID: ColumnId = ColumnId(2);
+const ID: ColumnId = ColumnId(3);
+
+/// Creates a catalog with 10,000 `people` (with statistics) and 5,000 `cities`.
+fn catalog() -> Catalog {
+    let mut catalog = Catalog::new();
+    catalog.add_table(
+        "people",
+        &[
+            ("age", DataType::Int64, false),
+            ("city", DataType::Utf8, true),
+            ("city_id", DataType::Int64, false),
+        ],
+        10_000,
+    );
+    catalog.add_table("cities", &[("id", DataType::Int64, false)], 5_000);
+    catalog.add_unique_key(CITIES, vec![ID]);
+
+    let age = ColumnStatistics {
+        null_fraction: 0.0,
+        distinct_count: 100.0,
+        most_common_values: vec![(Value::Int64(30), 0.05)],
+        histogram: Some(Histogram::new(
+            [0, 25, 50, 75, 100].into_iter().map(Value::Int64).collect(),
+            vec![2500.0; 4],
+            vec![25.0; 4],
+        )),
+        min: Some(Value::Int64(0)),
+        max: Some(Value::Int64(100)),
+        average_width: 8.0,
+    };
+    let city = ColumnStatistics {
+        null_fraction: 0.1,
+        distinct_count: 50.0,
+        most_common_values: vec![(Value::from("Pittsburgh"), 0.2)],
+        histogram: Some(Histogram::new(
+            ["A", "M", "Z"].into_iter().map(Value::from).collect(),
+            vec![4500.0; 2],
+            vec![25.0; 2],
+        )),
+        min: Some(Value::from("A")),
+        max: Some(Value::from("Z")),
+        average_width: 10.0,
+    };
+    let city_id = ColumnStatistics {
+        distinct_count: 5000.0,
+        average_width: 8.0,
+        ..ColumnStatistics::default()
+    };
+
+    catalog.set_statistics(
+        PEOPLE,
+        TableStatistics {
+            row_count: 10_000.0,
+            columns: HashMap::from([(AGE, age), (CITY, city), (CITY_ID, city_id)]),
+            column_groups: vec![],
+        },
+    );
+
+    catalog
+}
+
+fn compare(
+    column: ColumnId,
+    op: CompareOperator,
+    value: impl Into<Value>,
+) -> Arc<ScalarExpression> {
+    ScalarExpression::compare(
+        op,
+        ScalarExpression::column(column),
+        ScalarExpression::literal(value),
+    )
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-6,
+        "expected {expected}, got {actual}"
+    );
+}
+
+#[test]
+fn comparison_selectivities() {
+    let estimator = Estimator::new(Arc::new(catalog()));
+
+    // Most common values are exact, and everything else is spread over the remaining values.
+    assert_close(
+        estimator.selectivity(&compare(AGE, CompareOperator::Eq, 30)),
+        0.05,
+    );
+    assert_close(
+        estimator.selectivity(&compare(AGE, CompareOperator::Eq, 40)),
+        0.95 / 99.0,
+    );
+    assert_close(
+        estimator.selectivity(&compare(AGE, CompareOperator::Eq, 500)),
+        0.0,
+    );
+    assert_close(
+        estimator.selectivity(&compare(AGE, CompareOperator::NotEq, 30)),
+        0.95,
+    );
+
+    // Ranges come from the histogram.
+    assert_close(
+        estimator.selectivity(&compare(AGE, CompareOperator::Lt, 50)),
+        0.49,
+    );
+    assert_close(
+        estimator.selectivity(&compare(AGE, CompareOperator::GtEq, 50)),
+        0.51,
+    );
+
+    // Literals on the left are handled by flipping the comparison.
+    let flipped = ScalarExpression::compare(
+        CompareOperator::Gt,
+        ScalarExpression::literal(50),
+        ScalarExpression::column(AGE),
+    );
+    assert_close(estimator.selectivity(&flipped), 0.49);
+}
+
+#[test]
+fn null_in_and_like_selectivities() {
+    let estimator = Estimator::new(Arc::new(catalog()));
+    let city = ScalarExpression::column(CITY);
+
+    assert_close(
+        estimator.selectivity(&ScalarExpression::IsNull(city.clone())),
+        0.1,
+    );
+    assert_close(
+        estimator.selectivity(&ScalarExpression::IsNotNull(city.clone())),
+        0.9,
+    );
+
+    let in_list = |negated| ScalarExpression::InList {
+        expr: city.clone(),
+        list: vec![
+            ScalarExpression::literal("Pittsburgh"),
+            ScalarExpression::literal("Boston"),
+        ],
+        negated,
+    };
+    let matched = 0.2 + 0.7 / 49.0;
+    assert_close(estimator.selectivity(&in_list(false)), matched);
+    assert_close(estimator.selectivity(&in_list(true)), 0.9 - matched);
+
+    // A `LIKE` without wildcards is an equality, and a prefix is a (narrow) range.
+    let like = |pattern: &str| ScalarExpression::Like {
+        expr: city.clone(),
+        pattern: pattern.to_string(),
+        negated: false,
+    };
+    assert_close(estimator.selectivity(&like("Pittsburgh")), 0.2);
+    let prefix = estimator.selectivity(&like("Pitt%"));
+    assert!(prefix > 0.0 && prefix < 0.1, "got {prefix}");
+    assert_close(
+        estimator.selectivity(&like("%burgh")),
+        DEFAULT_LIKE_SELECTIVITY,
+    );
+}
+
+#[test]
+fn boolean_combinations() {
+    let catalog = Arc::new(catalog());
+    let estimator = Estimator::new(catalog.clone());
+
+    let is_30 = compare(AGE, CompareOperator::Eq, 30);
+    let is_40 = compare(AGE, CompareOperator::Eq, 40);
+    let s_30 = 0.05;
+    let s_40 = 0.95 / 99.0;
+
+    assert_close(
+        estimator.selectivity(&ScalarExpression::Not(is_30.clone())),
+        1.0 - s_30,
+    );
+    assert_close(
+        estimator.selectivity(&ScalarExpression::Or(vec![is_30.clone(), is_40.clone()])),
+        1.0 - (1.0 - s_30) * (1.0 - s_40),
+    );
+
+    let both = ScalarExpression::And(vec![is_30, is_40]);
+    assert_close(estimator.selectivity(&both), s_30 * s_40);
+
+    // With exponential backoff, the less selective predicate only counts for its square root.
+    let backoff = Estimator::with_options(
+        catalog,
+        EstimatorOptions {
+            correlation: Correlation::ExponentialBackoff,
+        },
+    );
+    assert_close(backoff.selectivity(&both), s_40 * s_30.sqrt());
+}
+
+#[test]
+fn ranges_on_the_same_column_are_combined() {
+    let estimator = Estimator::new(Arc::new(catalog()));
+
+    let range = [
+        compare(AGE, CompareOperator::Gt, 25),
+        compare(AGE, CompareOperator::Lt, 50),
+    ];
+    let combined = estimator.conjunction_selectivity(&range);
+    let independent = estimator.selectivity(&range[0]) * estimator.selectivity(&range[1]);
+
+    assert!(
+        combined > 0.2 && combined < 0.25,
+        "expected roughly a quarter of the rows, got {combined}"
+    );
+    assert!(combined < independent);
+
+    // A contradiction selects nothing.
+    let contradiction = [
+        compare(AGE, CompareOperator::Gt, 50),
+        compare(AGE, CompareOperator::Lt, 30),
+    ];
+    assert_close(estimator.conjunction_selectivity(&contradiction), 0.0);
+}
+
+fn scan(
+    catalog: &Catalog,
+    table_id: usize,
+    filters: Vec<Arc<ScalarExpression>>,
+) -> Arc<Expression> {
+    Arc::new(Expression::Logical(LogicalExpression::Scan(Scan {
+        table_id,
+        columns: catalog.table(table_id).unwrap().column_ids(),
+        filters,
+    })))
+}
+
+/// Joins `people` (with the given filters) with `cities`, and returns the table that the hash
+/// join of the best plan builds its hash table from along with the row count of the join.
+fn optimize_join(people_filters: Vec<Arc<ScalarExpression>>) -> (usize, f64) {
+    let catalog = Arc::new(catalog());
+
+    let join = Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+        join_type: JoinType::Inner,
+        conditions: vec![ScalarExpression::eq(
+            ScalarExpression::column(CITY_ID),
+            ScalarExpression::column(ID),
+        )],
+        left: scan(&catalog, PEOPLE, people_filters),
+        right: scan(&catalog, CITIES, vec![]),
+    })));
+
+    let memo = Arc::new(Memo::new(catalog.clone()));
+    let (root, _) = memo.add_expression(&join, None);
+
+    let engine = SearchEngine::new(memo, Arc::new(DefaultCostModel::new(catalog)));
+    let plan = engine
+        .optimize(root.clone())
+        .expect("the join should have a plan");
+
+    let Expression::Physical(PhysicalExpression::HashJoin(hash_join)) = plan.as_ref() else {
+        panic!("expected a hash join, got {plan:?}");
+    };
+    let Expression::Physical(PhysicalExpression::TableScan(build)) = hash_join.left.as_ref() else {
+        panic!("expected a table scan, got {:?}", hash_join.left);
+    };
+
+    (build.table_id, root.row_count())
+}
+
+#[test]
+fn join_estimates_drive_the_build_side() {
+    // Every person matches exactly one city.
+    let (build, rows) = optimize_join(vec![]);
+    assert_eq!(build, CITIES);
+    assert_close(rows, 10_000.0);
+
+    // Once people are filtered down to 500 rows, they become the smaller side.
+    let (build, rows) = optimize_join(vec![compare(AGE, CompareOperator::Eq, 30)]);
+    assert_eq!(build, PEOPLE);
+    assert_close(rows, 500.0);
+}
+
+/// Optimizes `(A JOIN B ON a = b) JOIN C ON b = c` with the given overrides, and returns the
+/// tables on each side of the top join of the best plan.
+fn join_order(overrides: CardinalityOverrides) -> BTreeSet<BTreeSet<usize>> {
+    let mut catalog = Catalog::new();
+    for name in ["a", "b", "c"] {
+        catalog.add_table(name, &[("id", DataType::Int64, false)], 1000);
+    }
+    let catalog = Arc::new(catalog);
+
+    let join = |left, right, (l, r): (usize, usize)| {
+        Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+            join_type: JoinType::Inner,
+            conditions: vec![ScalarExpression::eq(
+                ScalarExpression::column(ColumnId(l)),
+                ScalarExpression::column(ColumnId(r)),
+            )],
+            left,
+            right,
+        })))
+    };
+    let query = join(
+        join(scan(&catalog, 0, vec![]), scan(&catalog, 1, vec![]), (0, 1)),
+        scan(&catalog, 2, vec![]),
+        (1, 2),
+    );
+
+    let estimator = Estimator::new(catalog.clone()).with_overrides(overrides);
+    let memo = Arc::new(Memo::with_estimator(estimator));
+    let (root, _) = memo.add_expression(&query, None);
+
+    let engine = SearchEngine::new(memo, Arc::new(DefaultCostModel::new(catalog)));
+    let plan = engine.optimize(root).expect("the query should have a plan");
+
+    plan.children().iter().map(scanned_tables).collect()
+}
+
+fn scanned_tables(plan: &Arc<Expression>) -> BTreeSet<usize> {
+    match plan.as_ref() {
+        Expression::Physical(PhysicalExpression::TableScan(scan)) => {
+            BTreeSet::from([scan.table_id])
+        }
+        _ => plan.children().iter().flat_map(scanned_tables).collect(),
+    }
+}
+
+#[test]
+fn overrides_pin_the_join_order() {
+    let sides = |sides: [&[usize]; 2]| -> BTreeSet<BTreeSet<usize>> {
+        sides
+            .into_iter()
+            .map(|side| side.iter().copied().collect())
+            .collect()
+    };
+
+    let mut overrides = CardinalityOverrides::default();
+    overrides.set_tables([0, 1], 10.0);
+    overrides.set_tables([1, 2], 1_000_000.0);
+    assert_eq!(join_order(overrides), sides([&[0, 1], &[2]]));
+
+    let mut overrides = CardinalityOverrides::default();
+    overrides.set_tables([0, 1], 1_000_000.0);
+    overrides.set_tables([1, 2], 10.0);
+    assert_eq!(join_order(overrides), sides([&[0], &[1, 2]]));
+}
+
+#[test]
+fn expression_overrides_take_precedence() {
+    let catalog = Arc::new(catalog());
+    let filtered = scan(
+        &catalog,
+        PEOPLE,
+        vec![compare(AGE, CompareOperator::Eq, 30)],
+    );
+    let unfiltered = scan(&catalog, PEOPLE, vec![]);
+
+    let mut overrides = CardinalityOverrides::default();
+    overrides.set_tables([PEOPLE], 42.0);
+    overrides.set_expression(&filtered, 7.0);
+
+    let memo = Memo::with_estimator(Estimator::new(catalog).with_overrides(overrides));
+    assert_eq!(memo.add_expression(&filtered, None).0.row_count(), 7.0);
+    assert_eq!(memo.add_expression(&unfiltered, None).0.row_count(), 42.0);
+}
+
+/// Creates two tables of 100,000 `addresses` with a city (100 distinct values) and a zip code
+/// (10,000 distinct values, each in a single city), with the given column groups on both.
+fn addresses(column_groups: impl Fn(ColumnId, ColumnId) -> Vec<ColumnGroupStatistics>) -> Catalog {
+    let mut catalog = Catalog::new();
+    for name in ["shipping", "billing"] {
+        let table = catalog.add_table(
+            name,
+            &[
+                ("city", DataType::Utf8, false),
+                ("zip", DataType::Int64, false),
+            ],
+            100_000,
+        );
+        let [city, zip] = catalog.table(table).unwrap().column_ids()[..] else {
+            unreachable!()
+        };
+
+        let distinct = |distinct_count| ColumnStatistics {
+            distinct_count,
+            average_width: 8.0,
+            ..ColumnStatistics::default()
+        };
+        catalog.set_statistics(
+            table,
+            TableStatistics {
+                row_count: 100_000.0,
+                columns: HashMap::from([(city, distinct(100.0)), (zip, distinct(10_000.0))]),
+                column_groups: column_groups(city, zip),
+            },
+        );
+    }
+    catalog
+}
+
+#[test]
+fn correlated_columns_use_group_statistics() {
+    let (city, zip) = (ColumnId(0), ColumnId(1));
+    let filters = [
+        compare(city, CompareOperator::Eq, "Pittsburgh"),
+        compare(zip, CompareOperator::Eq, 15213),
+    ];
+    let filter_rows =
+        |catalog: Catalog| Estimator::new(Arc::new(catalog)).filter_row_count(100_000.0, &filters);
+
+    // Without column groups, the predicates are assumed to be independent.
+    assert_close(filter_rows(addresses(|_, _| vec![])), 0.1);
+
+    // The distinct count of the group spreads the rows over the combinations that actually exist.
+    let with_distinct = |city, zip| {
+        vec![ColumnGroupStatistics {
+            columns: vec![city, zip],
+            distinct_count: Some(10_000.0),
+            ..ColumnGroupStatistics::default()
+        }]
+    };
+    assert_close(filter_rows(addresses(with_distinct)), 10.0);
+
+    // The most common combinations are exact.
+    let with_common = |city, zip| {
+        vec![ColumnGroupStatistics {
+            columns: vec![zip, city],
+            most_common_values: vec![(
+                vec![Value::Int64(15213), Value::from("Pittsburgh")],
+                0.00005,
+            )],
+            ..ColumnGroupStatistics::default()
+        }]
+    };
+    assert_close(filter_rows(addresses(with_common)), 5.0);
+
+    // A zip code determines the city, so the city predicate does not filter anything more.
+    let with_dependency = |city, zip| {
+        vec![ColumnGroupStatistics {
+            columns: vec![city, zip],
+            dependencies: vec![FunctionalDependency {
+                determinant: vec![zip],
+                dependent: city,
+                degree: 1.0,
+            }],
+            ..ColumnGroupStatistics::default()
+        }]
+    };
+    assert_close(filter_rows(addresses(with_dependency)), 10.0);
+
+    // The same statistics fix joins on both columns, which would otherwise be estimated as if
+    // there were 1,000,000 distinct addresses.
+    let join_rows = |catalog: Catalog| {
+        let catalog = Arc::new(catalog);
+        let memo = Memo::new(catalog.clone());
+        let shipping = memo.add_expression(&scan(&catalog, 0, vec![]), None).0;
+        let billing = memo.add_expression(&scan(&catalog, 1, vec![]), None).0;
+
+        let conditions = [(0, 2), (1, 3)].map(|(left, right)| {
+            ScalarExpression::eq(
+                ScalarExpression::column(ColumnId(left)),
+                ScalarExpression::column(ColumnId(right)),
+            )
+        });
+        memo.estimator().join_row_count(
+            JoinType::Inner,
+            shipping.logical_properties(),
+            billing.logical_properties(),
+            &conditions,
+        )
+    };
+    assert_close(join_rows(addresses(|_, _| vec![])), 10_000.0);
+    assert_close(join_rows(addresses(with_distinct)), 1_000_000.0);
+    assert_close(join_rows(addresses(with_dependency)), 1_000_000.0);
+}
+
+#[test]
+fn scans_use_the_statistics_of_their_table() {
+    let with_distinct = |city, zip| {
+        vec![ColumnGroupStatistics {
+            columns: vec![city, zip],
+            distinct_count: Some(10_000.0),
+            ..ColumnGroupStatistics::default()
+        }]
+    };
+    let mut catalog = addresses(with_distinct);
+    let [city, zip] = catalog.add_scan(0)[..] else {
+        unreachable!()
+    };
+    let estimator = Estimator::new(Arc::new(catalog));
+
+    // The columns of another scan of `shipping` have the statistics of the columns of the table.
+    let filters = [
+        compare(city, CompareOperator::Eq, "Pittsburgh"),
+        compare(zip, CompareOperator::Eq, 15213),
+    ];
+    assert_close(estimator.filter_row_count(100_000.0, &filters), 10.0);
+
+    // The city of one scan and the zip code of the other are not correlated.
+    let filters = [
+        compare(ColumnId(0), CompareOperator::Eq, "Pittsburgh"),
+        compare(zip, CompareOperator::Eq, 15213),
+    ];
+    assert_close(estimator.filter_row_count(100_000.0, &filters), 0.1);
+}
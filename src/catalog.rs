@@ -0,0 +1,313 @@
+//! The catalog of tables and columns that the optimizer plans over.
+//!
+//! TODO:
+//! The catalog should eventually be backed by the persistent storage described in `GOALS.md`
+//! (Databases / Tables / Schemas / Attributes). For now it is a simple in-memory registry that is
+//! filled in before optimization begins and is read-only afterwards.
+
+use crate::statistics::{ColumnGroupStatistics, ColumnStatistics, TableStatistics};
+use std::collections::HashMap;
+
+/// The globally unique identifier of a column.
+///
+/// Column identifiers are unique across the entire catalog (not just within a table), so an
+/// expression can refer to a column without also having to say which table it came from. A scan
+/// of a table can get its own instances of the columns of the table (see [`Catalog::add_scan`]),
+/// so that the columns of two scans of the same table can be told apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ColumnId(pub usize);
+
+/// The data types that a column can have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DataType {
+    Boolean,
+    Int64,
+    Float64,
+    Utf8,
+}
+
+impl DataType {
+    /// The (average) width of a value of this type in bytes.
+    ///
+    /// For variable-width types this is only a guess, and statistics should be preferred.
+    pub fn width(&self) -> usize {
+        match self {
+            DataType::Boolean => 1,
+            DataType::Int64 => 8,
+            DataType::Float64 => 8,
+            DataType::Utf8 => 16,
+        }
+    }
+}
+
+/// A column of a table in the catalog.
+#[derive(Debug, Clone)]
+pub struct Column {
+    pub id: ColumnId,
+    pub name: String,
+    pub data_type: DataType,
+    pub nullable: bool,
+}
+
+/// A foreign key, which guarantees that every row of the referencing table whose key columns are all
+/// not `NULL` has exactly one matching row in the referenced table.
+#[derive(Debug, Clone)]
+pub struct ForeignKey {
+    /// The columns of the referencing table.
+    pub columns: Vec<ColumnId>,
+    pub referenced_table: usize,
+    /// The columns of the referenced table that the columns refer to, in the same order. They form
+    /// a unique key of the referenced table.
+    pub referenced_columns: Vec<ColumnId>,
+}
+
+/// A table in the catalog.
+#[derive(Debug, Clone)]
+pub struct Table {
+    pub id: usize,
+    pub name: String,
+    pub columns: Vec<Column>,
+    /// The number of rows in the table.
+    pub row_count: usize,
+    /// The sets of columns that are declared to be unique (primary keys and unique constraints).
+    pub unique_keys: Vec<Vec<ColumnId>>,
+    /// The foreign keys of this table that refer to other tables.
+    pub foreign_keys: Vec<ForeignKey>,
+    /// The statistics of the table and its columns, if any have been collected.
+    pub statistics: Option<TableStatistics>,
+}
+
+impl Table {
+    /// Returns the identifiers of all of the columns of this table, in order.
+    pub fn column_ids(&self) -> Vec<ColumnId> {
+        self.columns.iter().map(|column| column.id).collect()
+    }
+
+    /// The number of rows in the table, preferring the statistics over the declared row count.
+    pub fn estimated_row_count(&self) -> f64 {
+        self.statistics
+            .as_ref()
+            .map_or(self.row_count as f64, |statistics| statistics.row_count)
+    }
+
+    /// The estimated width of a column in bytes, preferring the statistics over the data type.
+    pub fn column_width(&self, column: &Column) -> f64 {
+        self.statistics
+            .as_ref()
+            .and_then(|statistics| statistics.column(column.id))
+            .map_or(column.data_type.width() as f64, |statistics| {
+                statistics.average_width
+            })
+    }
+
+    /// The estimated width of a row in bytes.
+    pub fn row_width(&self) -> f64 {
+        self.columns
+            .iter()
+            .map(|column| self.column_width(column))
+            .sum()
+    }
+}
+
+/// An in-memory catalog of tables.
+#[derive(Debug, Default)]
+pub struct Catalog {
+    tables: Vec<Table>,
+    /// Maps every column (including the columns of scans) to the table that owns it and its
+    /// position in that table.
+    columns: HashMap<ColumnId, (usize, usize)>,
+    /// The columns of every scan, in the order of the columns of the scanned table.
+    scans: Vec<Vec<ColumnId>>,
+    /// Maps every column of a scan to its position in `scans`.
+    scan_columns: HashMap<ColumnId, usize>,
+    /// The columns that are computed by the query (such as the results of aggregate functions)
+    /// rather than read from a table.
+    derived_columns: HashMap<ColumnId, Column>,
+    next_column_id: usize,
+}
+
+impl Catalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a new table with the given `(name, type, nullable)` columns and returns its table
+    /// identifier.
+    pub fn add_table(
+        &mut self,
+        name: &str,
+        columns: &[(&str, DataType, bool)],
+        row_count: usize,
+    ) -> usize {
+        let table_id = self.tables.len();
+
+        let columns = columns
+            .iter()
+            .enumerate()
+            .map(|(index, &(name, data_type, nullable))| {
+                let id = ColumnId(self.next_column_id);
+                self.next_column_id += 1;
+                self.columns.insert(id, (table_id, index));
+
+                Column {
+                    id,
+                    name: name.to_string(),
+                    data_type,
+                    nullable,
+                }
+            })
+            .collect();
+
+        self.tables.push(Table {
+            id: table_id,
+            name: name.to_string(),
+            columns,
+            row_count,
+            unique_keys: vec![],
+            foreign_keys: vec![],
+            statistics: None,
+        });
+
+        table_id
+    }
+
+    /// Registers a new scan of a table and returns the identifiers of its columns, which are
+    /// instances of the columns of the table (in the same order).
+    ///
+    /// The columns of the table itself can be used by a query that scans the table only once, but
+    /// every other scan of the table needs its own columns. Like tables, scans have to be
+    /// registered before optimization begins.
+    pub fn add_scan(&mut self, table_id: usize) -> Vec<ColumnId> {
+        let scan = self.scans.len();
+
+        let columns: Vec<ColumnId> = (0..self.tables[table_id].columns.len())
+            .map(|index| {
+                let id = ColumnId(self.next_column_id);
+                self.next_column_id += 1;
+                self.columns.insert(id, (table_id, index));
+                self.scan_columns.insert(id, scan);
+                id
+            })
+            .collect();
+
+        self.scans.push(columns.clone());
+        columns
+    }
+
+    /// Registers a column that does not belong to any table, such as the result of an aggregate
+    /// function, and returns its identifier.
+    ///
+    /// Like tables, derived columns have to be registered before optimization begins.
+    pub fn add_derived_column(
+        &mut self,
+        name: &str,
+        data_type: DataType,
+        nullable: bool,
+    ) -> ColumnId {
+        let id = ColumnId(self.next_column_id);
+        self.next_column_id += 1;
+        self.derived_columns.insert(
+            id,
+            Column {
+                id,
+                name: name.to_string(),
+                data_type,
+                nullable,
+            },
+        );
+        id
+    }
+
+    /// Declares that the given columns of a table are unique.
+    pub fn add_unique_key(&mut self, table_id: usize, key: Vec<ColumnId>) {
+        debug_assert!(key
+            .iter()
+            .all(|column| self.columns.get(column).map(|(table, _)| *table) == Some(table_id)));
+
+        self.tables[table_id].unique_keys.push(key);
+    }
+
+    /// Declares that the given columns of a table refer to a unique key of another table.
+    pub fn add_foreign_key(
+        &mut self,
+        table_id: usize,
+        columns: Vec<ColumnId>,
+        referenced_table: usize,
+        referenced_columns: Vec<ColumnId>,
+    ) {
+        debug_assert_eq!(columns.len(), referenced_columns.len());
+        debug_assert!(columns
+            .iter()
+            .all(|column| self.table_of(*column) == Some(table_id)));
+        debug_assert!(self.tables[referenced_table]
+            .unique_keys
+            .iter()
+            .any(|key| key.iter().all(|column| referenced_columns.contains(column))));
+
+        self.tables[table_id].foreign_keys.push(ForeignKey {
+            columns,
+            referenced_table,
+            referenced_columns,
+        });
+    }
+
+    /// Attaches (or replaces) the statistics of a table.
+    pub fn set_statistics(&mut self, table_id: usize, statistics: TableStatistics) {
+        self.tables[table_id].statistics = Some(statistics);
+    }
+
+    /// Returns the statistics of a column, if its table has any.
+    pub fn column_statistics(&self, column_id: ColumnId) -> Option<&ColumnStatistics> {
+        let table = self.table(self.table_of(column_id)?)?;
+        table
+            .statistics
+            .as_ref()?
+            .column(self.table_column(column_id))
+    }
+
+    /// Returns the statistics of the groups of correlated columns of a table.
+    pub fn column_groups(&self, table_id: usize) -> &[ColumnGroupStatistics] {
+        self.table(table_id)
+            .and_then(|table| table.statistics.as_ref())
+            .map_or(&[], |statistics| &statistics.column_groups)
+    }
+
+    pub fn table(&self, table_id: usize) -> Option<&Table> {
+        self.tables.get(table_id)
+    }
+
+    /// Returns a column of a table or a derived column. The columns of a scan return the column of
+    /// the table that they are an instance of.
+    pub fn column(&self, column_id: ColumnId) -> Option<&Column> {
+        match self.columns.get(&column_id) {
+            Some(&(table_id, index)) => Some(&self.tables[table_id].columns[index]),
+            None => self.derived_columns.get(&column_id),
+        }
+    }
+
+    /// Returns the identifier of the table that owns the given column, or `None` for derived
+    /// columns.
+    pub fn table_of(&self, column_id: ColumnId) -> Option<usize> {
+        self.columns.get(&column_id).map(|&(table_id, _)| table_id)
+    }
+
+    /// Returns the column of the table that a column of a scan is an instance of, or the column
+    /// itself if it is not the column of a scan.
+    pub fn table_column(&self, column_id: ColumnId) -> ColumnId {
+        self.column(column_id).map_or(column_id, |column| column.id)
+    }
+
+    /// Returns the instance of the table column `table_column` that belongs to the same scan as
+    /// `column_id` (which is a column of the same table).
+    pub fn scan_column(&self, column_id: ColumnId, table_column: ColumnId) -> ColumnId {
+        debug_assert_eq!(self.table_of(column_id), self.table_of(table_column));
+
+        match (
+            self.scan_columns.get(&column_id),
+            self.columns.get(&table_column),
+        ) {
+            (Some(&scan), Some(&(_, index))) => self.scans[scan][index],
+            _ => table_column,
+        }
+    }
+}
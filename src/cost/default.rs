@@ -0,0 +1,364 @@
+//! The default cost model for the physical operators that ship with the optimizer.
+//!
+//! The formulas here are intentionally simple (in the spirit of a textbook model): every operator
+//! is charged for the pages it reads, the rows it processes, the memory it holds on to, and the
+//! bytes it ships between partitions. All of the per-unit constants live in [`CostParameters`] so
+//! that they can be tuned for a specific execution engine without writing a new [`CostModel`].
+
+use super::{Cost, CostModel, CostWeights};
+use crate::catalog::Catalog;
+use crate::expression::logical::SetOperation;
+use crate::expression::physical::{
+    HashAggregate, HashJoin, HashSetOperation, IndexScan, MergeSort, NestedLoopApply,
+    PhysicalExpression, Projection, Selection, SortSetOperation, TableScan, TopN,
+};
+use crate::properties::LogicalProperties;
+use std::sync::Arc;
+
+/// The tunable per-unit constants of the [`DefaultCostModel`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CostParameters {
+    /// The size of a page in bytes.
+    pub page_size: f64,
+    /// The I/O cost of reading a page sequentially.
+    pub sequential_page: f64,
+    /// The I/O cost of reading a page at a random location (for example, through an index).
+    pub random_page: f64,
+    /// The CPU cost of producing a single row.
+    pub cpu_tuple: f64,
+    /// The CPU cost of evaluating a predicate against a single row.
+    pub cpu_predicate: f64,
+    /// The CPU cost of inserting a single row into a hash table.
+    pub cpu_hash_build: f64,
+    /// The CPU cost of probing a hash table with a single row.
+    pub cpu_hash_probe: f64,
+    /// The network cost of sending a single byte to another partition.
+    pub network_byte: f64,
+}
+
+impl Default for CostParameters {
+    fn default() -> Self {
+        Self {
+            page_size: 8192.0,
+            sequential_page: 100.0,
+            random_page: 400.0,
+            cpu_tuple: 1.0,
+            cpu_predicate: 0.25,
+            cpu_hash_build: 2.0,
+            cpu_hash_probe: 1.0,
+            network_byte: 0.1,
+        }
+    }
+}
+
+/// A cost model for all of the physical operators provided by the optimizer.
+///
+/// The costs are based on the estimated row counts and row widths of the groups involved, so better
+/// cardinality estimates directly result in better cost estimates.
+#[derive(Debug, Clone)]
+pub struct DefaultCostModel {
+    pub catalog: Arc<Catalog>,
+    pub parameters: CostParameters,
+    pub weights: CostWeights,
+}
+
+impl DefaultCostModel {
+    pub fn new(catalog: Arc<Catalog>) -> Self {
+        Self {
+            catalog,
+            parameters: CostParameters::default(),
+            weights: CostWeights::default(),
+        }
+    }
+
+    /// The number of pages needed to store `rows` rows of `width` bytes each.
+    fn pages(&self, rows: f64, width: f64) -> f64 {
+        (rows * width / self.parameters.page_size).ceil()
+    }
+
+    fn table_scan(&self, scan: &TableScan, output: &LogicalProperties) -> Cost {
+        let p = &self.parameters;
+
+        // Every row of the table has to be read, no matter how selective the filters are, but only
+        // the columns that are output or filtered on (assuming a columnar layout).
+        let (input_rows, table_width) = match self.catalog.table(scan.table_id) {
+            Some(table) => {
+                let width = scan
+                    .read_columns()
+                    .into_iter()
+                    .map(|id| self.catalog.table_column(id))
+                    .filter_map(|id| table.columns.iter().find(|column| column.id == id))
+                    .map(|column| table.column_width(column))
+                    .sum();
+                (table.estimated_row_count(), width)
+            }
+            None => (output.row_count, output.row_width() as f64),
+        };
+
+        // Every filter is evaluated against every row of the table.
+        let predicates = input_rows * scan.filters.len() as f64 * p.cpu_predicate;
+
+        Cost {
+            cpu: to_units(input_rows * p.cpu_tuple + predicates),
+            io: to_units(self.pages(input_rows, table_width) * p.sequential_page),
+            ..Cost::ZERO
+        }
+    }
+
+    fn index_scan(&self, _scan: &IndexScan, output: &LogicalProperties) -> Cost {
+        let p = &self.parameters;
+
+        // Traverse the index, and then fetch every matching row with a random read (assuming the
+        // worst case of no clustering).
+        let traversal = output.row_count.max(2.0).log2();
+        let fetches = output.row_count;
+
+        Cost {
+            cpu: to_units((traversal + output.row_count) * p.cpu_tuple),
+            io: to_units((traversal + fetches) * p.random_page),
+            ..Cost::ZERO
+        }
+    }
+
+    fn projection(&self, _projection: &Projection, output: &LogicalProperties) -> Cost {
+        // Every row is copied without the columns that are not needed anymore.
+        Cost::cpu(to_units(output.row_count * self.parameters.cpu_tuple))
+    }
+
+    fn selection(&self, selection: &Selection, input: &LogicalProperties) -> Cost {
+        // Every filter is evaluated against every input row.
+        let predicates = input.row_count * selection.filters.len() as f64;
+        Cost::cpu(to_units(predicates * self.parameters.cpu_predicate))
+    }
+
+    fn hash_join(
+        &self,
+        join: &HashJoin,
+        output: &LogicalProperties,
+        build: &LogicalProperties,
+        probe: &LogicalProperties,
+    ) -> Cost {
+        let p = &self.parameters;
+
+        let build_bytes = build.row_count * build.row_width() as f64;
+        let probe_bytes = probe.row_count * probe.row_width() as f64;
+
+        let cpu = build.row_count * p.cpu_hash_build
+            + probe.row_count * p.cpu_hash_probe
+            + output.row_count * p.cpu_tuple;
+
+        // If the hash table does not fit in memory, both sides are partitioned to disk and read
+        // back (a Grace hash join).
+        let partitions = join.partitions.max(1) as f64;
+        let io = if build_bytes / partitions > join.hash_table_size as f64 {
+            2.0 * (self.pages(build.row_count, build.row_width() as f64)
+                + self.pages(probe.row_count, probe.row_width() as f64))
+                * p.sequential_page
+        } else {
+            0.0
+        };
+
+        // With more than one partition, both sides have to be shuffled between partitions.
+        let network = if join.partitions > 1 {
+            (build_bytes + probe_bytes) * (partitions - 1.0) / partitions * p.network_byte
+        } else {
+            0.0
+        };
+
+        Cost {
+            cpu: to_units(cpu),
+            io: to_units(io),
+            network: to_units(network),
+            memory: to_units(build_bytes.min(join.hash_table_size as f64 * partitions)),
+        }
+    }
+
+    fn nested_loop_apply(
+        &self,
+        apply: &NestedLoopApply,
+        output: &LogicalProperties,
+        left: &LogicalProperties,
+    ) -> Cost {
+        let p = &self.parameters;
+
+        // Every left row is paired with the rows that the right side produces for it, and the
+        // conditions are evaluated against every pair.
+        let predicates =
+            output.row_count.max(left.row_count) * apply.conditions.len() as f64 * p.cpu_predicate;
+
+        Cost::cpu(to_units(
+            (left.row_count + output.row_count) * p.cpu_tuple + predicates,
+        ))
+    }
+
+    fn hash_aggregate(
+        &self,
+        _aggregate: &HashAggregate,
+        output: &LogicalProperties,
+        input: &LogicalProperties,
+    ) -> Cost {
+        let p = &self.parameters;
+
+        // Every input row probes the hash table for its group, which has an entry per output row.
+        let cpu = input.row_count * p.cpu_hash_probe
+            + output.row_count * (p.cpu_hash_build + p.cpu_tuple);
+
+        Cost {
+            cpu: to_units(cpu),
+            memory: to_units(output.row_count * output.row_width() as f64),
+            ..Cost::ZERO
+        }
+    }
+
+    fn merge_sort(&self, _sort: &MergeSort, output: &LogicalProperties) -> Cost {
+        let p = &self.parameters;
+
+        // Every row is compared about `log2(n)` times, and all of the rows are held at once.
+        let comparisons = output.row_count * output.row_count.max(2.0).log2();
+
+        Cost {
+            cpu: to_units(comparisons * p.cpu_predicate + output.row_count * p.cpu_tuple),
+            memory: to_units(output.row_count * output.row_width() as f64),
+            ..Cost::ZERO
+        }
+    }
+
+    fn top_n(&self, top_n: &TopN, output: &LogicalProperties, input: &LogicalProperties) -> Cost {
+        let p = &self.parameters;
+
+        // Every input row is compared with the largest row in the heap, and the rows that make it
+        // into the heap take about `log2(k)` comparisons to insert.
+        let kept = (top_n.offset + top_n.limit) as f64;
+        let comparisons = input.row_count * (1.0 + kept.max(2.0).log2());
+
+        Cost {
+            cpu: to_units(comparisons * p.cpu_predicate + output.row_count * p.cpu_tuple),
+            memory: to_units(kept.min(input.row_count) * output.row_width() as f64),
+            ..Cost::ZERO
+        }
+    }
+
+    fn hash_set_operation(
+        &self,
+        operation: &HashSetOperation,
+        output: &LogicalProperties,
+        inputs: &[&LogicalProperties],
+    ) -> Cost {
+        let p = &self.parameters;
+
+        // A union builds the hash table out of every input, which ends up with an entry per output
+        // row. The other operations only build it out of their left input.
+        let (build, probe, table) = match operation.operation {
+            SetOperation::Union => (
+                inputs.iter().map(|input| input.row_count).sum(),
+                0.0,
+                output,
+            ),
+            SetOperation::Intersect | SetOperation::Except => {
+                (inputs[0].row_count, inputs[1].row_count, inputs[0])
+            }
+        };
+
+        let cpu =
+            build * p.cpu_hash_build + probe * p.cpu_hash_probe + output.row_count * p.cpu_tuple;
+
+        Cost {
+            cpu: to_units(cpu),
+            memory: to_units(table.row_count * table.row_width() as f64),
+            ..Cost::ZERO
+        }
+    }
+
+    fn sort_set_operation(
+        &self,
+        _operation: &SortSetOperation,
+        output: &LogicalProperties,
+        inputs: &[&LogicalProperties],
+    ) -> Cost {
+        let p = &self.parameters;
+
+        // Every input is sorted on its own and held in memory, and the sorted inputs are merged.
+        let comparisons: f64 = inputs
+            .iter()
+            .map(|input| input.row_count * input.row_count.max(2.0).log2())
+            .sum();
+        let merged: f64 = inputs.iter().map(|input| input.row_count).sum();
+        let bytes: f64 = inputs
+            .iter()
+            .map(|input| input.row_count * input.row_width() as f64)
+            .sum();
+
+        Cost {
+            cpu: to_units(
+                comparisons * p.cpu_predicate
+                    + merged * p.cpu_predicate
+                    + output.row_count * p.cpu_tuple,
+            ),
+            memory: to_units(bytes),
+            ..Cost::ZERO
+        }
+    }
+}
+
+/// Converts a fractional cost into whole cost units, rounding up so that any work at all is never
+/// free.
+fn to_units(cost: f64) -> u64 {
+    // Casting a float to an integer saturates.
+    cost.ceil() as u64
+}
+
+impl CostModel for DefaultCostModel {
+    fn cost(
+        &self,
+        expr: &PhysicalExpression,
+        child_costs: &[Cost],
+        properties: &LogicalProperties,
+        child_properties: &[&LogicalProperties],
+    ) -> Cost {
+        let operator = match expr {
+            PhysicalExpression::TableScan(scan) => self.table_scan(scan, properties),
+            PhysicalExpression::IndexScan(scan) => self.index_scan(scan, properties),
+            PhysicalExpression::Projection(projection) => self.projection(projection, properties),
+            PhysicalExpression::Selection(selection) => {
+                self.selection(selection, child_properties[0])
+            }
+            PhysicalExpression::HashJoin(join) => {
+                self.hash_join(join, properties, child_properties[0], child_properties[1])
+            }
+            PhysicalExpression::NestedLoopApply(apply) => {
+                // The right side is evaluated once for every row of the left side, but its memory
+                // is reused between evaluations.
+                let executions = child_properties[0].row_count.max(1.0).ceil() as u64;
+                let right = Cost {
+                    memory: child_costs[1].memory,
+                    ..child_costs[1] * executions
+                };
+                let operator = self.nested_loop_apply(apply, properties, child_properties[0]);
+                return operator + child_costs[0] + right;
+            }
+            PhysicalExpression::HashAggregate(aggregate) => {
+                self.hash_aggregate(aggregate, properties, child_properties[0])
+            }
+            PhysicalExpression::MergeSort(sort) => self.merge_sort(sort, properties),
+            PhysicalExpression::TopN(top_n) => self.top_n(top_n, properties, child_properties[0]),
+            // Rows are passed on as they are.
+            PhysicalExpression::StreamingLimit(_) | PhysicalExpression::UnionAll(_) => {
+                Cost::cpu(to_units(properties.row_count * self.parameters.cpu_tuple))
+            }
+            PhysicalExpression::HashSetOperation(operation) => {
+                self.hash_set_operation(operation, properties, child_properties)
+            }
+            PhysicalExpression::SortSetOperation(operation) => {
+                self.sort_set_operation(operation, properties, child_properties)
+            }
+            // There is nothing to read or compute.
+            PhysicalExpression::EmptyScan(_) => Cost::ZERO,
+        };
+
+        operator + child_costs.iter().sum()
+    }
+
+    fn weights(&self) -> CostWeights {
+        self.weights
+    }
+}
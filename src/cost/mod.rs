@@ -0,0 +1,181 @@
+//! Cost models used by the [`SearchEngine`](crate::engine::SearchEngine) to compare physical plans.
+//!
+//! The search engine itself knows nothing about how expensive an operator is. Instead, it asks a
+//! [`CostModel`] to cost every physical expression it creates, which allows developers to plug in a
+//! model that matches the characteristics of their own execution engine.
+
+use crate::expression::physical::PhysicalExpression;
+use crate::properties::LogicalProperties;
+use std::cmp::Ordering;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Mul, Sub};
+
+pub mod default;
+
+#[cfg(test)]
+mod tests;
+
+/// The cost of a (partial) physical plan, broken down by the resources it consumes.
+///
+/// Since different resources are not directly comparable, costs are compared by collapsing them
+/// into a single scalar with a set of [`CostWeights`]. All arithmetic on costs saturates instead of
+/// overflowing, so [`Cost::MAX`] can be used as an infinite cost.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Cost {
+    pub cpu: u64,
+    pub io: u64,
+    pub network: u64,
+    pub memory: u64,
+}
+
+impl Cost {
+    pub const ZERO: Cost = Cost::new(0, 0, 0, 0);
+
+    pub const MAX: Cost = Cost::new(u64::MAX, u64::MAX, u64::MAX, u64::MAX);
+
+    pub const fn new(cpu: u64, io: u64, network: u64, memory: u64) -> Self {
+        Self {
+            cpu,
+            io,
+            network,
+            memory,
+        }
+    }
+
+    pub const fn cpu(cpu: u64) -> Self {
+        Self::new(cpu, 0, 0, 0)
+    }
+
+    pub const fn io(io: u64) -> Self {
+        Self::new(0, io, 0, 0)
+    }
+
+    pub const fn network(network: u64) -> Self {
+        Self::new(0, 0, network, 0)
+    }
+
+    pub const fn memory(memory: u64) -> Self {
+        Self::new(0, 0, 0, memory)
+    }
+}
+
+impl Add for Cost {
+    type Output = Cost;
+
+    fn add(self, rhs: Cost) -> Cost {
+        Cost {
+            cpu: self.cpu.saturating_add(rhs.cpu),
+            io: self.io.saturating_add(rhs.io),
+            network: self.network.saturating_add(rhs.network),
+            memory: self.memory.saturating_add(rhs.memory),
+        }
+    }
+}
+
+impl AddAssign for Cost {
+    fn add_assign(&mut self, rhs: Cost) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Cost {
+    type Output = Cost;
+
+    fn sub(self, rhs: Cost) -> Cost {
+        Cost {
+            cpu: self.cpu.saturating_sub(rhs.cpu),
+            io: self.io.saturating_sub(rhs.io),
+            network: self.network.saturating_sub(rhs.network),
+            memory: self.memory.saturating_sub(rhs.memory),
+        }
+    }
+}
+
+impl Mul<u64> for Cost {
+    type Output = Cost;
+
+    fn mul(self, rhs: u64) -> Cost {
+        Cost {
+            cpu: self.cpu.saturating_mul(rhs),
+            io: self.io.saturating_mul(rhs),
+            network: self.network.saturating_mul(rhs),
+            memory: self.memory.saturating_mul(rhs),
+        }
+    }
+}
+
+impl Sum for Cost {
+    fn sum<I: Iterator<Item = Cost>>(iter: I) -> Cost {
+        iter.fold(Cost::ZERO, Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Cost> for Cost {
+    fn sum<I: Iterator<Item = &'a Cost>>(iter: I) -> Cost {
+        iter.copied().sum()
+    }
+}
+
+/// The relative weights of each component of a [`Cost`], used to collapse a cost into a single
+/// comparable scalar.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CostWeights {
+    pub cpu: f64,
+    pub io: f64,
+    pub network: f64,
+    pub memory: f64,
+}
+
+impl Default for CostWeights {
+    /// By default, moving data (from disk or over the network) is much more expensive than
+    /// processing it, and memory usage is only a tie-breaker.
+    fn default() -> Self {
+        Self {
+            cpu: 1.0,
+            io: 4.0,
+            network: 8.0,
+            memory: 0.01,
+        }
+    }
+}
+
+impl CostWeights {
+    /// Collapses a cost into a single scalar.
+    pub fn collapse(&self, cost: &Cost) -> f64 {
+        self.cpu * cost.cpu as f64
+            + self.io * cost.io as f64
+            + self.network * cost.network as f64
+            + self.memory * cost.memory as f64
+    }
+
+    /// Compares two costs by their collapsed scalars.
+    pub fn compare(&self, left: &Cost, right: &Cost) -> Ordering {
+        self.collapse(left).total_cmp(&self.collapse(right))
+    }
+}
+
+/// A model that computes the cost of physical expressions.
+///
+/// The costs of the children of an expression are always computed before the expression itself, so
+/// a cost model only needs to worry about the operator at the root of the expression.
+pub trait CostModel: Send + Sync {
+    /// Computes the total cost of a physical expression (including the cost of its children).
+    ///
+    /// - `child_costs` are the total costs of the best plans of each child, in the same order as
+    ///   [`Relation::children`](crate::Relation::children).
+    /// - `properties` are the logical properties (such as the estimated row count) of the group that
+    ///   the expression belongs to.
+    /// - `child_properties` are the logical properties of the groups of each child.
+    fn cost(
+        &self,
+        expr: &PhysicalExpression,
+        child_costs: &[Cost],
+        properties: &LogicalProperties,
+        child_properties: &[&LogicalProperties],
+    ) -> Cost;
+
+    /// The weights used to compare the costs computed by this model.
+    fn weights(&self) -> CostWeights {
+        CostWeights::default()
+    }
+}
@@ -0,0 +1,130 @@
+use super::*;
+
+#[test]
+fn cost_arithmetic_saturates() {
+    let cost = Cost::new(1, 2, 3, 4);
+
+    assert_eq!(cost + Cost::MAX, Cost::MAX);
+    assert_eq!(Cost::ZERO - cost, Cost::ZERO);
+    assert_eq!(cost * u64::MAX, Cost::MAX);
+    assert_eq!([cost, cost].iter().sum::<Cost>(), cost * 2);
+}
+
+#[test]
+fn weights_decide_tradeoffs() {
+    let network_heavy = Cost::cpu(10) + Cost::network(10);
+    let cpu_heavy = Cost::cpu(100);
+
+    // By default, shipping data over the network is more expensive than processing it.
+    let weights = CostWeights::default();
+    assert_eq!(weights.compare(&network_heavy, &cpu_heavy), Ordering::Less);
+    assert_eq!(weights.compare(&cpu_heavy, &Cost::MAX), Ordering::Less);
+
+    // But a deployment with a fast network might weigh it very differently.
+    let weights = CostWeights {
+        network: 20.0,
+        ..CostWeights::default()
+    };
+    assert_eq!(
+        weights.compare(&network_heavy, &cpu_heavy),
+        Ordering::Greater
+    );
+}
+
+#[test]
+fn default_model_builds_hash_table_on_smaller_side() {
+    use crate::catalog::{Catalog, DataType};
+    use crate::engine::SearchEngine;
+    use crate::{Expression, Join, JoinType, LogicalExpression, Memo, Scan};
+    use default::DefaultCostModel;
+    use std::sync::Arc;
+
+    let mut catalog = Catalog::new();
+    let facts = catalog.add_table("facts", &[("id", DataType::Int64, false)], 1_000_000);
+    let dimension = catalog.add_table("dimension", &[("id", DataType::Int64, false)], 1_000);
+    let catalog = Arc::new(catalog);
+
+    let scan = |table_id: usize| {
+        Arc::new(Expression::Logical(LogicalExpression::Scan(Scan {
+            table_id,
+            columns: catalog.table(table_id).unwrap().column_ids(),
+            filters: vec![],
+        })))
+    };
+
+    let join = Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+        join_type: JoinType::Inner,
+        conditions: vec![],
+        left: scan(facts),
+        right: scan(dimension),
+    })));
+
+    let memo = Arc::new(Memo::new(catalog.clone()));
+    let (root, _) = memo.add_expression(&join, None);
+
+    let engine = SearchEngine::new(memo, Arc::new(DefaultCostModel::new(catalog.clone())));
+    let plan = engine.optimize(root).expect("the join should have a plan");
+
+    let Expression::Physical(PhysicalExpression::HashJoin(hash_join)) = plan.as_ref() else {
+        panic!("expected a hash join, got {plan:?}");
+    };
+    let Expression::Physical(PhysicalExpression::TableScan(build)) = hash_join.left.as_ref() else {
+        panic!("expected a table scan, got {:?}", hash_join.left);
+    };
+    assert_eq!(build.table_id, dimension);
+}
+
+#[test]
+fn default_model_charges_for_the_columns_read() {
+    use crate::catalog::{Catalog, DataType};
+    use crate::expression::physical::{PhysicalExpression, TableScan};
+    use crate::expression::scalar::ScalarExpression;
+    use crate::{Expression, LogicalExpression, Memo, Scan};
+    use default::DefaultCostModel;
+    use std::sync::Arc;
+
+    let mut catalog = Catalog::new();
+    let table = catalog.add_table(
+        "wide",
+        &[
+            ("a", DataType::Int64, false),
+            ("b", DataType::Int64, false),
+            ("c", DataType::Utf8, false),
+        ],
+        1_000_000,
+    );
+    let columns = catalog.table(table).unwrap().column_ids();
+    let catalog = Arc::new(catalog);
+
+    let memo = Memo::new(catalog.clone());
+    let scan = Arc::new(Expression::Logical(LogicalExpression::Scan(Scan {
+        table_id: table,
+        columns: columns.clone(),
+        filters: vec![],
+    })));
+    let (group, _) = memo.add_expression(&scan, None);
+
+    let model = DefaultCostModel::new(catalog);
+    let io = |columns: Vec<_>, filters| {
+        let scan = PhysicalExpression::TableScan(TableScan {
+            table_id: table,
+            columns,
+            filters,
+        });
+        model.cost(&scan, &[], group.logical_properties(), &[]).io
+    };
+
+    let all = io(columns.clone(), vec![]);
+    let narrow = io(vec![columns[0]], vec![]);
+    assert!(
+        narrow * 3 < all,
+        "expected {narrow} to be much less than {all}"
+    );
+
+    // Columns that are only filtered on still have to be read.
+    let filter = ScalarExpression::eq(
+        ScalarExpression::column(columns[1]),
+        ScalarExpression::literal(5),
+    );
+    assert_eq!(io(vec![columns[0]], vec![filter]), 2 * narrow);
+}
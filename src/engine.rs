@@ -1,55 +1,106 @@
+use crate::cost::{Cost, CostModel};
+use crate::properties::LogicalProperties;
 use crate::Relation;
-use crate::{rules::Rule, Expression, Group, Guidance, Memo};
+use crate::{rules::Rule, Expression, Group, Guidance, Memo, Move, Winner};
 use scc::Stack;
-use std::sync::{atomic::Ordering, Arc};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+pub mod random;
+
+#[cfg(test)]
+mod tests;
 
 /// The different types of tasks in the Cascades framework.
 pub enum Task {
     OptimizeGroup {
         expr: Arc<Group>,
-        limit: usize,
+        limit: Cost,
     },
     ExploreGroup {
         expr: Arc<Group>,
-        limit: usize,
+        limit: Cost,
     },
     ExploreExpression {
         expr: Arc<Expression>,
-        limit: usize,
+        limit: Cost,
     },
     OptimizeExpression {
         expr: Arc<Expression>,
-        limit: usize,
+        limit: Cost,
     },
     OptimizeInputs {
         expr: Arc<Expression>,
-        limit: usize,
+        limit: Cost,
     },
     ApplyRule {
         expr: Arc<Expression>,
-        limit: usize,
+        limit: Cost,
         rule: Arc<dyn Rule>,
         promise: usize,
+        /// The guidance for the expressions that the rule produces.
+        guidance: Arc<Guidance>,
     },
 }
 
 /// The "global" state we need to keep track of during search in the Cascades framework.
 ///
+/// Since tasks are stored on a stack, every task that a task schedules runs to completion before
+/// any task that was scheduled before it. The search relies on this to make sure that the groups of
+/// the children of an expression are explored / optimized before the expression itself.
+///
 /// TODO:
 /// Note that all of the fields need to be serializable if we want to implement leaving breadcrumbs.
 pub struct SearchEngine {
     memo: Arc<Memo>,
     tasks: Stack<Task>,
+    cost_model: Arc<dyn CostModel>,
+    /// The transformation rules that are disabled for every expression, on top of the guidance of
+    /// the expression itself.
+    disabled: Guidance,
+    /// The number of transformation rules that can still be scheduled. Once it runs out, the
+    /// remaining groups are only implemented, not explored any further.
+    budget: AtomicUsize,
 }
 
 impl SearchEngine {
+    pub fn new(memo: Arc<Memo>, cost_model: Arc<dyn CostModel>) -> Self {
+        memo.set_cost_weights(cost_model.weights());
+        Self {
+            memo,
+            tasks: Stack::default(),
+            cost_model,
+            disabled: Guidance::default(),
+            budget: AtomicUsize::new(usize::MAX),
+        }
+    }
+
+    /// Disables transformation rules (by their index in the rule list) for the whole search.
+    pub fn with_disabled_rules(mut self, rules: &[usize]) -> Self {
+        self.disabled = Guidance::disabling(rules);
+        self
+    }
+
+    /// Limits the number of transformation rules that the search applies.
+    pub fn with_transformation_budget(mut self, budget: usize) -> Self {
+        self.budget = AtomicUsize::new(budget);
+        self
+    }
+
+    pub fn memo(&self) -> &Arc<Memo> {
+        &self.memo
+    }
+
     /// The top-level function that optimizes a query plan.
     ///
+    /// Returns the cheapest physical plan for the given group, or `None` if the group cannot be
+    /// implemented with the available rules.
+    ///
     /// TODO: Parallelism.
-    pub fn optimize(&self, query: Arc<Group>) {
+    pub fn optimize(&self, query: Arc<Group>) -> Option<Arc<Expression>> {
         self.tasks.push(Task::OptimizeGroup {
-            expr: query,
-            limit: usize::MAX,
+            expr: query.clone(),
+            limit: Cost::MAX,
         });
 
         while let Some(task) = self.tasks.pop() {
@@ -64,101 +115,366 @@ impl SearchEngine {
                     limit,
                     rule,
                     promise,
-                } => self.apply_rule(expr, *limit, rule, *promise),
+                    guidance,
+                } => self.apply_rule(expr, *limit, rule, *promise, guidance),
             }
         }
 
-        todo!("Return the fully optimized plan")
+        self.best_plan(&query)
+    }
+
+    /// Builds the best physical plan for a group out of the winners of it and its descendants.
+    pub fn best_plan(&self, group: &Arc<Group>) -> Option<Arc<Expression>> {
+        let group = self.current(group);
+        let (expression, _) = group.winner()?;
+
+        let children = expression
+            .children()
+            .iter()
+            .map(|child| self.best_plan(&child.group(&self.memo)))
+            .collect::<Option<Vec<_>>>()?;
+
+        if children.is_empty() {
+            return Some(expression);
+        }
+
+        Some(Arc::new(expression.with_children(children)))
     }
 
     /// Derives the best physical plan for a group / equivalence class and places it in the memo
     /// table.
-    pub fn optimize_group(&self, group: &Arc<Group>, limit: usize) {
-        if group.explored.load(Ordering::Acquire) {
-            todo!("Begin optimizing the expressions inside this group");
-        } else {
-            todo!("Loop through all expressions in this group and call `optimize_expression`");
+    pub fn optimize_group(&self, group: &Arc<Group>, limit: Cost) {
+        let group = &self.current(group);
+        if !group.explored.load(Ordering::Acquire) {
+            // Come back to this group once it has been fully explored.
+            self.tasks.push(Task::OptimizeGroup {
+                expr: group.clone(),
+                limit,
+            });
+            self.tasks.push(Task::ExploreGroup {
+                expr: group.clone(),
+                limit,
+            });
+            return;
+        }
+
+        if group.optimized.swap(true, Ordering::AcqRel) {
+            // The physical expressions that were pruned under a smaller limit are costed again.
+            for expr in self.pruned_expressions(group, limit) {
+                self.tasks.push(Task::OptimizeInputs { expr, limit });
+            }
+            return;
+        }
+
+        for expr in group.expressions() {
+            if let Expression::Logical(_) = expr.as_ref() {
+                self.tasks.push(Task::OptimizeExpression { expr, limit });
+            }
         }
     }
 
+    /// Returns the physical expressions of a group without a plan that were pruned under a smaller
+    /// limit than `limit`, and therefore may have a plan within `limit`.
+    ///
+    /// A group with a plan never has to be optimized again: its winner is within the limit it was
+    /// found under, and no plan that is cheaper than the winner can have been pruned.
+    fn pruned_expressions(&self, group: &Group, limit: Cost) -> Vec<Arc<Expression>> {
+        if group.winner().is_some() {
+            return vec![];
+        }
+
+        let weights = self.cost_model.weights();
+        group
+            .expressions
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(expr, guidance)| {
+                matches!(expr.as_ref(), Expression::Physical(_))
+                    && weights.compare(&limit, &guidance.cost_limit.load()).is_gt()
+            })
+            .map(|(expr, _)| expr.clone())
+            .collect()
+    }
+
     /// Generates alternative equivalent logical expressions for the group.
-    pub fn explore_group(&self, group: &Arc<Group>, limit: usize) {
-        // TODO: Why do we store true here now and not later???
-        group.explored.store(true, Ordering::Release);
+    pub fn explore_group(&self, group: &Arc<Group>, limit: Cost) {
+        let group = &self.current(group);
 
-        todo!("Loop through all expressions in this group and call `explore_expression`")
+        // We store true here (and not after all of the expressions are explored) so that no other
+        // task schedules the exploration of this group again.
+        if group.explored.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        for expr in group.expressions() {
+            if let Expression::Logical(_) = expr.as_ref() {
+                self.tasks.push(Task::ExploreExpression { expr, limit });
+            }
+        }
     }
 
     /// Generates alternative equivalent logical expressions for the expression, pushing `ApplyRule`
     /// tasks onto the stack.
-    pub fn explore_expression(&self, expr: &Arc<Expression>, limit: usize) {
-        let group = expr.group(&self.memo);
+    pub fn explore_expression(&self, expr: &Arc<Expression>, limit: Cost) {
+        let guidance = expr.group(&self.memo).guidance(expr).union(&self.disabled);
 
-        // TODO: Get the guidance object from the memo table using the group somehow.
-        let guidance = Guidance::default();
+        let moves = expr.transformation_moves(&guidance, self.memo.estimator());
 
-        let moves = expr.transformation_moves(&guidance);
+        // Place all of the possible moves ordered by their promise onto the stack, as long as the
+        // budget allows it.
+        for Move {
+            rule,
+            promise,
+            guidance,
+        } in moves
+        {
+            let spent = self
+                .budget
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |budget| {
+                    budget.checked_sub(1)
+                });
+            if spent.is_err() {
+                break;
+            }
 
-        // Place all of the possible moves ordered by their promise onto the stack.
-        for (rule, promise) in moves {
             self.tasks.push(Task::ApplyRule {
                 expr: expr.clone(),
                 limit,
                 rule,
                 promise,
+                guidance,
             });
         }
 
-        todo!("Explore the groups of the children???")
+        // The children must be explored before the rules are applied, since rules can match
+        // against any of the expressions in the groups of the children.
+        for child in expr.children() {
+            let child_group = child.group(&self.memo);
+            if !child_group.explored.load(Ordering::Acquire) {
+                self.tasks.push(Task::ExploreGroup {
+                    expr: child_group,
+                    limit,
+                });
+            }
+        }
     }
 
     /// Derives the best physical plan for an expression and places it in the memo table.
-    pub fn optimize_expression(&self, expr: &Arc<Expression>, limit: usize) {
-        let group = expr.group(&self.memo);
+    pub fn optimize_expression(&self, expr: &Arc<Expression>, limit: Cost) {
+        let guidance = expr.group(&self.memo).guidance(expr);
 
-        // TODO: Get the guidance object from the memo table using the group somehow.
-        let guidance = Guidance::default();
-
-        let moves = expr.all_moves(&guidance);
+        let moves = expr.implementation_moves(&guidance);
 
         // Place all of the possible moves ordered by their promise onto the stack.
-        for (rule, promise) in moves {
+        for Move {
+            rule,
+            promise,
+            guidance,
+        } in moves
+        {
             self.tasks.push(Task::ApplyRule {
                 expr: expr.clone(),
                 limit,
                 rule,
                 promise,
+                guidance,
             });
         }
-
-        todo!("Explore the groups of the children???")
     }
 
     /// Applies a rule to the given expression, updates the memo table, and adds new expressions to
     /// explore if new expressions are created.
+    ///
+    /// New logical expressions are explored further, and new physical expressions have their
+    /// inputs optimized so that they can be costed.
     pub fn apply_rule(
         &self,
         expr: &Arc<Expression>,
-        limit: usize,
+        limit: Cost,
         rule: &Arc<dyn Rule>,
-        promise: usize,
+        _promise: usize,
+        guidance: &Arc<Guidance>,
     ) {
+        let group = expr.group(&self.memo);
+
         // TODO: Rules should be able to generate more than 1 new expression
-        let new_expr = rule(expr);
+        for binding in self.bindings(expr) {
+            let Some(new_expr) = rule(&binding) else {
+                continue;
+            };
+
+            // Every physical expression gets its own guidance, which tracks the limit it was costed
+            // under.
+            let guidance = match new_expr.as_ref() {
+                Expression::Logical(_) => guidance.clone(),
+                Expression::Physical(_) => Arc::default(),
+            };
+            let (_, added) =
+                self.memo
+                    .add_expression_with_guidance(&new_expr, Some(group.key()), guidance);
+            if !added {
+                continue;
+            }
+
+            match new_expr.as_ref() {
+                Expression::Logical(_) => self.tasks.push(Task::ExploreExpression {
+                    expr: new_expr,
+                    limit,
+                }),
+                Expression::Physical(_) => self.tasks.push(Task::OptimizeInputs {
+                    expr: new_expr,
+                    limit,
+                }),
+            };
+        }
+    }
+
+    /// Returns every version of `expr` where each child is replaced by one of the logical
+    /// expressions in the child's group, so that rules can match patterns that are more than one
+    /// level deep.
+    fn bindings(&self, expr: &Arc<Expression>) -> Vec<Arc<Expression>> {
+        let children = expr.children();
+        if children.is_empty() || !matches!(expr.as_ref(), Expression::Logical(_)) {
+            return vec![expr.clone()];
+        }
+
+        let mut bindings: Vec<Vec<Arc<Expression>>> = vec![vec![]];
+        for child in children {
+            let alternatives: Vec<Arc<Expression>> = child
+                .group(&self.memo)
+                .expressions()
+                .into_iter()
+                .filter(|alternative| matches!(alternative.as_ref(), Expression::Logical(_)))
+                .collect();
+
+            bindings = bindings
+                .into_iter()
+                .flat_map(|prefix| {
+                    alternatives.iter().map(move |alternative| {
+                        let mut binding = prefix.clone();
+                        binding.push(alternative.clone());
+                        binding
+                    })
+                })
+                .collect();
+        }
 
-        todo!(
-            "Update the memo with the new expression(s?), \
-              and then case on whether or not the rule is a transformation rule or not"
-        );
+        bindings
+            .into_iter()
+            .map(|children| Arc::new(expr.with_children(children)))
+            .collect()
+    }
+
+    /// Returns the group that a group has been merged into, or the group itself.
+    fn current(&self, group: &Arc<Group>) -> Arc<Group> {
+        self.memo
+            .group(group.key())
+            .expect("groups are never removed from the memo table")
     }
 
     /// Iterates over the inputs / children of an expression and optimizes them.
-    pub fn optimize_inputs(&self, expr: &Arc<Expression>, limit: usize) {
-        for child_expr in expr.children() {
-            todo!(
-                "Update the cost bounds, then the cost limit, \
-                  and then optimize the groups of the children"
+    ///
+    /// Once all of the children have been optimized, the expression is costed and becomes the
+    /// winner of its group if it is cheaper than the current winner.
+    ///
+    /// The expression is only worth costing if it can beat both `limit` and the current winner of
+    /// its group, so its children are optimized one at a time, each under what remains of that
+    /// upper bound after the costs of the expression itself and of its other children. Once the
+    /// partial cost of the expression exceeds the upper bound, the expression is pruned.
+    pub fn optimize_inputs(&self, expr: &Arc<Expression>, limit: Cost) {
+        let Expression::Physical(physical) = expr.as_ref() else {
+            unreachable!("only physical expressions have their inputs optimized");
+        };
+
+        let group = expr.group(&self.memo);
+        let weights = self.cost_model.weights();
+        let limit = match group.winner() {
+            Some((_, cost)) if weights.compare(&cost, &limit).is_lt() => cost,
+            _ => limit,
+        };
+        group.guidance(expr).cost_limit.rcu(|costed| {
+            if weights.compare(&limit, costed).is_gt() {
+                limit
+            } else {
+                **costed
+            }
+        });
+
+        let children: Vec<Arc<Group>> = expr
+            .children()
+            .iter()
+            .map(|child| child.group(&self.memo))
+            .collect();
+        let child_properties: Vec<&LogicalProperties> = children
+            .iter()
+            .map(|child| child.logical_properties())
+            .collect();
+        let cost = |child_costs: &[Cost]| {
+            self.cost_model.cost(
+                physical,
+                child_costs,
+                group.logical_properties(),
+                &child_properties,
             )
+        };
+
+        // The children that have not been optimized yet cost at least nothing, which makes the
+        // partial cost a lower bound of the cost of the expression.
+        let mut child_costs: Vec<Cost> = children
+            .iter()
+            .map(|child| {
+                child
+                    .winner()
+                    .filter(|_| child.optimized.load(Ordering::Acquire))
+                    .map_or(Cost::ZERO, |(_, cost)| cost)
+            })
+            .collect();
+        if weights.compare(&cost(&child_costs), &limit).is_gt() {
+            return;
         }
+
+        for (index, child) in children.iter().enumerate() {
+            let child_cost = std::mem::replace(&mut child_costs[index], Cost::ZERO);
+            let child_limit = limit - cost(&child_costs);
+            child_costs[index] = child_cost;
+
+            let optimized = child.optimized.load(Ordering::Acquire)
+                && self.pruned_expressions(child, child_limit).is_empty();
+            if !optimized {
+                // Come back to this expression once the child has been optimized.
+                self.tasks.push(Task::OptimizeInputs {
+                    expr: expr.clone(),
+                    limit,
+                });
+                self.tasks.push(Task::OptimizeGroup {
+                    expr: child.clone(),
+                    limit: child_limit,
+                });
+                return;
+            }
+        }
+
+        // If any child has no plan within its limit, neither does this expression.
+        let Some(child_costs) = children
+            .iter()
+            .map(|child| child.winner().map(|(_, cost)| cost))
+            .collect::<Option<Vec<Cost>>>()
+        else {
+            return;
+        };
+
+        let cost = cost(&child_costs);
+        if weights.compare(&cost, &limit).is_gt() {
+            return;
+        }
+
+        group.winner.rcu(|winner| match winner {
+            Some(winner) if weights.compare(&winner.cost, &cost).is_le() => Some(winner.clone()),
+            _ => Some(Arc::new(Winner {
+                expression: expr.clone(),
+                cost,
+            })),
+        });
     }
 }
@@ -0,0 +1,272 @@
+//! A randomized search over complete plans, as an alternative to the Cascades search for search
+//! spaces that are too large to explore.
+//!
+//! Instead of a memo table, the search keeps a single complete logical plan. Every step applies a
+//! random rule (a "move") to a random expression of the plan, costs the new plan with the same
+//! [`CostModel`] as the Cascades search, and decides whether to continue from it. Iterative
+//! improvement only accepts moves that make the plan cheaper, while simulated annealing also
+//! accepts moves that make it more expensive, with a probability that shrinks as the temperature
+//! cools down.
+//!
+//! The physical plan of a logical plan is its cheapest implementation with the static
+//! implementation rules, one expression at a time.
+
+use crate::cardinality::Estimator;
+use crate::cost::{Cost, CostModel};
+use crate::expression::logical::LogicalRelation;
+use crate::properties::LogicalProperties;
+use crate::rules::implementation::STATIC_IMPLEMENTATION_RULES;
+use crate::rules::transformation::{
+    join_commutativity, join_exchange, join_left_associativity, join_right_associativity,
+};
+use crate::rules::{Rule, StaticRule};
+use crate::statistics::sketch::Random;
+use crate::{Expression, Relation};
+use std::sync::Arc;
+
+/// How the randomized search decides whether to continue from a plan that a move produced.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Acceptance {
+    /// Only continue from cheaper plans.
+    IterativeImprovement,
+    /// Continue from a plan that is more expensive by `delta` with probability
+    /// `exp(-delta / temperature)`.
+    SimulatedAnnealing {
+        /// The starting temperature, relative to the cost of the initial plan.
+        temperature: f64,
+        /// The factor that the temperature is multiplied by after every move.
+        cooling: f64,
+    },
+}
+
+/// The options of a [`RandomizedSearch`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RandomizedOptions {
+    pub acceptance: Acceptance,
+    /// The number of moves to try.
+    pub moves: usize,
+    /// The seed of the random choices, so that searches are reproducible.
+    pub seed: u64,
+}
+
+impl Default for RandomizedOptions {
+    fn default() -> Self {
+        Self {
+            acceptance: Acceptance::SimulatedAnnealing {
+                temperature: 0.1,
+                cooling: 0.99,
+            },
+            moves: 1000,
+            seed: 0,
+        }
+    }
+}
+
+/// A physical plan, along with its cost and the logical properties of its root.
+struct Implementation {
+    plan: Arc<Expression>,
+    cost: Cost,
+    properties: LogicalProperties,
+}
+
+/// A randomized search over complete plans.
+pub struct RandomizedSearch {
+    estimator: Estimator,
+    cost_model: Arc<dyn CostModel>,
+    moves: Vec<Arc<dyn Rule>>,
+    options: RandomizedOptions,
+}
+
+impl RandomizedSearch {
+    /// Creates a search that uses the join reordering rules as its moves.
+    pub fn new(
+        estimator: Estimator,
+        cost_model: Arc<dyn CostModel>,
+        options: RandomizedOptions,
+    ) -> Self {
+        let moves: [StaticRule; 4] = [
+            join_commutativity,
+            join_right_associativity,
+            join_left_associativity,
+            join_exchange,
+        ];
+        Self {
+            estimator,
+            cost_model,
+            moves: moves
+                .into_iter()
+                .map(|rule| Arc::new(rule) as Arc<dyn Rule>)
+                .collect(),
+            options,
+        }
+    }
+
+    /// Replaces the rules that are used as moves.
+    pub fn with_moves(mut self, moves: Vec<Arc<dyn Rule>>) -> Self {
+        self.moves = moves;
+        self
+    }
+
+    /// Searches for a cheaper version of a logical plan, and returns the cheapest physical plan
+    /// found and its cost.
+    ///
+    /// Returns `None` if the initial plan cannot be implemented with the available rules.
+    pub fn optimize(&self, plan: &Arc<Expression>) -> Option<(Arc<Expression>, Cost)> {
+        let weights = self.cost_model.weights();
+        let mut random = Random::new(self.options.seed);
+
+        let mut current = (plan.clone(), self.implement(plan)?.cost);
+        let mut best = current.clone();
+        let mut temperature = match self.options.acceptance {
+            Acceptance::IterativeImprovement => 0.0,
+            Acceptance::SimulatedAnnealing { temperature, .. } => {
+                temperature * weights.collapse(&current.1)
+            }
+        };
+
+        for _ in 0..self.options.moves {
+            let paths = paths(&current.0);
+            let path = &paths[random.below(paths.len() as u64) as usize];
+            let rule = &self.moves[random.below(self.moves.len() as u64) as usize];
+
+            if let Some(candidate) = apply_at(&current.0, path, rule.as_ref()) {
+                if let Some(implementation) = self.implement(&candidate) {
+                    let delta =
+                        weights.collapse(&implementation.cost) - weights.collapse(&current.1);
+                    let accepted = delta < 0.0
+                        || (temperature > 0.0 && random.next_f64() < (-delta / temperature).exp());
+
+                    if accepted {
+                        current = (candidate, implementation.cost);
+                        if weights.compare(&current.1, &best.1).is_lt() {
+                            best = current.clone();
+                        }
+                    }
+                }
+            }
+
+            if let Acceptance::SimulatedAnnealing { cooling, .. } = self.options.acceptance {
+                temperature *= cooling;
+            }
+        }
+
+        let implementation = self.implement(&best.0)?;
+        Some((implementation.plan, implementation.cost))
+    }
+
+    /// Finds the cheapest physical plan of a logical plan, choosing the cheapest implementation of
+    /// every expression given the cheapest implementations of its children.
+    fn implement(&self, expr: &Arc<Expression>) -> Option<Implementation> {
+        let Expression::Logical(logical) = expr.as_ref() else {
+            panic!("only logical plans can be implemented");
+        };
+
+        let children = expr
+            .children()
+            .iter()
+            .map(|child| self.implement(child))
+            .collect::<Option<Vec<_>>>()?;
+        let child_properties: Vec<&LogicalProperties> =
+            children.iter().map(|child| &child.properties).collect();
+
+        let mut properties = logical.derive_properties(&child_properties, &self.estimator);
+        if let Some(row_count) = self.estimator.overrides().row_count(expr, &properties) {
+            properties.row_count = row_count;
+        }
+
+        let own_children = expr.children();
+        let weights = self.cost_model.weights();
+        let mut best: Option<(Arc<Expression>, Cost)> = None;
+        for physical in STATIC_IMPLEMENTATION_RULES
+            .iter()
+            .filter_map(|rule| rule(expr))
+        {
+            let Expression::Physical(operator) = physical.as_ref() else {
+                unreachable!("implementation rules produce physical expressions");
+            };
+
+            // Rules that match more than one level (such as `top_n`) take their inputs from further
+            // down the plan, which have to be implemented on their own.
+            let inputs = physical.children();
+            let below;
+            let implemented = if inputs.len() == own_children.len()
+                && inputs
+                    .iter()
+                    .zip(&own_children)
+                    .all(|(input, child)| Arc::ptr_eq(input, child))
+            {
+                &children
+            } else {
+                let Some(implementations) = inputs
+                    .iter()
+                    .map(|input| self.implement(input))
+                    .collect::<Option<Vec<_>>>()
+                else {
+                    continue;
+                };
+                below = implementations;
+                &below
+            };
+
+            let input_costs: Vec<Cost> = implemented.iter().map(|input| input.cost).collect();
+            let input_properties: Vec<&LogicalProperties> =
+                implemented.iter().map(|input| &input.properties).collect();
+            let cost = self
+                .cost_model
+                .cost(operator, &input_costs, &properties, &input_properties);
+            if best
+                .as_ref()
+                .is_some_and(|(_, best)| weights.compare(best, &cost).is_le())
+            {
+                continue;
+            }
+
+            let plan = if implemented.is_empty() {
+                physical
+            } else {
+                let inputs = implemented.iter().map(|input| input.plan.clone()).collect();
+                Arc::new(physical.with_children(inputs))
+            };
+            best = Some((plan, cost));
+        }
+        let (plan, cost) = best?;
+
+        Some(Implementation {
+            plan,
+            cost,
+            properties,
+        })
+    }
+}
+
+/// Returns the path (the indices of the children to follow from the root) to every expression of
+/// a plan.
+fn paths(plan: &Arc<Expression>) -> Vec<Vec<usize>> {
+    let mut paths = vec![vec![]];
+    for (index, child) in plan.children().iter().enumerate() {
+        paths.extend(paths_below(child, index));
+    }
+    paths
+}
+
+fn paths_below(expr: &Arc<Expression>, index: usize) -> Vec<Vec<usize>> {
+    paths(expr)
+        .into_iter()
+        .map(|mut path| {
+            path.insert(0, index);
+            path
+        })
+        .collect()
+}
+
+/// Applies a rule to the expression at the end of a path, and returns the new plan if the rule
+/// matched.
+fn apply_at(plan: &Arc<Expression>, path: &[usize], rule: &dyn Rule) -> Option<Arc<Expression>> {
+    let Some((&index, rest)) = path.split_first() else {
+        return rule(plan);
+    };
+
+    let mut children = plan.children();
+    children[index] = apply_at(&children[index], rest, rule)?;
+    Some(Arc::new(plan.with_children(children)))
+}
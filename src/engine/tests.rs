@@ -0,0 +1,475 @@
+use crate::catalog::{Catalog, ColumnId, DataType};
+use crate::cost::{Cost, CostModel};
+use crate::expression::physical::PhysicalExpression;
+use crate::expression::scalar::ScalarExpression;
+use crate::properties::LogicalProperties;
+use crate::{Expression, Filter, Group, Join, JoinType, LogicalExpression, Memo, Relation, Scan};
+use std::sync::Arc;
+
+use super::*;
+
+/// A cost model that charges one unit per row scanned or probed, and two units per row inserted
+/// into a hash table (built from the left child).
+struct RowCountCostModel;
+
+impl CostModel for RowCountCostModel {
+    fn cost(
+        &self,
+        expr: &PhysicalExpression,
+        child_costs: &[Cost],
+        properties: &LogicalProperties,
+        child_properties: &[&LogicalProperties],
+    ) -> Cost {
+        let children: Cost = child_costs.iter().sum();
+
+        match expr {
+            PhysicalExpression::HashJoin(_) => {
+                let (build, probe) = (child_properties[0], child_properties[1]);
+                children + Cost::cpu(probe.row_count as u64 + 2 * build.row_count as u64)
+            }
+            _ => children + Cost::cpu(properties.row_count as u64),
+        }
+    }
+}
+
+fn scan(catalog: &Catalog, table_id: usize) -> Arc<Expression> {
+    Arc::new(Expression::Logical(LogicalExpression::Scan(Scan {
+        table_id,
+        columns: catalog.table(table_id).unwrap().column_ids(),
+        filters: vec![],
+    })))
+}
+
+fn join(left: Arc<Expression>, right: Arc<Expression>) -> Arc<Expression> {
+    Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+        join_type: JoinType::Inner,
+        conditions: vec![],
+        left,
+        right,
+    })))
+}
+
+/// Returns the tables scanned by a physical plan, from left to right.
+fn scanned_tables(plan: &Arc<Expression>) -> Vec<usize> {
+    match plan.as_ref() {
+        Expression::Physical(PhysicalExpression::TableScan(scan)) => vec![scan.table_id],
+        _ => plan.children().iter().flat_map(scanned_tables).collect(),
+    }
+}
+
+#[test]
+fn joins_smallest_tables_first() {
+    let mut catalog = Catalog::new();
+    for (name, rows) in [("a", 1000), ("b", 100), ("c", 10)] {
+        catalog.add_table(name, &[("id", DataType::Int64, false)], rows);
+    }
+    let catalog = Arc::new(catalog);
+
+    // Start from the worst join order: `(A x B) x C`.
+    let query = join(
+        join(scan(&catalog, 0), scan(&catalog, 1)),
+        scan(&catalog, 2),
+    );
+
+    let memo = Arc::new(Memo::new(catalog.clone()));
+    let (root, _) = memo.add_expression(&query, None);
+
+    let engine = SearchEngine::new(memo, Arc::new(RowCountCostModel));
+    let plan = engine
+        .optimize(root.clone())
+        .expect("the query should have a plan");
+
+    // The best plan joins `B` and `C` before joining with `A`.
+    let Expression::Physical(PhysicalExpression::HashJoin(top)) = plan.as_ref() else {
+        panic!("the top of the plan should be a hash join, got {plan:?}");
+    };
+    let (left, right) = (scanned_tables(&top.left), scanned_tables(&top.right));
+    assert!(
+        (left == vec![0] && right.len() == 2) || (right == vec![0] && left.len() == 2),
+        "expected `A` to be joined last, got {left:?} and {right:?}"
+    );
+
+    let (_, cost) = root.winner().unwrap();
+    assert_eq!(cost.cpu, 1000 + 110 + 100 + 2 * 10 + 1000 + 2 * 1000);
+}
+
+#[test]
+fn filters_are_evaluated_in_scans() {
+    let mut catalog = Catalog::new();
+    for (name, rows) in [("a", 1000), ("b", 1000)] {
+        catalog.add_table(name, &[("id", DataType::Int64, false)], rows);
+    }
+    let catalog = Arc::new(catalog);
+    let (a, b) = (
+        ScalarExpression::column(ColumnId(0)),
+        ScalarExpression::column(ColumnId(1)),
+    );
+
+    // `SELECT * FROM a, b WHERE a.id = b.id AND a.id = 5`
+    let predicates = vec![
+        ScalarExpression::eq(a.clone(), b.clone()),
+        ScalarExpression::eq(a, ScalarExpression::literal(5)),
+    ];
+    let query = Arc::new(Expression::Logical(LogicalExpression::Filter(Filter {
+        filters: predicates.clone(),
+        children: join(scan(&catalog, 0), scan(&catalog, 1)),
+    })));
+
+    let memo = Arc::new(Memo::new(catalog.clone()));
+    let (root, _) = memo.add_expression(&query, None);
+
+    let engine = SearchEngine::new(memo, Arc::new(RowCountCostModel));
+    let plan = engine
+        .optimize(root)
+        .expect("the filter should be pushed into the join and the scan");
+
+    let Expression::Physical(PhysicalExpression::HashJoin(join)) = plan.as_ref() else {
+        panic!("the top of the plan should be a hash join, got {plan:?}");
+    };
+    assert_eq!(join.conditions, vec![predicates[0].clone()]);
+
+    let filters: Vec<_> = [&join.left, &join.right]
+        .into_iter()
+        .flat_map(|side| match side.as_ref() {
+            Expression::Physical(PhysicalExpression::TableScan(scan)) => scan.filters.clone(),
+            _ => panic!("both sides should be table scans, got {side:?}"),
+        })
+        .collect();
+    assert_eq!(filters, vec![predicates[1].clone()]);
+}
+
+#[test]
+fn filters_that_cannot_be_pushed_down_are_selections() {
+    let mut catalog = Catalog::new();
+    for (name, rows) in [("a", 1000), ("b", 1000)] {
+        catalog.add_table(name, &[("id", DataType::Int64, false)], rows);
+    }
+    let catalog = Arc::new(catalog);
+    let (a, b) = (
+        ScalarExpression::column(ColumnId(0)),
+        ScalarExpression::column(ColumnId(1)),
+    );
+
+    // `SELECT * FROM a LEFT JOIN b ON a.id = b.id WHERE b.id IS NULL`, where the filter holds for
+    // the rows that the join adds, so it can neither be pushed into the join nor into `b`.
+    let is_null = Arc::new(ScalarExpression::IsNull(b.clone()));
+    let outer_join = Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+        join_type: JoinType::LeftOuter,
+        conditions: vec![ScalarExpression::eq(a, b)],
+        left: scan(&catalog, 0),
+        right: scan(&catalog, 1),
+    })));
+    let query = Arc::new(Expression::Logical(LogicalExpression::Filter(Filter {
+        filters: vec![is_null.clone()],
+        children: outer_join,
+    })));
+
+    let memo = Arc::new(Memo::new(catalog.clone()));
+    let (root, _) = memo.add_expression(&query, None);
+
+    let engine = SearchEngine::new(memo, Arc::new(RowCountCostModel));
+    let plan = engine
+        .optimize(root)
+        .expect("the filter should be implemented on its own");
+
+    let Expression::Physical(PhysicalExpression::Selection(selection)) = plan.as_ref() else {
+        panic!("the top of the plan should be a selection, got {plan:?}");
+    };
+    assert_eq!(selection.filters, vec![is_null]);
+    assert!(matches!(
+        selection.child.as_ref(),
+        Expression::Physical(PhysicalExpression::HashJoin(_))
+    ));
+}
+
+/// Counts the distinct logical join trees that a group represents.
+fn join_trees(memo: &Arc<Memo>, group: &Arc<Group>) -> u64 {
+    group
+        .expressions()
+        .iter()
+        .map(|expr| match expr.as_ref() {
+            Expression::Logical(LogicalExpression::Scan(_)) => 1,
+            Expression::Logical(LogicalExpression::Join(_)) => expr
+                .children()
+                .iter()
+                .map(|child| join_trees(memo, &child.group(memo)))
+                .product(),
+            _ => 0,
+        })
+        .sum()
+}
+
+#[test]
+fn every_join_tree_is_generated_once() {
+    for relations in 2..=6 {
+        let mut catalog = Catalog::new();
+        for table in 0..relations {
+            let rows = 10 * (table + 1);
+            catalog.add_table(
+                &format!("t{table}"),
+                &[("id", DataType::Int64, false)],
+                rows,
+            );
+        }
+        let catalog = Arc::new(catalog);
+
+        let query = (1..relations).fold(scan(&catalog, 0), |left, table| {
+            join(left, scan(&catalog, table))
+        });
+
+        let memo = Arc::new(Memo::new(catalog.clone()));
+        let (root, _) = memo.add_expression(&query, None);
+        let engine = SearchEngine::new(memo.clone(), Arc::new(RowCountCostModel));
+        engine
+            .optimize(root.clone())
+            .expect("the query should have a plan");
+
+        // There are `n! * C(n - 1)` = `(2n - 2)! / (n - 1)!` bushy join trees over `n` relations
+        // (with cross products), where `C` are the Catalan numbers.
+        let expected: u64 = (relations as u64..=2 * relations as u64 - 2).product();
+        assert_eq!(
+            join_trees(&memo, &root),
+            expected,
+            "wrong number of join trees for {relations} relations"
+        );
+    }
+}
+
+#[test]
+fn randomized_search_comes_close_to_cascades() {
+    use super::random::{Acceptance, RandomizedOptions, RandomizedSearch};
+    use crate::cardinality::Estimator;
+
+    let mut catalog = Catalog::new();
+    for (name, rows) in [("a", 1000), ("b", 100), ("c", 10), ("d", 1)] {
+        catalog.add_table(name, &[("id", DataType::Int64, false)], rows);
+    }
+    let catalog = Arc::new(catalog);
+
+    let query = join(
+        join(
+            join(scan(&catalog, 0), scan(&catalog, 1)),
+            scan(&catalog, 2),
+        ),
+        scan(&catalog, 3),
+    );
+
+    let memo = Arc::new(Memo::new(catalog.clone()));
+    let (root, _) = memo.add_expression(&query, None);
+    SearchEngine::new(memo, Arc::new(RowCountCostModel)).optimize(root.clone());
+    let (_, cascades) = root.winner().unwrap();
+
+    for acceptance in [
+        Acceptance::IterativeImprovement,
+        Acceptance::SimulatedAnnealing {
+            temperature: 0.1,
+            cooling: 0.99,
+        },
+    ] {
+        let options = RandomizedOptions {
+            acceptance,
+            moves: 500,
+            seed: 42,
+        };
+        let search = || {
+            let estimator = Estimator::new(catalog.clone());
+            RandomizedSearch::new(estimator, Arc::new(RowCountCostModel), options)
+                .optimize(&query)
+                .expect("the query should have a plan")
+        };
+
+        // The initial plan costs more than two million, and the best plan a little over four
+        // thousand. A randomized search is not guaranteed to find the best plan, but it should get
+        // close to it.
+        let (plan, cost) = search();
+        assert!(
+            cost.cpu * 10 <= cascades.cpu * 11,
+            "{acceptance:?} found a plan costing {cost:?}, but the best plan costs {cascades:?}"
+        );
+
+        // The same seed makes the same choices.
+        let (again, again_cost) = search();
+        assert_eq!(scanned_tables(&plan), scanned_tables(&again));
+        assert_eq!(cost, again_cost);
+    }
+}
+
+#[test]
+fn randomized_search_implements_top_n() {
+    use super::random::{RandomizedOptions, RandomizedSearch};
+    use crate::cardinality::Estimator;
+    use crate::cost::default::DefaultCostModel;
+    use crate::{Limit, Sort, SortKey};
+
+    let mut catalog = Catalog::new();
+    catalog.add_table("a", &[("id", DataType::Int64, false)], 100_000);
+    let catalog = Arc::new(catalog);
+
+    // `SELECT * FROM a ORDER BY id LIMIT 10`
+    let query = Arc::new(Expression::Logical(LogicalExpression::Limit(Limit {
+        limit: 10,
+        offset: 0,
+        children: Arc::new(Expression::Logical(LogicalExpression::Sort(Sort {
+            order: vec![SortKey {
+                column: ColumnId(0),
+                descending: false,
+            }],
+            children: scan(&catalog, 0),
+        }))),
+    })));
+
+    let search = RandomizedSearch::new(
+        Estimator::new(catalog.clone()),
+        Arc::new(DefaultCostModel::new(catalog)),
+        RandomizedOptions::default(),
+    );
+    let (plan, _) = search
+        .optimize(&query)
+        .expect("the query should have a plan");
+
+    // The top-n reads the scan directly, instead of a full sort of it.
+    let Expression::Physical(PhysicalExpression::TopN(top_n)) = plan.as_ref() else {
+        panic!("the top of the plan should be a top-n, got {plan:?}");
+    };
+    assert!(
+        matches!(
+            top_n.child.as_ref(),
+            Expression::Physical(PhysicalExpression::TableScan(_))
+        ),
+        "{plan:?}"
+    );
+}
+
+#[test]
+fn expressions_above_the_upper_bound_are_pruned() {
+    let mut catalog = Catalog::new();
+    for (name, rows) in [("small", 10), ("big", 100_000), ("huge", 100_000)] {
+        catalog.add_table(name, &[("id", DataType::Int64, false)], rows);
+    }
+    let catalog = Arc::new(catalog);
+    let filter = |column, children| {
+        Arc::new(Expression::Logical(LogicalExpression::Filter(Filter {
+            filters: vec![ScalarExpression::eq(
+                ScalarExpression::column(ColumnId(column)),
+                ScalarExpression::literal(5),
+            )],
+            children,
+        })))
+    };
+
+    // Pretend that filtering the join of the big tables gives the same rows as filtering the small
+    // table, which is optimized first (the expressions of a group are optimized last to first).
+    let memo = Arc::new(Memo::new(catalog.clone()));
+    let joined = join(scan(&catalog, 1), scan(&catalog, 2));
+    let (root, _) = memo.add_expression(&filter(1, joined.clone()), None);
+    memo.add_expression(&filter(0, scan(&catalog, 0)), Some(root.key()));
+
+    let engine =
+        SearchEngine::new(memo.clone(), Arc::new(RowCountCostModel)).with_transformation_budget(0);
+    let plan = engine
+        .optimize(root.clone())
+        .expect("the query should have a plan");
+    assert_eq!(scanned_tables(&plan), vec![0], "got {plan:?}");
+
+    // The hash join alone costs more than the filter of the small table, so the join is pruned
+    // without ever optimizing the big tables.
+    let join_group = joined.group(&memo);
+    assert!(join_group.optimized.load(Ordering::Acquire));
+    assert!(join_group.winner().is_none());
+    for table in [1, 2] {
+        assert!(!scan(&catalog, table)
+            .group(&memo)
+            .optimized
+            .load(Ordering::Acquire));
+    }
+
+    // Without the upper bound of the filter, the join does have a plan.
+    let plan = engine
+        .optimize(join_group)
+        .expect("the join should have a plan");
+    assert_eq!(scanned_tables(&plan), vec![1, 2], "got {plan:?}");
+}
+
+#[test]
+fn aggregation_alternatives_share_a_group() {
+    use crate::{Aggregate, AggregateCall, AggregateFunction, AggregatePhase};
+
+    let mut catalog = Catalog::new();
+    catalog.add_table(
+        "facts",
+        &[
+            ("id", DataType::Int64, false),
+            ("x", DataType::Int64, false),
+        ],
+        1000,
+    );
+    catalog.add_table("dimension", &[("id", DataType::Int64, false)], 10);
+    let total = catalog.add_derived_column("total", DataType::Int64, true);
+    let catalog = Arc::new(catalog);
+
+    // `SELECT dimension.id, SUM(facts.x) FROM facts JOIN dimension ON facts.id = dimension.id
+    // GROUP BY dimension.id`
+    let query = Arc::new(Expression::Logical(LogicalExpression::Aggregate(
+        Aggregate {
+            phase: AggregatePhase::Complete,
+            group_by: vec![ColumnId(2)],
+            aggregates: vec![AggregateCall {
+                function: AggregateFunction::Sum,
+                argument: Some(ColumnId(1)),
+                output: total,
+            }],
+            children: Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+                join_type: JoinType::Inner,
+                conditions: vec![ScalarExpression::eq(
+                    ScalarExpression::column(ColumnId(0)),
+                    ScalarExpression::column(ColumnId(2)),
+                )],
+                left: scan(&catalog, 0),
+                right: scan(&catalog, 1),
+            }))),
+        },
+    )));
+
+    let memo = Arc::new(Memo::new(catalog.clone()));
+    let (root, _) = memo.add_expression(&query, None);
+    let engine = SearchEngine::new(memo.clone(), Arc::new(RowCountCostModel));
+    engine
+        .optimize(root.clone())
+        .expect("the query should have a plan");
+
+    // The original aggregation, the split aggregation, and the aggregation pushed below the join
+    // (before and after commuting the join).
+    let finals: Vec<Arc<Expression>> = root
+        .expressions()
+        .into_iter()
+        .filter(|expr| {
+            matches!(
+                expr.as_ref(),
+                Expression::Logical(LogicalExpression::Aggregate(Aggregate {
+                    phase: AggregatePhase::Final,
+                    ..
+                }))
+            )
+        })
+        .collect();
+    let below_join = finals
+        .iter()
+        .filter(|expr| {
+            expr.children()[0]
+                .group(&memo)
+                .expressions()
+                .iter()
+                .any(|child| {
+                    matches!(
+                        child.as_ref(),
+                        Expression::Logical(LogicalExpression::Join(_))
+                    )
+                })
+        })
+        .count();
+    assert!(
+        finals.len() >= 2,
+        "expected a split and an eager aggregation"
+    );
+    assert!(below_join >= 1, "expected an eager aggregation");
+}
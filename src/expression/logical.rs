@@ -1,19 +1,180 @@
+use crate::cardinality::Estimator;
+use crate::catalog::ColumnId;
+use crate::expression::scalar::{hash_conjunction, same_conjunction, ScalarExpression};
+use crate::properties::equivalence::{add_equalities, equalities, restrict};
+use crate::properties::{minimize_keys, ColumnSet, Field, LogicalProperties};
 use crate::{Expression, PhysicalProperties, Relation};
 use enum_dispatch::enum_dispatch;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeSet;
+use std::hash::Hash;
 use std::sync::Arc;
 
-#[enum_dispatch(Relation)]
-#[derive(Debug)]
+#[enum_dispatch(Relation, LogicalRelation)]
+#[derive(Debug, PartialEq)]
 pub enum LogicalExpression {
     Scan,
     Filter,
+    Project,
     Join,
+    Apply,
+    Aggregate,
+    Sort,
+    Limit,
+    Union,
+    Intersect,
+    Except,
+    Empty,
 }
 
-#[derive(Debug)]
+/// The trait defining shared behavior between all logical expressions.
+#[enum_dispatch]
+pub trait LogicalRelation {
+    /// The identifiers of the columns that this expression outputs, in order.
+    ///
+    /// Unlike the [`LogicalProperties`] of a group, this does not need the catalog, so rules can
+    /// use it to figure out where columns come from.
+    fn output_columns(&self) -> Vec<ColumnId>;
+
+    /// Derives the logical properties of this expression given the properties of its children (in
+    /// the same order as [`Relation::children`]).
+    fn derive_properties(
+        &self,
+        children: &[&LogicalProperties],
+        estimator: &Estimator,
+    ) -> LogicalProperties;
+
+    /// Derives the column equivalence classes of this expression given the classes of its
+    /// children. Like [`LogicalRelation::output_columns`], this does not need the catalog.
+    fn derive_equivalences(&self, children: &[&[ColumnSet]]) -> Vec<ColumnSet>;
+}
+
+/// Returns the output columns of a logical expression.
+///
+/// # Panics
+///
+/// Panics if the expression is not a logical expression.
+pub fn output_columns(expr: &Expression) -> Vec<ColumnId> {
+    let Expression::Logical(logical) = expr else {
+        panic!("only logical expressions have structural output columns");
+    };
+
+    logical.output_columns()
+}
+
+/// Returns the column equivalence classes of a logical expression, derived from the expression
+/// and its descendants.
+///
+/// # Panics
+///
+/// Panics if the expression or any of its descendants is not a logical expression.
+pub fn equivalences(expr: &Expression) -> Vec<ColumnSet> {
+    let Expression::Logical(logical) = expr else {
+        panic!("only logical expressions have structural equivalences");
+    };
+
+    let children: Vec<Vec<ColumnSet>> = expr
+        .children()
+        .iter()
+        .map(|child| equivalences(child))
+        .collect();
+    let children: Vec<&[ColumnSet]> = children.iter().map(Vec::as_slice).collect();
+    logical.derive_equivalences(&children)
+}
+
+/// Derives the logical properties of a logical expression from the expression and its
+/// descendants, for rules that need properties such as unique keys outside of the memo table.
+///
+/// # Panics
+///
+/// Panics if the expression or any of its descendants is not a logical expression.
+pub fn logical_properties(expr: &Expression, estimator: &Estimator) -> LogicalProperties {
+    let Expression::Logical(logical) = expr else {
+        panic!("only logical expressions have logical properties");
+    };
+
+    let children: Vec<LogicalProperties> = expr
+        .children()
+        .iter()
+        .map(|child| logical_properties(child, estimator))
+        .collect();
+    let children: Vec<&LogicalProperties> = children.iter().collect();
+    logical.derive_properties(&children, estimator)
+}
+
+/// Returns every column that the predicates and operators of a logical expression and its
+/// descendants reference, including the outer references of correlated subqueries (see [`Apply`]).
+///
+/// # Panics
+///
+/// Panics if the expression or any of its descendants is not a logical expression.
+pub fn referenced_columns(expr: &Expression) -> ColumnSet {
+    let Expression::Logical(logical) = expr else {
+        panic!("only logical expressions have structural column references");
+    };
+
+    let predicates = |predicates: &[Arc<ScalarExpression>]| -> ColumnSet {
+        predicates
+            .iter()
+            .flat_map(|predicate| predicate.columns())
+            .collect()
+    };
+
+    let mut columns = match logical {
+        LogicalExpression::Scan(scan) => predicates(&scan.filters),
+        LogicalExpression::Filter(filter) => predicates(&filter.filters),
+        LogicalExpression::Project(project) => project.columns.iter().copied().collect(),
+        LogicalExpression::Join(join) => predicates(&join.conditions),
+        LogicalExpression::Apply(apply) => predicates(&apply.conditions),
+        LogicalExpression::Aggregate(aggregate) => aggregate.input_columns(),
+        LogicalExpression::Sort(sort) => sort.order.iter().map(|key| key.column).collect(),
+        LogicalExpression::Limit(_)
+        | LogicalExpression::Union(_)
+        | LogicalExpression::Intersect(_)
+        | LogicalExpression::Except(_)
+        | LogicalExpression::Empty(_) => ColumnSet::new(),
+    };
+    for child in expr.children() {
+        columns.extend(referenced_columns(&child));
+    }
+    columns
+}
+
+/// Returns the columns of `outer` that a logical expression references without producing them
+/// itself, which makes it correlated with the expression that produces them.
+pub fn outer_references(expr: &Expression, outer: &ColumnSet) -> ColumnSet {
+    referenced_columns(expr)
+        .intersection(outer)
+        .copied()
+        .collect()
+}
+
+/// Returns the equivalence classes of the children of an expression, to pass on to
+/// [`LogicalRelation::derive_equivalences`].
+fn child_equivalences<'a>(children: &[&'a LogicalProperties]) -> Vec<&'a [ColumnSet]> {
+    children
+        .iter()
+        .map(|child| child.equivalences.as_slice())
+        .collect()
+}
+
+#[derive(Debug, Clone)]
 pub struct Scan {
     pub table_id: usize,
-    pub filters: (),
+    /// The columns that this scan outputs, which are either columns of the table or the columns of
+    /// a scan registered with [`Catalog::add_scan`](crate::catalog::Catalog::add_scan).
+    pub columns: Vec<ColumnId>,
+    /// The predicates that every output row satisfies (a conjunction).
+    pub filters: Vec<Arc<ScalarExpression>>,
+}
+
+/// Scans are equal if they have the same filters in any order, like their fingerprints.
+impl PartialEq for Scan {
+    fn eq(&self, other: &Self) -> bool {
+        self.table_id == other.table_id
+            && self.columns == other.columns
+            && same_conjunction(&self.filters, &other.filters)
+    }
 }
 
 impl Relation for Scan {
@@ -24,14 +185,96 @@ impl Relation for Scan {
     fn physical_properties(&self) -> Vec<PhysicalProperties> {
         vec![]
     }
+
+    fn with_children(&self, _: Vec<Arc<Expression>>) -> Expression {
+        Expression::Logical(LogicalExpression::Scan(self.clone()))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        self.table_id.hash(state);
+        self.columns.hash(state);
+        hash_conjunction(&self.filters, state);
+    }
 }
 
-#[derive(Debug)]
+impl LogicalRelation for Scan {
+    fn output_columns(&self) -> Vec<ColumnId> {
+        self.columns.clone()
+    }
+
+    fn derive_properties(
+        &self,
+        _: &[&LogicalProperties],
+        estimator: &Estimator,
+    ) -> LogicalProperties {
+        let catalog = estimator.catalog();
+        let table = catalog
+            .table(self.table_id)
+            .expect("scanned table must exist in the catalog");
+
+        let schema = self
+            .columns
+            .iter()
+            .map(|&id| {
+                let column = catalog
+                    .column(id)
+                    .expect("scanned column must exist in the catalog");
+                Field {
+                    id,
+                    data_type: column.data_type,
+                    nullable: column.nullable,
+                }
+            })
+            .collect();
+
+        // The keys of the table are declared over the columns of the table, which this scan may
+        // have its own instances of. Only the keys that are fully contained in the scanned columns are
+        // still keys.
+        let instance = |column| {
+            self.columns
+                .first()
+                .map_or(column, |&scanned| catalog.scan_column(scanned, column))
+        };
+        let unique_keys = table
+            .unique_keys
+            .iter()
+            .map(|key| {
+                key.iter()
+                    .map(|&column| instance(column))
+                    .collect::<ColumnSet>()
+            })
+            .filter(|key| key.iter().all(|column| self.columns.contains(column)))
+            .collect();
+
+        LogicalProperties {
+            schema,
+            row_count: estimator.filter_row_count(table.estimated_row_count(), &self.filters),
+            unique_keys: minimize_keys(unique_keys),
+            tables: BTreeSet::from([self.table_id]),
+            equivalences: self.derive_equivalences(&[]),
+        }
+    }
+
+    fn derive_equivalences(&self, _: &[&[ColumnSet]]) -> Vec<ColumnSet> {
+        let mut equivalences = vec![];
+        add_equalities(&mut equivalences, equalities(&self.filters));
+        restrict(&equivalences, &self.columns)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Filter {
-    pub filters: (),
+    /// The predicates that every output row satisfies (a conjunction).
+    pub filters: Vec<Arc<ScalarExpression>>,
     pub children: Arc<Expression>,
 }
 
+impl PartialEq for Filter {
+    fn eq(&self, other: &Self) -> bool {
+        same_conjunction(&self.filters, &other.filters) && self.children == other.children
+    }
+}
+
 impl Relation for Filter {
     fn children(&self) -> Vec<Arc<Expression>> {
         vec![self.children.clone()]
@@ -40,15 +283,178 @@ impl Relation for Filter {
     fn physical_properties(&self) -> Vec<PhysicalProperties> {
         vec![]
     }
+
+    fn with_children(&self, children: Vec<Arc<Expression>>) -> Expression {
+        let [children] = children.try_into().expect("a filter has exactly one child");
+        Expression::Logical(LogicalExpression::Filter(Filter {
+            children,
+            ..self.clone()
+        }))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        hash_conjunction(&self.filters, state);
+    }
 }
 
-#[derive(Debug)]
+impl LogicalRelation for Filter {
+    fn output_columns(&self) -> Vec<ColumnId> {
+        output_columns(&self.children)
+    }
+
+    fn derive_properties(
+        &self,
+        children: &[&LogicalProperties],
+        estimator: &Estimator,
+    ) -> LogicalProperties {
+        let child = children[0];
+
+        LogicalProperties {
+            schema: child.schema.clone(),
+            row_count: estimator.filter_row_count(child.row_count, &self.filters),
+            unique_keys: child.unique_keys.clone(),
+            tables: child.tables.clone(),
+            equivalences: self.derive_equivalences(&child_equivalences(children)),
+        }
+    }
+
+    fn derive_equivalences(&self, children: &[&[ColumnSet]]) -> Vec<ColumnSet> {
+        let mut equivalences = children[0].to_vec();
+        add_equalities(&mut equivalences, equalities(&self.filters));
+        equivalences
+    }
+}
+
+/// Outputs a subset of the columns of its child, in the given order.
+///
+/// TODO: Projections can only reference existing columns for now. Computing new columns out of
+/// scalar expressions needs scalar projections, whose results can be registered with
+/// [`Catalog::add_derived_column`](crate::catalog::Catalog::add_derived_column).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Project {
+    pub columns: Vec<ColumnId>,
+    pub children: Arc<Expression>,
+}
+
+impl Relation for Project {
+    fn children(&self) -> Vec<Arc<Expression>> {
+        vec![self.children.clone()]
+    }
+
+    fn physical_properties(&self) -> Vec<PhysicalProperties> {
+        vec![]
+    }
+
+    fn with_children(&self, children: Vec<Arc<Expression>>) -> Expression {
+        let [children] = children
+            .try_into()
+            .expect("a projection has exactly one child");
+        Expression::Logical(LogicalExpression::Project(Project {
+            children,
+            ..self.clone()
+        }))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        self.columns.hash(state);
+    }
+}
+
+impl LogicalRelation for Project {
+    fn output_columns(&self) -> Vec<ColumnId> {
+        self.columns.clone()
+    }
+
+    fn derive_properties(
+        &self,
+        children: &[&LogicalProperties],
+        _: &Estimator,
+    ) -> LogicalProperties {
+        let child = children[0];
+
+        let schema = self
+            .columns
+            .iter()
+            .map(|&id| {
+                child
+                    .field(id)
+                    .expect("projected column must be an output of the child")
+                    .clone()
+            })
+            .collect();
+
+        // Only the keys that are fully contained in the projected columns are still keys.
+        let unique_keys = child
+            .unique_keys
+            .iter()
+            .filter(|key| key.iter().all(|column| self.columns.contains(column)))
+            .cloned()
+            .collect();
+
+        LogicalProperties {
+            schema,
+            row_count: child.row_count,
+            unique_keys,
+            tables: child.tables.clone(),
+            equivalences: self.derive_equivalences(&child_equivalences(children)),
+        }
+    }
+
+    fn derive_equivalences(&self, children: &[&[ColumnSet]]) -> Vec<ColumnSet> {
+        restrict(children[0], &self.columns)
+    }
+}
+
+/// The different types of joins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum JoinType {
+    Inner,
+    LeftOuter,
+    RightOuter,
+    FullOuter,
+    /// Outputs the rows of the left side that have at least one match on the right side.
+    LeftSemi,
+    /// Outputs the rows of the left side that have no match on the right side.
+    LeftAnti,
+}
+
+impl JoinType {
+    /// Returns `true` if the join outputs the columns of the right side.
+    pub fn outputs_right(&self) -> bool {
+        !matches!(self, JoinType::LeftSemi | JoinType::LeftAnti)
+    }
+
+    /// Returns the join type with the left and right sides swapped, if there is one.
+    pub fn commute(&self) -> Option<JoinType> {
+        match self {
+            JoinType::Inner => Some(JoinType::Inner),
+            JoinType::LeftOuter => Some(JoinType::RightOuter),
+            JoinType::RightOuter => Some(JoinType::LeftOuter),
+            JoinType::FullOuter => Some(JoinType::FullOuter),
+            JoinType::LeftSemi | JoinType::LeftAnti => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Join {
-    pub join_type: (),
+    pub join_type: JoinType,
+    /// The predicates that every pair of joined rows satisfies (a conjunction). A join without any
+    /// conditions is a cross product.
+    pub conditions: Vec<Arc<ScalarExpression>>,
     pub left: Arc<Expression>,
     pub right: Arc<Expression>,
 }
 
+impl PartialEq for Join {
+    fn eq(&self, other: &Self) -> bool {
+        self.join_type == other.join_type
+            && same_conjunction(&self.conditions, &other.conditions)
+            && self.left == other.left
+            && self.right == other.right
+    }
+}
+
 impl Relation for Join {
     fn children(&self) -> Vec<Arc<Expression>> {
         vec![self.left.clone(), self.right.clone()]
@@ -57,4 +463,778 @@ impl Relation for Join {
     fn physical_properties(&self) -> Vec<PhysicalProperties> {
         vec![]
     }
+
+    fn with_children(&self, children: Vec<Arc<Expression>>) -> Expression {
+        let [left, right] = children
+            .try_into()
+            .expect("a join has exactly two children");
+        Expression::Logical(LogicalExpression::Join(Join {
+            left,
+            right,
+            ..self.clone()
+        }))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        self.join_type.hash(state);
+        hash_conjunction(&self.conditions, state);
+    }
+}
+
+impl LogicalRelation for Join {
+    fn output_columns(&self) -> Vec<ColumnId> {
+        let mut columns = output_columns(&self.left);
+        if self.join_type.outputs_right() {
+            columns.extend(output_columns(&self.right));
+        }
+        columns
+    }
+
+    fn derive_properties(
+        &self,
+        children: &[&LogicalProperties],
+        estimator: &Estimator,
+    ) -> LogicalProperties {
+        let (left, right) = (children[0], children[1]);
+        let row_count = estimator.join_row_count(self.join_type, left, right, &self.conditions);
+        let tables = left.tables.union(&right.tables).copied().collect();
+
+        // The side(s) that may be padded with `NULL`s.
+        let (left_nullable, right_nullable) = match self.join_type {
+            JoinType::LeftOuter => (false, true),
+            JoinType::RightOuter => (true, false),
+            JoinType::FullOuter => (true, true),
+            _ => (false, false),
+        };
+
+        let mut schema: Vec<Field> = left
+            .schema
+            .iter()
+            .map(|field| Field {
+                nullable: field.nullable || left_nullable,
+                ..field.clone()
+            })
+            .collect();
+
+        if !self.join_type.outputs_right() {
+            return LogicalProperties {
+                schema,
+                row_count,
+                unique_keys: left.unique_keys.clone(),
+                tables,
+                equivalences: self.derive_equivalences(&child_equivalences(children)),
+            };
+        }
+
+        schema.extend(right.schema.iter().map(|field| Field {
+            nullable: field.nullable || right_nullable,
+            ..field.clone()
+        }));
+
+        // Every output row is identified by the pair of rows it was created from.
+        let unique_keys = left
+            .unique_keys
+            .iter()
+            .flat_map(|left_key| {
+                right
+                    .unique_keys
+                    .iter()
+                    .map(move |right_key| left_key.union(right_key).copied().collect())
+            })
+            .collect();
+
+        LogicalProperties {
+            schema,
+            row_count,
+            unique_keys: minimize_keys(unique_keys),
+            tables,
+            equivalences: self.derive_equivalences(&child_equivalences(children)),
+        }
+    }
+
+    fn derive_equivalences(&self, children: &[&[ColumnSet]]) -> Vec<ColumnSet> {
+        let mut equivalences = children[0].to_vec();
+        if !self.join_type.outputs_right() {
+            return equivalences;
+        }
+
+        // The conditions of an outer join do not hold for the rows that it pads with `NULL`s.
+        equivalences.extend(children[1].iter().cloned());
+        if self.join_type == JoinType::Inner {
+            add_equalities(&mut equivalences, equalities(&self.conditions));
+        }
+        equivalences
+    }
+}
+
+/// A dependent join, which evaluates its right side once for every row of its left side.
+///
+/// This is how correlated subqueries enter the optimizer: the right side may reference columns of
+/// the left side (its outer references, see [`outer_references`]), which act as parameters that
+/// are bound to the values of the current left row. The join type says how the rows of both
+/// sides are combined:
+///
+/// - [`JoinType::Inner`] for `LATERAL` subqueries and `CROSS APPLY`,
+/// - [`JoinType::LeftOuter`] for scalar subqueries and `OUTER APPLY`,
+/// - [`JoinType::LeftSemi`] for `EXISTS` and `IN`, with the `IN` comparison as a condition,
+/// - [`JoinType::LeftAnti`] for `NOT EXISTS`. Since `NOT IN` is `NULL` rather than `TRUE` if the
+///   subquery contains a `NULL`, it has to be expressed with explicit `IS NULL` checks.
+///
+/// The [`unnesting`](crate::rules::unnesting) rules turn dependent joins into regular joins.
+#[derive(Debug, Clone)]
+pub struct Apply {
+    pub join_type: JoinType,
+    /// The predicates that every pair of joined rows satisfies (a conjunction).
+    pub conditions: Vec<Arc<ScalarExpression>>,
+    pub left: Arc<Expression>,
+    pub right: Arc<Expression>,
+}
+
+impl Apply {
+    /// Returns the regular join that combines the rows of both sides in the same way.
+    pub fn as_join(&self) -> Join {
+        Join {
+            join_type: self.join_type,
+            conditions: self.conditions.clone(),
+            left: self.left.clone(),
+            right: self.right.clone(),
+        }
+    }
+}
+
+impl PartialEq for Apply {
+    fn eq(&self, other: &Self) -> bool {
+        self.join_type == other.join_type
+            && same_conjunction(&self.conditions, &other.conditions)
+            && self.left == other.left
+            && self.right == other.right
+    }
+}
+
+impl Relation for Apply {
+    fn children(&self) -> Vec<Arc<Expression>> {
+        vec![self.left.clone(), self.right.clone()]
+    }
+
+    fn physical_properties(&self) -> Vec<PhysicalProperties> {
+        vec![]
+    }
+
+    fn with_children(&self, children: Vec<Arc<Expression>>) -> Expression {
+        let [left, right] = children
+            .try_into()
+            .expect("a dependent join has exactly two children");
+        Expression::Logical(LogicalExpression::Apply(Apply {
+            left,
+            right,
+            ..self.clone()
+        }))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        self.join_type.hash(state);
+        hash_conjunction(&self.conditions, state);
+    }
+}
+
+impl LogicalRelation for Apply {
+    fn output_columns(&self) -> Vec<ColumnId> {
+        self.as_join().output_columns()
+    }
+
+    /// The properties of a dependent join are estimated as if it was a regular join, with the
+    /// correlated predicates of the right side treated like any other predicate.
+    fn derive_properties(
+        &self,
+        children: &[&LogicalProperties],
+        estimator: &Estimator,
+    ) -> LogicalProperties {
+        LogicalProperties {
+            equivalences: self.derive_equivalences(&child_equivalences(children)),
+            ..self.as_join().derive_properties(children, estimator)
+        }
+    }
+
+    fn derive_equivalences(&self, children: &[&[ColumnSet]]) -> Vec<ColumnSet> {
+        // The correlated predicates of the right side make its columns equivalent to columns of
+        // the left side, which does not hold for the rows that are padded with `NULL`s.
+        let right = restrict(children[1], &output_columns(&self.right));
+        self.as_join().derive_equivalences(&[children[0], &right])
+    }
+}
+
+/// The aggregate functions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AggregateFunction {
+    /// The number of rows, or the number of non-`NULL` values of the argument.
+    Count,
+    Sum,
+    Min,
+    Max,
+}
+
+impl AggregateFunction {
+    /// Returns the function that combines the partial results of this function into the final
+    /// result: the counts of several partial groups are added up, for example.
+    pub fn combine(&self) -> AggregateFunction {
+        match self {
+            AggregateFunction::Count | AggregateFunction::Sum => AggregateFunction::Sum,
+            AggregateFunction::Min => AggregateFunction::Min,
+            AggregateFunction::Max => AggregateFunction::Max,
+        }
+    }
+}
+
+/// A call to an aggregate function, whose result is stored in a derived column (see
+/// [`Catalog::add_derived_column`](crate::catalog::Catalog::add_derived_column)).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AggregateCall {
+    pub function: AggregateFunction,
+    /// The column that is aggregated, or `None` for `COUNT(*)`.
+    pub argument: Option<ColumnId>,
+    pub output: ColumnId,
+}
+
+/// The phases that an aggregation can be split into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AggregatePhase {
+    /// Computes the aggregate functions over the whole input at once.
+    Complete,
+    /// Computes partial results over parts of the input (a partition, or the rows of one side of a
+    /// join), so that a group may have more than one output row.
+    Partial,
+    /// Combines the partial results of a partial aggregation. The aggregate functions of a final
+    /// aggregation take the partial results as their arguments (see
+    /// [`AggregateFunction::combine`]).
+    Final,
+}
+
+/// Groups the rows of its child by the grouping columns, and computes aggregate functions over
+/// every group. Outputs the grouping columns followed by the results of the aggregate functions.
+///
+/// Without any grouping columns, this is a scalar aggregate, which outputs exactly one row (even
+/// if its child is empty).
+///
+/// A partial aggregation stores its partial results in the same columns that the final
+/// aggregation stores its results in, since the optimizer cannot allocate new columns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aggregate {
+    pub phase: AggregatePhase,
+    pub group_by: Vec<ColumnId>,
+    pub aggregates: Vec<AggregateCall>,
+    pub children: Arc<Expression>,
+}
+
+impl Aggregate {
+    /// Returns the columns of the child that the aggregate reads.
+    pub fn input_columns(&self) -> ColumnSet {
+        let mut columns: ColumnSet = self.group_by.iter().copied().collect();
+        columns.extend(self.aggregates.iter().filter_map(|call| call.argument));
+        columns
+    }
+}
+
+impl Relation for Aggregate {
+    fn children(&self) -> Vec<Arc<Expression>> {
+        vec![self.children.clone()]
+    }
+
+    fn physical_properties(&self) -> Vec<PhysicalProperties> {
+        vec![]
+    }
+
+    fn with_children(&self, children: Vec<Arc<Expression>>) -> Expression {
+        let [children] = children
+            .try_into()
+            .expect("an aggregate has exactly one child");
+        Expression::Logical(LogicalExpression::Aggregate(Aggregate {
+            children,
+            ..self.clone()
+        }))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        self.phase.hash(state);
+        self.group_by.hash(state);
+        self.aggregates.hash(state);
+    }
+}
+
+impl LogicalRelation for Aggregate {
+    fn output_columns(&self) -> Vec<ColumnId> {
+        let mut columns = self.group_by.clone();
+        columns.extend(self.aggregates.iter().map(|call| call.output));
+        columns
+    }
+
+    fn derive_properties(
+        &self,
+        children: &[&LogicalProperties],
+        estimator: &Estimator,
+    ) -> LogicalProperties {
+        let child = children[0];
+
+        let mut schema: Vec<Field> = self
+            .group_by
+            .iter()
+            .map(|&id| {
+                child
+                    .field(id)
+                    .expect("grouping column must be an output of the child")
+                    .clone()
+            })
+            .collect();
+        schema.extend(self.aggregates.iter().map(|call| {
+            let column = estimator
+                .catalog()
+                .column(call.output)
+                .expect("aggregate result must be a derived column in the catalog");
+            Field {
+                id: call.output,
+                data_type: column.data_type,
+                nullable: column.nullable,
+            }
+        }));
+
+        // There are at most as many groups as there are combinations of distinct grouping values.
+        let row_count = self
+            .group_by
+            .iter()
+            .map(|&column| estimator.distinct_count(column, child.row_count))
+            .product::<f64>()
+            .min(child.row_count)
+            .max(1.0);
+
+        // The groups of a partial aggregation may be split over several output rows.
+        let unique_keys = match self.phase {
+            AggregatePhase::Partial => vec![],
+            AggregatePhase::Complete | AggregatePhase::Final => {
+                vec![self.group_by.iter().copied().collect()]
+            }
+        };
+
+        LogicalProperties {
+            schema,
+            row_count,
+            unique_keys,
+            tables: child.tables.clone(),
+            equivalences: self.derive_equivalences(&child_equivalences(children)),
+        }
+    }
+
+    fn derive_equivalences(&self, children: &[&[ColumnSet]]) -> Vec<ColumnSet> {
+        restrict(children[0], &self.group_by)
+    }
+}
+
+/// A column to sort by, and the direction to sort it in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SortKey {
+    pub column: ColumnId,
+    pub descending: bool,
+}
+
+/// Sorts the rows of its child by the sort keys, where every key breaks the ties of the keys
+/// before it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sort {
+    pub order: Vec<SortKey>,
+    pub children: Arc<Expression>,
+}
+
+impl Relation for Sort {
+    fn children(&self) -> Vec<Arc<Expression>> {
+        vec![self.children.clone()]
+    }
+
+    fn physical_properties(&self) -> Vec<PhysicalProperties> {
+        vec![]
+    }
+
+    fn with_children(&self, children: Vec<Arc<Expression>>) -> Expression {
+        let [children] = children.try_into().expect("a sort has exactly one child");
+        Expression::Logical(LogicalExpression::Sort(Sort {
+            children,
+            ..self.clone()
+        }))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        self.order.hash(state);
+    }
+}
+
+impl LogicalRelation for Sort {
+    fn output_columns(&self) -> Vec<ColumnId> {
+        output_columns(&self.children)
+    }
+
+    fn derive_properties(
+        &self,
+        children: &[&LogicalProperties],
+        _: &Estimator,
+    ) -> LogicalProperties {
+        children[0].clone()
+    }
+
+    fn derive_equivalences(&self, children: &[&[ColumnSet]]) -> Vec<ColumnSet> {
+        children[0].to_vec()
+    }
+}
+
+/// Skips the first `offset` rows of its child, and outputs at most `limit` of the rows after them.
+///
+/// Which rows those are is only defined if the child is a [`Sort`]; otherwise any rows will do.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Limit {
+    pub limit: usize,
+    pub offset: usize,
+    pub children: Arc<Expression>,
+}
+
+impl Relation for Limit {
+    fn children(&self) -> Vec<Arc<Expression>> {
+        vec![self.children.clone()]
+    }
+
+    fn physical_properties(&self) -> Vec<PhysicalProperties> {
+        vec![]
+    }
+
+    fn with_children(&self, children: Vec<Arc<Expression>>) -> Expression {
+        let [children] = children.try_into().expect("a limit has exactly one child");
+        Expression::Logical(LogicalExpression::Limit(Limit {
+            children,
+            ..self.clone()
+        }))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        self.limit.hash(state);
+        self.offset.hash(state);
+    }
+}
+
+impl LogicalRelation for Limit {
+    fn output_columns(&self) -> Vec<ColumnId> {
+        output_columns(&self.children)
+    }
+
+    fn derive_properties(
+        &self,
+        children: &[&LogicalProperties],
+        _: &Estimator,
+    ) -> LogicalProperties {
+        let child = children[0];
+        let row_count = (child.row_count - self.offset as f64).clamp(0.0, self.limit as f64);
+
+        LogicalProperties {
+            row_count,
+            ..child.clone()
+        }
+    }
+
+    fn derive_equivalences(&self, children: &[&[ColumnSet]]) -> Vec<ColumnSet> {
+        children[0].to_vec()
+    }
+}
+
+/// The different set operations, which compare whole rows with each other. Unlike the conditions
+/// of a join, they consider two `NULL`s to be equal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SetOperation {
+    Union,
+    Intersect,
+    Except,
+}
+
+/// The rows of all of its inputs, which all have the same number of columns: `UNION ALL` if `all`
+/// is set, and `UNION` (without duplicates) otherwise.
+///
+/// The output columns are derived columns (see
+/// [`Catalog::add_derived_column`](crate::catalog::Catalog::add_derived_column)): the `i`-th
+/// output column holds the values of the `i`-th column of every input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Union {
+    pub all: bool,
+    pub columns: Vec<ColumnId>,
+    pub inputs: Vec<Arc<Expression>>,
+}
+
+impl Relation for Union {
+    fn children(&self) -> Vec<Arc<Expression>> {
+        self.inputs.clone()
+    }
+
+    fn physical_properties(&self) -> Vec<PhysicalProperties> {
+        vec![]
+    }
+
+    fn with_children(&self, inputs: Vec<Arc<Expression>>) -> Expression {
+        Expression::Logical(LogicalExpression::Union(Union {
+            all: self.all,
+            columns: self.columns.clone(),
+            inputs,
+        }))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        self.all.hash(state);
+        self.columns.hash(state);
+        self.inputs.len().hash(state);
+    }
+}
+
+impl LogicalRelation for Union {
+    fn output_columns(&self) -> Vec<ColumnId> {
+        self.columns.clone()
+    }
+
+    fn derive_properties(
+        &self,
+        children: &[&LogicalProperties],
+        estimator: &Estimator,
+    ) -> LogicalProperties {
+        // A column is nullable if it is nullable in any of the inputs.
+        let schema = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(index, &id)| {
+                let column = estimator
+                    .catalog()
+                    .column(id)
+                    .expect("union column must be a derived column in the catalog");
+                Field {
+                    id,
+                    data_type: column.data_type,
+                    nullable: column.nullable
+                        || children.iter().any(|child| child.schema[index].nullable),
+                }
+            })
+            .collect();
+
+        // Without duplicates, the rows themselves are unique. The row count is an upper bound,
+        // since nothing is known about how many rows the inputs have in common.
+        let unique_keys = if self.all {
+            vec![]
+        } else {
+            vec![self.columns.iter().copied().collect()]
+        };
+
+        LogicalProperties {
+            schema,
+            row_count: children.iter().map(|child| child.row_count).sum(),
+            unique_keys,
+            tables: children
+                .iter()
+                .flat_map(|child| child.tables.iter().copied())
+                .collect(),
+            equivalences: vec![],
+        }
+    }
+
+    fn derive_equivalences(&self, _: &[&[ColumnSet]]) -> Vec<ColumnSet> {
+        vec![]
+    }
+}
+
+/// The rows of its left input that are also rows of its right input: `INTERSECT ALL` if `all` is
+/// set (where a row is output as many times as it is in both inputs), and `INTERSECT` (without
+/// duplicates) otherwise.
+///
+/// Both inputs have the same number of columns, which are matched by position. Every output row
+/// is a row of the left input, so the output columns are the columns of the left input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Intersect {
+    pub all: bool,
+    pub left: Arc<Expression>,
+    pub right: Arc<Expression>,
+}
+
+/// The rows of its left input that are not rows of its right input: `EXCEPT ALL` if `all` is set
+/// (where a row is output as many more times as it is in the left input than in the right input),
+/// and `EXCEPT` (without duplicates) otherwise.
+///
+/// Like for [`Intersect`], the columns of the inputs are matched by position, and the output
+/// columns are the columns of the left input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Except {
+    pub all: bool,
+    pub left: Arc<Expression>,
+    pub right: Arc<Expression>,
+}
+
+/// Derives the logical properties of an [`Intersect`] or an [`Except`], whose output rows are a
+/// subset of the rows of the left input, with at most `row_count` rows.
+fn subset_properties(
+    all: bool,
+    left: &LogicalProperties,
+    right: &LogicalProperties,
+    row_count: f64,
+) -> LogicalProperties {
+    let mut unique_keys = left.unique_keys.clone();
+    if !all {
+        unique_keys.push(left.schema.iter().map(|field| field.id).collect());
+    }
+
+    LogicalProperties {
+        schema: left.schema.clone(),
+        row_count,
+        unique_keys: minimize_keys(unique_keys),
+        tables: left.tables.union(&right.tables).copied().collect(),
+        equivalences: left.equivalences.clone(),
+    }
+}
+
+impl Relation for Intersect {
+    fn children(&self) -> Vec<Arc<Expression>> {
+        vec![self.left.clone(), self.right.clone()]
+    }
+
+    fn physical_properties(&self) -> Vec<PhysicalProperties> {
+        vec![]
+    }
+
+    fn with_children(&self, children: Vec<Arc<Expression>>) -> Expression {
+        let [left, right] = children
+            .try_into()
+            .expect("an intersection has exactly two children");
+        Expression::Logical(LogicalExpression::Intersect(Intersect {
+            all: self.all,
+            left,
+            right,
+        }))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        self.all.hash(state);
+    }
+}
+
+impl LogicalRelation for Intersect {
+    fn output_columns(&self) -> Vec<ColumnId> {
+        output_columns(&self.left)
+    }
+
+    fn derive_properties(
+        &self,
+        children: &[&LogicalProperties],
+        _: &Estimator,
+    ) -> LogicalProperties {
+        let (left, right) = (children[0], children[1]);
+        subset_properties(self.all, left, right, left.row_count.min(right.row_count))
+    }
+
+    fn derive_equivalences(&self, children: &[&[ColumnSet]]) -> Vec<ColumnSet> {
+        children[0].to_vec()
+    }
+}
+
+impl Relation for Except {
+    fn children(&self) -> Vec<Arc<Expression>> {
+        vec![self.left.clone(), self.right.clone()]
+    }
+
+    fn physical_properties(&self) -> Vec<PhysicalProperties> {
+        vec![]
+    }
+
+    fn with_children(&self, children: Vec<Arc<Expression>>) -> Expression {
+        let [left, right] = children
+            .try_into()
+            .expect("a difference has exactly two children");
+        Expression::Logical(LogicalExpression::Except(Except {
+            all: self.all,
+            left,
+            right,
+        }))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        self.all.hash(state);
+    }
+}
+
+impl LogicalRelation for Except {
+    fn output_columns(&self) -> Vec<ColumnId> {
+        output_columns(&self.left)
+    }
+
+    fn derive_properties(
+        &self,
+        children: &[&LogicalProperties],
+        _: &Estimator,
+    ) -> LogicalProperties {
+        let (left, right) = (children[0], children[1]);
+        subset_properties(self.all, left, right, left.row_count)
+    }
+
+    fn derive_equivalences(&self, children: &[&[ColumnSet]]) -> Vec<ColumnSet> {
+        children[0].to_vec()
+    }
+}
+
+/// A relation without any rows, such as a filter whose predicates can never be true.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Empty {
+    pub columns: Vec<ColumnId>,
+}
+
+impl Relation for Empty {
+    fn children(&self) -> Vec<Arc<Expression>> {
+        vec![]
+    }
+
+    fn physical_properties(&self) -> Vec<PhysicalProperties> {
+        vec![]
+    }
+
+    fn with_children(&self, _: Vec<Arc<Expression>>) -> Expression {
+        Expression::Logical(LogicalExpression::Empty(self.clone()))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        self.columns.hash(state);
+    }
+}
+
+impl LogicalRelation for Empty {
+    fn output_columns(&self) -> Vec<ColumnId> {
+        self.columns.clone()
+    }
+
+    fn derive_properties(
+        &self,
+        _: &[&LogicalProperties],
+        estimator: &Estimator,
+    ) -> LogicalProperties {
+        let schema = self
+            .columns
+            .iter()
+            .map(|&id| {
+                let column = estimator
+                    .catalog()
+                    .column(id)
+                    .expect("output column must exist in the catalog");
+                Field {
+                    id,
+                    data_type: column.data_type,
+                    nullable: column.nullable,
+                }
+            })
+            .collect();
+
+        LogicalProperties {
+            schema,
+            row_count: 0.0,
+            unique_keys: vec![],
+            tables: BTreeSet::new(),
+            equivalences: vec![],
+        }
+    }
+
+    fn derive_equivalences(&self, _: &[&[ColumnSet]]) -> Vec<ColumnSet> {
+        vec![]
+    }
 }
@@ -1,2 +1,7 @@
 pub mod logical;
 pub mod physical;
+pub mod scalar;
+pub mod simplify;
+
+#[cfg(test)]
+mod tests;
@@ -1,19 +1,52 @@
+use crate::catalog::ColumnId;
+use crate::expression::logical::{AggregateCall, AggregatePhase, JoinType, SetOperation, SortKey};
+use crate::expression::scalar::ScalarExpression;
 use crate::{Expression, PhysicalProperties, Relation};
 use enum_dispatch::enum_dispatch;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hash;
 use std::sync::Arc;
 
 #[enum_dispatch(Relation)]
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum PhysicalExpression {
     TableScan,
     IndexScan,
+    Projection,
+    Selection,
     HashJoin,
+    NestedLoopApply,
+    HashAggregate,
+    MergeSort,
+    TopN,
+    StreamingLimit,
+    UnionAll,
+    HashSetOperation,
+    SortSetOperation,
+    EmptyScan,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TableScan {
     pub table_id: usize,
-    pub filters: (),
+    /// The columns that the scan outputs. The scan also reads the columns referenced by the
+    /// filters, but no other columns.
+    pub columns: Vec<ColumnId>,
+    pub filters: Vec<Arc<ScalarExpression>>,
+}
+
+impl TableScan {
+    /// Returns every column that the scan has to read: the output columns, followed by any other
+    /// columns referenced by the filters.
+    pub fn read_columns(&self) -> Vec<ColumnId> {
+        let mut columns = self.columns.clone();
+        for column in self.filters.iter().flat_map(|filter| filter.columns()) {
+            if !columns.contains(&column) {
+                columns.push(column);
+            }
+        }
+        columns
+    }
 }
 
 impl Relation for TableScan {
@@ -24,9 +57,19 @@ impl Relation for TableScan {
     fn physical_properties(&self) -> Vec<PhysicalProperties> {
         vec![]
     }
+
+    fn with_children(&self, _: Vec<Arc<Expression>>) -> Expression {
+        Expression::Physical(PhysicalExpression::TableScan(self.clone()))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        self.table_id.hash(state);
+        self.columns.hash(state);
+        self.filters.hash(state);
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct IndexScan {
     pub table: (),
     pub filters: (),
@@ -42,12 +85,87 @@ impl Relation for IndexScan {
     fn physical_properties(&self) -> Vec<PhysicalProperties> {
         vec![PhysicalProperties::Sorted(42)]
     }
+
+    fn with_children(&self, _: Vec<Arc<Expression>>) -> Expression {
+        Expression::Physical(PhysicalExpression::IndexScan(self.clone()))
+    }
+
+    fn fingerprint(&self, _: &mut DefaultHasher) {
+        // TODO: Hash the table, filters, and index once there is a representation for them.
+    }
+}
+
+/// Outputs a subset of the columns of its child, in the given order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Projection {
+    pub columns: Vec<ColumnId>,
+    pub child: Arc<Expression>,
+}
+
+impl Relation for Projection {
+    fn children(&self) -> Vec<Arc<Expression>> {
+        vec![self.child.clone()]
+    }
+
+    fn physical_properties(&self) -> Vec<PhysicalProperties> {
+        vec![]
+    }
+
+    fn with_children(&self, children: Vec<Arc<Expression>>) -> Expression {
+        let [child] = children
+            .try_into()
+            .expect("a projection has exactly one child");
+        Expression::Physical(PhysicalExpression::Projection(Projection {
+            child,
+            ..self.clone()
+        }))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        self.columns.hash(state);
+    }
+}
+
+/// Outputs the rows of its child that satisfy all of the filters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection {
+    pub filters: Vec<Arc<ScalarExpression>>,
+    pub child: Arc<Expression>,
+}
+
+impl Relation for Selection {
+    fn children(&self) -> Vec<Arc<Expression>> {
+        vec![self.child.clone()]
+    }
+
+    fn physical_properties(&self) -> Vec<PhysicalProperties> {
+        vec![]
+    }
+
+    fn with_children(&self, children: Vec<Arc<Expression>>) -> Expression {
+        let [child] = children
+            .try_into()
+            .expect("a selection has exactly one child");
+        Expression::Physical(PhysicalExpression::Selection(Selection {
+            child,
+            ..self.clone()
+        }))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        self.filters.hash(state);
+    }
 }
 
-#[derive(Debug)]
+/// A hash join, which builds a hash table out of its left child and probes it with the rows of its
+/// right child.
+#[derive(Debug, Clone, PartialEq)]
 pub struct HashJoin {
-    pub join_type: (),
+    pub join_type: JoinType,
+    pub conditions: Vec<Arc<ScalarExpression>>,
+    /// The amount of memory (in bytes) available to the hash table of each partition.
     pub hash_table_size: usize,
+    /// The number of partitions that the join is executed over in parallel.
     pub partitions: usize,
     pub left: Arc<Expression>,
     pub right: Arc<Expression>,
@@ -61,4 +179,323 @@ impl Relation for HashJoin {
     fn physical_properties(&self) -> Vec<PhysicalProperties> {
         vec![]
     }
+
+    fn with_children(&self, children: Vec<Arc<Expression>>) -> Expression {
+        let [left, right] = children
+            .try_into()
+            .expect("a hash join has exactly two children");
+        Expression::Physical(PhysicalExpression::HashJoin(HashJoin {
+            left,
+            right,
+            ..self.clone()
+        }))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        self.join_type.hash(state);
+        self.conditions.hash(state);
+        self.hash_table_size.hash(state);
+        self.partitions.hash(state);
+    }
+}
+
+/// A dependent join that re-evaluates its right child for every row of its left child, with the
+/// outer references of the right child bound to the values of that row.
+///
+/// This is the only way to execute a correlated subquery that could not be unnested.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NestedLoopApply {
+    pub join_type: JoinType,
+    pub conditions: Vec<Arc<ScalarExpression>>,
+    pub left: Arc<Expression>,
+    pub right: Arc<Expression>,
+}
+
+impl Relation for NestedLoopApply {
+    fn children(&self) -> Vec<Arc<Expression>> {
+        vec![self.left.clone(), self.right.clone()]
+    }
+
+    fn physical_properties(&self) -> Vec<PhysicalProperties> {
+        vec![]
+    }
+
+    fn with_children(&self, children: Vec<Arc<Expression>>) -> Expression {
+        let [left, right] = children
+            .try_into()
+            .expect("a nested loop apply has exactly two children");
+        Expression::Physical(PhysicalExpression::NestedLoopApply(NestedLoopApply {
+            left,
+            right,
+            ..self.clone()
+        }))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        self.join_type.hash(state);
+        self.conditions.hash(state);
+    }
+}
+
+/// A hash aggregate, which builds a hash table with an entry per group and updates the aggregates
+/// of the matching entry for every input row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HashAggregate {
+    pub phase: AggregatePhase,
+    pub group_by: Vec<ColumnId>,
+    pub aggregates: Vec<AggregateCall>,
+    pub child: Arc<Expression>,
+}
+
+impl Relation for HashAggregate {
+    fn children(&self) -> Vec<Arc<Expression>> {
+        vec![self.child.clone()]
+    }
+
+    fn physical_properties(&self) -> Vec<PhysicalProperties> {
+        vec![]
+    }
+
+    fn with_children(&self, children: Vec<Arc<Expression>>) -> Expression {
+        let [child] = children
+            .try_into()
+            .expect("a hash aggregate has exactly one child");
+        Expression::Physical(PhysicalExpression::HashAggregate(HashAggregate {
+            child,
+            ..self.clone()
+        }))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        self.phase.hash(state);
+        self.group_by.hash(state);
+        self.aggregates.hash(state);
+    }
+}
+
+/// A sort of the whole input, which has to read all of the rows of its child before it can output
+/// the first one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeSort {
+    pub order: Vec<SortKey>,
+    pub child: Arc<Expression>,
+}
+
+impl Relation for MergeSort {
+    fn children(&self) -> Vec<Arc<Expression>> {
+        vec![self.child.clone()]
+    }
+
+    fn physical_properties(&self) -> Vec<PhysicalProperties> {
+        vec![]
+    }
+
+    fn with_children(&self, children: Vec<Arc<Expression>>) -> Expression {
+        let [child] = children
+            .try_into()
+            .expect("a merge sort has exactly one child");
+        Expression::Physical(PhysicalExpression::MergeSort(MergeSort {
+            child,
+            ..self.clone()
+        }))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        self.order.hash(state);
+    }
+}
+
+/// Outputs the first rows of its child in sort order (a sort followed by a limit), keeping only the
+/// `offset + limit` smallest rows seen so far in a bounded heap instead of sorting the whole input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopN {
+    pub order: Vec<SortKey>,
+    pub limit: usize,
+    pub offset: usize,
+    pub child: Arc<Expression>,
+}
+
+impl Relation for TopN {
+    fn children(&self) -> Vec<Arc<Expression>> {
+        vec![self.child.clone()]
+    }
+
+    fn physical_properties(&self) -> Vec<PhysicalProperties> {
+        vec![]
+    }
+
+    fn with_children(&self, children: Vec<Arc<Expression>>) -> Expression {
+        let [child] = children.try_into().expect("a top-n has exactly one child");
+        Expression::Physical(PhysicalExpression::TopN(TopN {
+            child,
+            ..self.clone()
+        }))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        self.order.hash(state);
+        self.limit.hash(state);
+        self.offset.hash(state);
+    }
+}
+
+/// Skips the first `offset` rows of its child and passes on at most `limit` rows, after which it
+/// stops pulling rows from its child.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamingLimit {
+    pub limit: usize,
+    pub offset: usize,
+    pub child: Arc<Expression>,
+}
+
+impl Relation for StreamingLimit {
+    fn children(&self) -> Vec<Arc<Expression>> {
+        vec![self.child.clone()]
+    }
+
+    fn physical_properties(&self) -> Vec<PhysicalProperties> {
+        vec![]
+    }
+
+    fn with_children(&self, children: Vec<Arc<Expression>>) -> Expression {
+        let [child] = children
+            .try_into()
+            .expect("a streaming limit has exactly one child");
+        Expression::Physical(PhysicalExpression::StreamingLimit(StreamingLimit {
+            child,
+            ..self.clone()
+        }))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        self.limit.hash(state);
+        self.offset.hash(state);
+    }
+}
+
+/// Passes on the rows of each of its children in turn, renaming their columns to its own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnionAll {
+    pub columns: Vec<ColumnId>,
+    pub children: Vec<Arc<Expression>>,
+}
+
+impl Relation for UnionAll {
+    fn children(&self) -> Vec<Arc<Expression>> {
+        self.children.clone()
+    }
+
+    fn physical_properties(&self) -> Vec<PhysicalProperties> {
+        vec![]
+    }
+
+    fn with_children(&self, children: Vec<Arc<Expression>>) -> Expression {
+        Expression::Physical(PhysicalExpression::UnionAll(UnionAll {
+            columns: self.columns.clone(),
+            children,
+        }))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        self.columns.hash(state);
+        self.children.len().hash(state);
+    }
+}
+
+/// A set operation that builds a hash table keyed by whole rows, with a count per input for every
+/// distinct row.
+///
+/// A union inserts the rows of every child into the hash table. An intersection or a difference
+/// only builds the hash table out of its left child, and probes it with the rows of its right
+/// child.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HashSetOperation {
+    pub operation: SetOperation,
+    /// Whether duplicates are kept (`UNION ALL`, `INTERSECT ALL` or `EXCEPT ALL`).
+    pub all: bool,
+    pub columns: Vec<ColumnId>,
+    pub children: Vec<Arc<Expression>>,
+}
+
+impl Relation for HashSetOperation {
+    fn children(&self) -> Vec<Arc<Expression>> {
+        self.children.clone()
+    }
+
+    fn physical_properties(&self) -> Vec<PhysicalProperties> {
+        vec![]
+    }
+
+    fn with_children(&self, children: Vec<Arc<Expression>>) -> Expression {
+        Expression::Physical(PhysicalExpression::HashSetOperation(HashSetOperation {
+            children,
+            ..self.clone()
+        }))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        self.operation.hash(state);
+        self.all.hash(state);
+        self.columns.hash(state);
+        self.children.len().hash(state);
+    }
+}
+
+/// A set operation that sorts every child by all of its columns, and then merges the sorted
+/// children, comparing the current rows of every child.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortSetOperation {
+    pub operation: SetOperation,
+    /// Whether duplicates are kept (`UNION ALL`, `INTERSECT ALL` or `EXCEPT ALL`).
+    pub all: bool,
+    pub columns: Vec<ColumnId>,
+    pub children: Vec<Arc<Expression>>,
+}
+
+impl Relation for SortSetOperation {
+    fn children(&self) -> Vec<Arc<Expression>> {
+        self.children.clone()
+    }
+
+    fn physical_properties(&self) -> Vec<PhysicalProperties> {
+        vec![]
+    }
+
+    fn with_children(&self, children: Vec<Arc<Expression>>) -> Expression {
+        Expression::Physical(PhysicalExpression::SortSetOperation(SortSetOperation {
+            children,
+            ..self.clone()
+        }))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        self.operation.hash(state);
+        self.all.hash(state);
+        self.columns.hash(state);
+        self.children.len().hash(state);
+    }
+}
+
+/// Produces no rows at all.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmptyScan {
+    pub columns: Vec<ColumnId>,
+}
+
+impl Relation for EmptyScan {
+    fn children(&self) -> Vec<Arc<Expression>> {
+        vec![]
+    }
+
+    fn physical_properties(&self) -> Vec<PhysicalProperties> {
+        vec![]
+    }
+
+    fn with_children(&self, _: Vec<Arc<Expression>>) -> Expression {
+        Expression::Physical(PhysicalExpression::EmptyScan(self.clone()))
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        self.columns.hash(state);
+    }
 }
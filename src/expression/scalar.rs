@@ -0,0 +1,252 @@
+//! Scalar (predicate / SQL) expressions, such as the filters of a scan or the condition of a join.
+
+use crate::catalog::ColumnId;
+use crate::value::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+/// A scalar expression that evaluates to a single value for every row.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ScalarExpression {
+    Column(ColumnId),
+    Literal(Value),
+    Compare {
+        op: CompareOperator,
+        left: Arc<ScalarExpression>,
+        right: Arc<ScalarExpression>,
+    },
+    And(Vec<Arc<ScalarExpression>>),
+    Or(Vec<Arc<ScalarExpression>>),
+    Not(Arc<ScalarExpression>),
+    IsNull(Arc<ScalarExpression>),
+    IsNotNull(Arc<ScalarExpression>),
+    InList {
+        expr: Arc<ScalarExpression>,
+        list: Vec<Arc<ScalarExpression>>,
+        negated: bool,
+    },
+    /// A SQL `LIKE` pattern match, where `%` matches any string and `_` matches any character.
+    Like {
+        expr: Arc<ScalarExpression>,
+        pattern: String,
+        negated: bool,
+    },
+}
+
+/// The different comparison operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompareOperator {
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+}
+
+impl CompareOperator {
+    /// Returns the operator that gives the same result when the two sides are swapped.
+    pub fn flip(&self) -> CompareOperator {
+        match self {
+            CompareOperator::Eq => CompareOperator::Eq,
+            CompareOperator::NotEq => CompareOperator::NotEq,
+            CompareOperator::Lt => CompareOperator::Gt,
+            CompareOperator::LtEq => CompareOperator::GtEq,
+            CompareOperator::Gt => CompareOperator::Lt,
+            CompareOperator::GtEq => CompareOperator::LtEq,
+        }
+    }
+
+    /// Returns the operator that gives the opposite result (ignoring `NULL`s).
+    pub fn negate(&self) -> CompareOperator {
+        match self {
+            CompareOperator::Eq => CompareOperator::NotEq,
+            CompareOperator::NotEq => CompareOperator::Eq,
+            CompareOperator::Lt => CompareOperator::GtEq,
+            CompareOperator::LtEq => CompareOperator::Gt,
+            CompareOperator::Gt => CompareOperator::LtEq,
+            CompareOperator::GtEq => CompareOperator::Lt,
+        }
+    }
+}
+
+impl ScalarExpression {
+    pub fn column(id: ColumnId) -> Arc<Self> {
+        Arc::new(ScalarExpression::Column(id))
+    }
+
+    pub fn literal(value: impl Into<Value>) -> Arc<Self> {
+        Arc::new(ScalarExpression::Literal(value.into()))
+    }
+
+    pub fn compare(
+        op: CompareOperator,
+        left: Arc<ScalarExpression>,
+        right: Arc<ScalarExpression>,
+    ) -> Arc<Self> {
+        Arc::new(ScalarExpression::Compare { op, left, right })
+    }
+
+    /// A shorthand for the very common `left = right`.
+    pub fn eq(left: Arc<ScalarExpression>, right: Arc<ScalarExpression>) -> Arc<Self> {
+        Self::compare(CompareOperator::Eq, left, right)
+    }
+
+    /// Returns the identifiers of every column referenced by this expression.
+    pub fn columns(&self) -> BTreeSet<ColumnId> {
+        let mut columns = BTreeSet::new();
+        self.collect_columns(&mut columns);
+        columns
+    }
+
+    fn collect_columns(&self, columns: &mut BTreeSet<ColumnId>) {
+        match self {
+            ScalarExpression::Column(id) => {
+                columns.insert(*id);
+            }
+            ScalarExpression::Literal(_) => {}
+            _ => self
+                .children()
+                .iter()
+                .for_each(|child| child.collect_columns(columns)),
+        }
+    }
+
+    /// Returns the direct children of this expression.
+    pub fn children(&self) -> Vec<Arc<ScalarExpression>> {
+        match self {
+            ScalarExpression::Column(_) | ScalarExpression::Literal(_) => vec![],
+            ScalarExpression::Compare { left, right, .. } => vec![left.clone(), right.clone()],
+            ScalarExpression::And(children) | ScalarExpression::Or(children) => children.clone(),
+            ScalarExpression::Not(child)
+            | ScalarExpression::IsNull(child)
+            | ScalarExpression::IsNotNull(child)
+            | ScalarExpression::Like { expr: child, .. } => vec![child.clone()],
+            ScalarExpression::InList { expr, list, .. } => std::iter::once(expr.clone())
+                .chain(list.iter().cloned())
+                .collect(),
+        }
+    }
+
+    /// Returns a copy of this expression where every column in `renames` is replaced by the column
+    /// it maps to, such as when a predicate over the output of a union is pushed into its inputs.
+    pub fn rename_columns(&self, renames: &HashMap<ColumnId, ColumnId>) -> Arc<ScalarExpression> {
+        let rename = |child: &Arc<ScalarExpression>| child.rename_columns(renames);
+        let renamed = match self {
+            ScalarExpression::Column(id) => {
+                ScalarExpression::Column(renames.get(id).copied().unwrap_or(*id))
+            }
+            ScalarExpression::Literal(_) => self.clone(),
+            ScalarExpression::Compare { op, left, right } => ScalarExpression::Compare {
+                op: *op,
+                left: rename(left),
+                right: rename(right),
+            },
+            ScalarExpression::And(children) => {
+                ScalarExpression::And(children.iter().map(rename).collect())
+            }
+            ScalarExpression::Or(children) => {
+                ScalarExpression::Or(children.iter().map(rename).collect())
+            }
+            ScalarExpression::Not(child) => ScalarExpression::Not(rename(child)),
+            ScalarExpression::IsNull(child) => ScalarExpression::IsNull(rename(child)),
+            ScalarExpression::IsNotNull(child) => ScalarExpression::IsNotNull(rename(child)),
+            ScalarExpression::InList {
+                expr,
+                list,
+                negated,
+            } => ScalarExpression::InList {
+                expr: rename(expr),
+                list: list.iter().map(rename).collect(),
+                negated: *negated,
+            },
+            ScalarExpression::Like {
+                expr,
+                pattern,
+                negated,
+            } => ScalarExpression::Like {
+                expr: rename(expr),
+                pattern: pattern.clone(),
+                negated: *negated,
+            },
+        };
+        Arc::new(renamed)
+    }
+
+    /// If this is a comparison between a column and a literal, returns the column, the operator
+    /// (with the column on the left), and the literal.
+    pub fn as_column_comparison(&self) -> Option<(ColumnId, CompareOperator, &Value)> {
+        let ScalarExpression::Compare { op, left, right } = self else {
+            return None;
+        };
+
+        match (left.as_ref(), right.as_ref()) {
+            (ScalarExpression::Column(column), ScalarExpression::Literal(value)) => {
+                Some((*column, *op, value))
+            }
+            (ScalarExpression::Literal(value), ScalarExpression::Column(column)) => {
+                Some((*column, op.flip(), value))
+            }
+            _ => None,
+        }
+    }
+
+    /// If this is an equality between two columns, returns the two columns.
+    pub fn as_column_equality(&self) -> Option<(ColumnId, ColumnId)> {
+        let ScalarExpression::Compare {
+            op: CompareOperator::Eq,
+            left,
+            right,
+        } = self
+        else {
+            return None;
+        };
+
+        match (left.as_ref(), right.as_ref()) {
+            (ScalarExpression::Column(left), ScalarExpression::Column(right)) => {
+                Some((*left, *right))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Splits a predicate into its top-level conjuncts, flattening nested `AND`s.
+pub fn conjuncts(predicate: &Arc<ScalarExpression>) -> Vec<Arc<ScalarExpression>> {
+    match predicate.as_ref() {
+        ScalarExpression::And(children) => children.iter().flat_map(conjuncts).collect(),
+        _ => vec![predicate.clone()],
+    }
+}
+
+/// Hashes a conjunction of predicates independently of the order of the predicates, so that the
+/// same conjunction always has the same fingerprint no matter how its predicates were collected.
+pub fn hash_conjunction(predicates: &[Arc<ScalarExpression>], state: &mut DefaultHasher) {
+    let mut hashes: Vec<u64> = predicates
+        .iter()
+        .map(|predicate| {
+            let mut predicate_state = DefaultHasher::new();
+            predicate.hash(&mut predicate_state);
+            predicate_state.finish()
+        })
+        .collect();
+    hashes.sort_unstable();
+    hashes.hash(state);
+}
+
+/// Checks if two conjunctions consist of the same predicates, independently of the order of the
+/// predicates (like [`hash_conjunction`]).
+pub fn same_conjunction(left: &[Arc<ScalarExpression>], right: &[Arc<ScalarExpression>]) -> bool {
+    let count = |predicates: &[Arc<ScalarExpression>], predicate| {
+        predicates
+            .iter()
+            .filter(|other| *other == predicate)
+            .count()
+    };
+    left.len() == right.len()
+        && left
+            .iter()
+            .all(|predicate| count(left, predicate) == count(right, predicate))
+}
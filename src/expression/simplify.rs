@@ -0,0 +1,507 @@
+//! Simplification of scalar expressions.
+//!
+//! [`simplify`] folds constants, removes the boolean identities (`x AND TRUE`, `x OR FALSE`),
+//! pushes `NOT` down to the leaves (with De Morgan's laws), and replaces conjunctions that can never
+//! be true (such as `x > 5 AND x < 3`) with `FALSE`. Every rewrite follows SQL's three-valued
+//! logic, so a simplified expression evaluates to the same value (including `NULL`) for every row.
+//!
+//! [`rejects_nulls`] uses the simplifier to check if a predicate can only be true when some
+//! columns are not `NULL`.
+//!
+//! [`to_cnf`] and [`to_dnf`] convert a predicate into conjunctive or disjunctive normal form, which
+//! can grow exponentially, so they give up once the result would have too many clauses.
+
+use super::scalar::{conjuncts, CompareOperator, ScalarExpression};
+use crate::catalog::ColumnId;
+use crate::properties::ColumnSet;
+use crate::value::Value;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// The maximum number of clauses that [`to_cnf`] and [`to_dnf`] produce.
+pub const MAX_CLAUSES: usize = 256;
+
+/// Simplifies a scalar expression bottom-up.
+pub fn simplify(expr: &Arc<ScalarExpression>) -> Arc<ScalarExpression> {
+    match expr.as_ref() {
+        ScalarExpression::Column(_) | ScalarExpression::Literal(_) => expr.clone(),
+        ScalarExpression::Compare { op, left, right } => {
+            compare(*op, simplify(left), simplify(right))
+        }
+        ScalarExpression::And(children) => and(children.iter().map(simplify).collect()),
+        ScalarExpression::Or(children) => or(children.iter().map(simplify).collect()),
+        ScalarExpression::Not(child) => not(&simplify(child)),
+        ScalarExpression::IsNull(child) => {
+            let child = simplify(child);
+            match child.as_ref() {
+                ScalarExpression::Literal(value) => ScalarExpression::literal(value.is_null()),
+                _ => Arc::new(ScalarExpression::IsNull(child)),
+            }
+        }
+        ScalarExpression::IsNotNull(child) => {
+            let child = simplify(child);
+            match child.as_ref() {
+                ScalarExpression::Literal(value) => ScalarExpression::literal(!value.is_null()),
+                _ => Arc::new(ScalarExpression::IsNotNull(child)),
+            }
+        }
+        ScalarExpression::InList {
+            expr,
+            list,
+            negated,
+        } => in_list(
+            simplify(expr),
+            list.iter().map(simplify).collect(),
+            *negated,
+        ),
+        ScalarExpression::Like {
+            expr,
+            pattern,
+            negated,
+        } => {
+            let expr = simplify(expr);
+            match expr.as_ref() {
+                ScalarExpression::Literal(Value::Null) => expr,
+                _ => Arc::new(ScalarExpression::Like {
+                    expr,
+                    pattern: pattern.clone(),
+                    negated: *negated,
+                }),
+            }
+        }
+    }
+}
+
+/// Simplifies a conjunction of predicates, as they appear in filters and join conditions.
+///
+/// Since a filter rejects a row both when a predicate is `FALSE` and when it is `NULL`, this also
+/// applies the rewrites that only keep the difference between the two, such as turning `a = a`
+/// into `a IS NOT NULL`.
+///
+/// Returns `None` if the conjunction can never be true, in which case no row satisfies it.
+/// Predicates that are always true are removed.
+pub fn simplify_conjunction(
+    predicates: &[Arc<ScalarExpression>],
+) -> Option<Vec<Arc<ScalarExpression>>> {
+    let simplified = simplify(&Arc::new(ScalarExpression::And(predicates.to_vec())));
+    let simplified = rejecting_nulls(&simplified);
+    match simplified.as_ref() {
+        ScalarExpression::Literal(Value::Boolean(true)) => Some(vec![]),
+        ScalarExpression::Literal(Value::Boolean(false)) => None,
+        _ => Some(conjuncts(&simplified)),
+    }
+}
+
+/// Checks if a predicate rejects (is `FALSE` or `NULL` for) every row in which all of the given
+/// columns are `NULL`, such as the rows that an outer join pads with `NULL`s.
+///
+/// This is conservative: it may return `false` for predicates that do reject those rows.
+pub fn rejects_nulls(predicate: &Arc<ScalarExpression>, columns: &ColumnSet) -> bool {
+    let substituted = substitute_nulls(predicate, columns);
+    simplify_conjunction(&[substituted]).is_none()
+}
+
+/// Replaces every reference to one of the given columns with a `NULL` literal.
+fn substitute_nulls(expr: &Arc<ScalarExpression>, columns: &ColumnSet) -> Arc<ScalarExpression> {
+    let substitute = |child: &Arc<ScalarExpression>| substitute_nulls(child, columns);
+    Arc::new(match expr.as_ref() {
+        ScalarExpression::Column(column) if columns.contains(column) => {
+            ScalarExpression::Literal(Value::Null)
+        }
+        ScalarExpression::Column(_) | ScalarExpression::Literal(_) => return expr.clone(),
+        ScalarExpression::Compare { op, left, right } => ScalarExpression::Compare {
+            op: *op,
+            left: substitute(left),
+            right: substitute(right),
+        },
+        ScalarExpression::And(children) => {
+            ScalarExpression::And(children.iter().map(substitute).collect())
+        }
+        ScalarExpression::Or(children) => {
+            ScalarExpression::Or(children.iter().map(substitute).collect())
+        }
+        ScalarExpression::Not(child) => ScalarExpression::Not(substitute(child)),
+        ScalarExpression::IsNull(child) => ScalarExpression::IsNull(substitute(child)),
+        ScalarExpression::IsNotNull(child) => ScalarExpression::IsNotNull(substitute(child)),
+        ScalarExpression::InList {
+            expr,
+            list,
+            negated,
+        } => ScalarExpression::InList {
+            expr: substitute(expr),
+            list: list.iter().map(substitute).collect(),
+            negated: *negated,
+        },
+        ScalarExpression::Like {
+            expr,
+            pattern,
+            negated,
+        } => ScalarExpression::Like {
+            expr: substitute(expr),
+            pattern: pattern.clone(),
+            negated: *negated,
+        },
+    })
+}
+
+/// Simplifies a simplified predicate whose `NULL` results may be turned into `FALSE`.
+///
+/// Below `AND` and `OR` (but not `NOT`, which is already pushed down to the leaves), turning a
+/// `NULL` into `FALSE` can only turn the result from `NULL` into `FALSE` as well.
+fn rejecting_nulls(expr: &Arc<ScalarExpression>) -> Arc<ScalarExpression> {
+    match expr.as_ref() {
+        ScalarExpression::And(children) => and(children.iter().map(rejecting_nulls).collect()),
+        ScalarExpression::Or(children) => or(children.iter().map(rejecting_nulls).collect()),
+        ScalarExpression::Literal(Value::Null) => ScalarExpression::literal(false),
+        // `a = a` is `TRUE` unless `a` is `NULL`.
+        ScalarExpression::Compare { op, left, right }
+            if left == right
+                && !matches!(left.as_ref(), ScalarExpression::Literal(_))
+                && matches!(
+                    op,
+                    CompareOperator::Eq | CompareOperator::LtEq | CompareOperator::GtEq
+                ) =>
+        {
+            Arc::new(ScalarExpression::IsNotNull(left.clone()))
+        }
+        _ => expr.clone(),
+    }
+}
+
+/// Converts a predicate into conjunctive normal form: an `AND` of `OR`s of predicates that are
+/// neither.
+///
+/// Returns `None` if the result would have more than [`MAX_CLAUSES`] clauses.
+pub fn to_cnf(expr: &Arc<ScalarExpression>) -> Option<Arc<ScalarExpression>> {
+    let clauses = normal_form(&simplify(expr), true)?;
+    Some(and(clauses.into_iter().map(or).collect()))
+}
+
+/// Converts a predicate into disjunctive normal form: an `OR` of `AND`s of predicates that are
+/// neither.
+///
+/// Returns `None` if the result would have more than [`MAX_CLAUSES`] clauses.
+pub fn to_dnf(expr: &Arc<ScalarExpression>) -> Option<Arc<ScalarExpression>> {
+    let clauses = normal_form(&simplify(expr), false)?;
+    Some(or(clauses.into_iter().map(and).collect()))
+}
+
+/// Returns the clauses of the CNF (if `conjunctive`) or DNF of a simplified predicate.
+///
+/// The outer operator of the normal form concatenates the clauses of its children, while the inner
+/// operator distributes over them.
+fn normal_form(
+    expr: &Arc<ScalarExpression>,
+    conjunctive: bool,
+) -> Option<Vec<Vec<Arc<ScalarExpression>>>> {
+    match (expr.as_ref(), conjunctive) {
+        (ScalarExpression::And(children), true) | (ScalarExpression::Or(children), false) => {
+            let mut clauses = vec![];
+            for child in children {
+                clauses.extend(normal_form(child, conjunctive)?);
+                if clauses.len() > MAX_CLAUSES {
+                    return None;
+                }
+            }
+            Some(clauses)
+        }
+        (ScalarExpression::Or(children), true) | (ScalarExpression::And(children), false) => {
+            let mut clauses: Vec<Vec<Arc<ScalarExpression>>> = vec![vec![]];
+            for child in children {
+                let child = normal_form(child, conjunctive)?;
+                if clauses.len() * child.len() > MAX_CLAUSES {
+                    return None;
+                }
+                clauses = clauses
+                    .iter()
+                    .flat_map(|clause| {
+                        child.iter().map(move |other| {
+                            let mut clause = clause.clone();
+                            clause.extend(other.iter().cloned());
+                            clause
+                        })
+                    })
+                    .collect();
+            }
+            Some(clauses)
+        }
+        _ => Some(vec![vec![expr.clone()]]),
+    }
+}
+
+/// Simplifies a comparison of two simplified expressions.
+fn compare(
+    op: CompareOperator,
+    left: Arc<ScalarExpression>,
+    right: Arc<ScalarExpression>,
+) -> Arc<ScalarExpression> {
+    match (left.as_ref(), right.as_ref()) {
+        (ScalarExpression::Literal(Value::Null), _)
+        | (_, ScalarExpression::Literal(Value::Null)) => ScalarExpression::literal(Value::Null),
+        (ScalarExpression::Literal(l), ScalarExpression::Literal(r)) if comparable(l, r) => {
+            ScalarExpression::literal(satisfies(op, order(l, r)))
+        }
+        _ => ScalarExpression::compare(op, left, right),
+    }
+}
+
+/// Simplifies a conjunction of simplified expressions.
+fn and(children: Vec<Arc<ScalarExpression>>) -> Arc<ScalarExpression> {
+    let mut flattened: Vec<Arc<ScalarExpression>> = vec![];
+    for child in children.iter().flat_map(conjuncts) {
+        match child.as_ref() {
+            ScalarExpression::Literal(Value::Boolean(true)) => {}
+            ScalarExpression::Literal(Value::Boolean(false)) => {
+                return ScalarExpression::literal(false)
+            }
+            _ if flattened.contains(&child) => {}
+            _ => flattened.push(child),
+        }
+    }
+
+    if is_contradiction(&flattened) {
+        return ScalarExpression::literal(false);
+    }
+
+    match flattened.len() {
+        0 => ScalarExpression::literal(true),
+        1 => flattened.remove(0),
+        _ => Arc::new(ScalarExpression::And(flattened)),
+    }
+}
+
+/// Simplifies a disjunction of simplified expressions.
+fn or(children: Vec<Arc<ScalarExpression>>) -> Arc<ScalarExpression> {
+    let mut flattened: Vec<Arc<ScalarExpression>> = vec![];
+    for child in children.iter().flat_map(disjuncts) {
+        match child.as_ref() {
+            ScalarExpression::Literal(Value::Boolean(false)) => {}
+            ScalarExpression::Literal(Value::Boolean(true)) => {
+                return ScalarExpression::literal(true)
+            }
+            _ if flattened.contains(&child) => {}
+            _ => flattened.push(child),
+        }
+    }
+
+    match flattened.len() {
+        0 => ScalarExpression::literal(false),
+        1 => flattened.remove(0),
+        _ => Arc::new(ScalarExpression::Or(flattened)),
+    }
+}
+
+/// Negates a simplified expression, pushing the negation down as far as possible.
+fn not(expr: &Arc<ScalarExpression>) -> Arc<ScalarExpression> {
+    match expr.as_ref() {
+        ScalarExpression::Literal(Value::Boolean(value)) => ScalarExpression::literal(!value),
+        ScalarExpression::Literal(Value::Null) => expr.clone(),
+        ScalarExpression::Not(child) => child.clone(),
+        // De Morgan's laws hold in three-valued logic as well.
+        ScalarExpression::And(children) => or(children.iter().map(not).collect()),
+        ScalarExpression::Or(children) => and(children.iter().map(not).collect()),
+        // A comparison with a `NULL` side is `NULL` either way.
+        ScalarExpression::Compare { op, left, right } => {
+            ScalarExpression::compare(op.negate(), left.clone(), right.clone())
+        }
+        ScalarExpression::IsNull(child) => Arc::new(ScalarExpression::IsNotNull(child.clone())),
+        ScalarExpression::IsNotNull(child) => Arc::new(ScalarExpression::IsNull(child.clone())),
+        ScalarExpression::InList {
+            expr,
+            list,
+            negated,
+        } => Arc::new(ScalarExpression::InList {
+            expr: expr.clone(),
+            list: list.clone(),
+            negated: !negated,
+        }),
+        ScalarExpression::Like {
+            expr,
+            pattern,
+            negated,
+        } => Arc::new(ScalarExpression::Like {
+            expr: expr.clone(),
+            pattern: pattern.clone(),
+            negated: !negated,
+        }),
+        _ => Arc::new(ScalarExpression::Not(expr.clone())),
+    }
+}
+
+/// Simplifies an `IN` list of simplified expressions, which can be folded if all of them are
+/// literals.
+fn in_list(
+    expr: Arc<ScalarExpression>,
+    list: Vec<Arc<ScalarExpression>>,
+    negated: bool,
+) -> Arc<ScalarExpression> {
+    let folded = || -> Option<Value> {
+        let ScalarExpression::Literal(value) = expr.as_ref() else {
+            return None;
+        };
+        if value.is_null() {
+            return Some(Value::Null);
+        }
+
+        let mut has_null = false;
+        for item in &list {
+            let ScalarExpression::Literal(item) = item.as_ref() else {
+                return None;
+            };
+            if item.is_null() {
+                has_null = true;
+            } else if !comparable(value, item) {
+                return None;
+            } else if order(value, item).is_eq() {
+                return Some(Value::Boolean(!negated));
+            }
+        }
+
+        // Without a match, `x IN (..., NULL)` is `NULL`.
+        Some(if has_null {
+            Value::Null
+        } else {
+            Value::Boolean(negated)
+        })
+    };
+
+    match folded() {
+        Some(value) => ScalarExpression::literal(value),
+        None => Arc::new(ScalarExpression::InList {
+            expr,
+            list,
+            negated,
+        }),
+    }
+}
+
+/// Splits a predicate into its top-level disjuncts, flattening nested `OR`s.
+fn disjuncts(predicate: &Arc<ScalarExpression>) -> Vec<Arc<ScalarExpression>> {
+    match predicate.as_ref() {
+        ScalarExpression::Or(children) => children.iter().flat_map(disjuncts).collect(),
+        _ => vec![predicate.clone()],
+    }
+}
+
+/// The bounds that the comparisons of a conjunction place on a single column.
+#[derive(Default)]
+struct Bounds<'a> {
+    /// The largest lower bound, and whether it is inclusive.
+    lower: Option<(&'a Value, bool)>,
+    /// The smallest upper bound, and whether it is inclusive.
+    upper: Option<(&'a Value, bool)>,
+    excluded: Vec<&'a Value>,
+}
+
+impl<'a> Bounds<'a> {
+    /// Narrows the bounds with `column op value`. Returns `false` if the value cannot be compared
+    /// with the values of the earlier comparisons.
+    fn add(&mut self, op: CompareOperator, value: &'a Value) -> bool {
+        let known = self.lower.or(self.upper).map(|(value, _)| value);
+        let known = known.or(self.excluded.first().copied());
+        if known.is_some_and(|known| !comparable(known, value)) {
+            return false;
+        }
+
+        let tighter = |bound: Option<(&'a Value, bool)>, new: (&'a Value, bool), wanted| match bound
+        {
+            Some(old) if order(old.0, new.0) == wanted => Some(old),
+            Some(old) if order(old.0, new.0).is_eq() => Some((old.0, old.1 && new.1)),
+            _ => Some(new),
+        };
+
+        match op {
+            CompareOperator::Eq => {
+                self.lower = tighter(self.lower, (value, true), Ordering::Greater);
+                self.upper = tighter(self.upper, (value, true), Ordering::Less);
+            }
+            CompareOperator::NotEq => self.excluded.push(value),
+            CompareOperator::Gt | CompareOperator::GtEq => {
+                let inclusive = op == CompareOperator::GtEq;
+                self.lower = tighter(self.lower, (value, inclusive), Ordering::Greater);
+            }
+            CompareOperator::Lt | CompareOperator::LtEq => {
+                let inclusive = op == CompareOperator::LtEq;
+                self.upper = tighter(self.upper, (value, inclusive), Ordering::Less);
+            }
+        }
+        true
+    }
+
+    /// Checks if no value satisfies the bounds.
+    fn is_empty(&self) -> bool {
+        let (Some((lower, lower_inclusive)), Some((upper, upper_inclusive))) =
+            (self.lower, self.upper)
+        else {
+            return false;
+        };
+
+        match order(lower, upper) {
+            Ordering::Greater => true,
+            Ordering::Equal => {
+                !(lower_inclusive && upper_inclusive)
+                    || self
+                        .excluded
+                        .iter()
+                        .any(|excluded| order(excluded, lower).is_eq())
+            }
+            Ordering::Less => false,
+        }
+    }
+}
+
+/// Checks if the comparisons between columns and literals in a conjunction contradict each other.
+fn is_contradiction(conjuncts: &[Arc<ScalarExpression>]) -> bool {
+    let mut bounds: BTreeMap<ColumnId, Bounds> = BTreeMap::new();
+    for conjunct in conjuncts {
+        let Some((column, op, value)) = conjunct.as_column_comparison() else {
+            continue;
+        };
+        if value.is_null() {
+            continue;
+        }
+        if !bounds.entry(column).or_default().add(op, value) {
+            return false;
+        }
+    }
+
+    bounds.values().any(Bounds::is_empty)
+}
+
+/// Checks if two non-`NULL` values have types that can be compared with each other.
+fn comparable(left: &Value, right: &Value) -> bool {
+    matches!(
+        (left, right),
+        (Value::Boolean(_), Value::Boolean(_))
+            | (
+                Value::Int64(_) | Value::Float64(_),
+                Value::Int64(_) | Value::Float64(_)
+            )
+            | (Value::Utf8(_), Value::Utf8(_))
+    )
+}
+
+/// Compares two comparable values, where integers and floats are compared numerically.
+fn order(left: &Value, right: &Value) -> Ordering {
+    match (left, right) {
+        (Value::Int64(_), Value::Float64(_)) | (Value::Float64(_), Value::Int64(_)) => {
+            let (left, right) = (left.as_f64(), right.as_f64());
+            left.unwrap_or_default()
+                .total_cmp(&right.unwrap_or_default())
+        }
+        _ => left.cmp(right),
+    }
+}
+
+/// Checks if the ordering of two values satisfies a comparison.
+fn satisfies(op: CompareOperator, ordering: Ordering) -> bool {
+    match op {
+        CompareOperator::Eq => ordering.is_eq(),
+        CompareOperator::NotEq => ordering.is_ne(),
+        CompareOperator::Lt => ordering.is_lt(),
+        CompareOperator::LtEq => ordering.is_le(),
+        CompareOperator::Gt => ordering.is_gt(),
+        CompareOperator::GtEq => ordering.is_ge(),
+    }
+}
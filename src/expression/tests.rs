@@ -0,0 +1,221 @@
+use super::scalar::{CompareOperator, ScalarExpression};
+use super::simplify::{rejects_nulls, simplify, simplify_conjunction, to_cnf, to_dnf};
+use crate::catalog::ColumnId;
+use crate::properties::ColumnSet;
+use crate::value::Value;
+use std::sync::Arc;
+
+fn column(id: usize) -> Arc<ScalarExpression> {
+    ScalarExpression::column(ColumnId(id))
+}
+
+fn literal(value: impl Into<Value>) -> Arc<ScalarExpression> {
+    ScalarExpression::literal(value)
+}
+
+fn compare(
+    op: CompareOperator,
+    left: Arc<ScalarExpression>,
+    right: Arc<ScalarExpression>,
+) -> Arc<ScalarExpression> {
+    ScalarExpression::compare(op, left, right)
+}
+
+fn and(children: Vec<Arc<ScalarExpression>>) -> Arc<ScalarExpression> {
+    Arc::new(ScalarExpression::And(children))
+}
+
+fn or(children: Vec<Arc<ScalarExpression>>) -> Arc<ScalarExpression> {
+    Arc::new(ScalarExpression::Or(children))
+}
+
+fn not(child: Arc<ScalarExpression>) -> Arc<ScalarExpression> {
+    Arc::new(ScalarExpression::Not(child))
+}
+
+/// `#{id} = 1`, an opaque predicate for the normal form tests.
+fn atom(id: usize) -> Arc<ScalarExpression> {
+    ScalarExpression::eq(column(id), literal(1))
+}
+
+#[test]
+fn constants_are_folded() {
+    let x = || ScalarExpression::eq(column(0), literal(3));
+
+    // `1 < 2 AND x = 3`
+    let expr = and(vec![
+        compare(CompareOperator::Lt, literal(1), literal(2)),
+        x(),
+    ]);
+    assert_eq!(simplify(&expr), x());
+
+    // `x = 3 OR 1 = 2`
+    let expr = or(vec![x(), ScalarExpression::eq(literal(1), literal(2))]);
+    assert_eq!(simplify(&expr), x());
+
+    // `x = 3 OR NOT FALSE`
+    let expr = or(vec![x(), not(literal(false))]);
+    assert_eq!(simplify(&expr), literal(true));
+
+    // Comparisons with `NULL` are `NULL`, and numbers are compared numerically.
+    let expr = ScalarExpression::eq(literal(Value::Null), literal(1));
+    assert_eq!(simplify(&expr), literal(Value::Null));
+    let expr = ScalarExpression::eq(literal(5), literal(5.0));
+    assert_eq!(simplify(&expr), literal(true));
+
+    // `2 IN (1, NULL)` is `NULL`, and `2 NOT IN (1, 2)` is `FALSE`.
+    let in_list = |negated, list: Vec<Arc<ScalarExpression>>| {
+        Arc::new(ScalarExpression::InList {
+            expr: literal(2),
+            list,
+            negated,
+        })
+    };
+    let expr = in_list(false, vec![literal(1), literal(Value::Null)]);
+    assert_eq!(simplify(&expr), literal(Value::Null));
+    let expr = in_list(true, vec![literal(1), literal(2)]);
+    assert_eq!(simplify(&expr), literal(false));
+}
+
+#[test]
+fn negations_are_pushed_down() {
+    let like = |negated| {
+        Arc::new(ScalarExpression::Like {
+            expr: column(2),
+            pattern: "a%".to_string(),
+            negated,
+        })
+    };
+
+    // `NOT (#0 = 1 AND (#1 IS NULL OR NOT #2 LIKE 'a%'))`
+    let expr = not(and(vec![
+        atom(0),
+        or(vec![
+            Arc::new(ScalarExpression::IsNull(column(1))),
+            like(true),
+        ]),
+    ]));
+
+    // `#0 <> 1 OR (#1 IS NOT NULL AND #2 LIKE 'a%')`
+    let expected = or(vec![
+        compare(CompareOperator::NotEq, column(0), literal(1)),
+        and(vec![
+            Arc::new(ScalarExpression::IsNotNull(column(1))),
+            like(false),
+        ]),
+    ]);
+    assert_eq!(simplify(&expr), expected);
+
+    // Double negations cancel out, even for predicates that cannot be negated otherwise.
+    let boolean = column(3);
+    assert_eq!(simplify(&not(not(boolean.clone()))), boolean);
+}
+
+#[test]
+fn self_comparisons_only_become_null_checks_in_filters() {
+    let same = ScalarExpression::eq(column(0), column(0));
+
+    // `NOT (a = a)` is `NULL` if `a` is `NULL`, but `NOT (a IS NOT NULL)` would be `TRUE`.
+    assert_eq!(simplify(&same), same);
+    assert_eq!(
+        simplify_conjunction(&[same, atom(1)]),
+        Some(vec![
+            Arc::new(ScalarExpression::IsNotNull(column(0))),
+            atom(1)
+        ])
+    );
+
+    // A `NULL` predicate rejects every row, and a `TRUE` predicate none.
+    assert_eq!(simplify_conjunction(&[literal(Value::Null)]), None);
+    assert_eq!(simplify_conjunction(&[literal(true)]), Some(vec![]));
+}
+
+#[test]
+fn contradictions_are_detected() {
+    let x = |op, value: i64| compare(op, column(0), literal(value));
+
+    // `x > 5 AND x < 3`
+    let expr = and(vec![x(CompareOperator::Gt, 5), x(CompareOperator::Lt, 3)]);
+    assert_eq!(simplify(&expr), literal(false));
+
+    // `x >= 5 AND 5 >= x AND x <> 5`, where the second comparison has the literal on the left.
+    let at_most = compare(CompareOperator::GtEq, literal(5), column(0));
+    let expr = and(vec![x(CompareOperator::GtEq, 5), at_most.clone()]);
+    assert_eq!(simplify(&expr), expr);
+    let expr = and(vec![
+        x(CompareOperator::GtEq, 5),
+        at_most,
+        x(CompareOperator::NotEq, 5),
+    ]);
+    assert_eq!(simplify(&expr), literal(false));
+
+    // `x = 5 AND x > 5`, but not `x = 5 AND y > 5`.
+    let expr = and(vec![x(CompareOperator::Eq, 5), x(CompareOperator::Gt, 5)]);
+    assert_eq!(simplify(&expr), literal(false));
+    let y = compare(CompareOperator::Gt, column(1), literal(5));
+    let expr = and(vec![x(CompareOperator::Eq, 5), y]);
+    assert_eq!(simplify(&expr), expr);
+
+    // Values of types that cannot be compared are left alone.
+    let expr = and(vec![
+        x(CompareOperator::Eq, 5),
+        ScalarExpression::eq(column(0), literal("five")),
+    ]);
+    assert_eq!(simplify(&expr), expr);
+}
+
+#[test]
+fn normal_forms() {
+    // `(a AND b) OR c` is `(a OR c) AND (b OR c)` in CNF.
+    let expr = or(vec![and(vec![atom(0), atom(1)]), atom(2)]);
+    let cnf = and(vec![or(vec![atom(0), atom(2)]), or(vec![atom(1), atom(2)])]);
+    assert_eq!(to_cnf(&expr), Some(cnf));
+    assert_eq!(to_dnf(&expr), Some(expr.clone()));
+
+    // `(a OR b) AND NOT (c AND d)` is `(a AND NOT c) OR (a AND NOT d) OR (b AND ...) ...` in DNF.
+    let expr = and(vec![
+        or(vec![atom(0), atom(1)]),
+        not(and(vec![atom(2), atom(3)])),
+    ]);
+    let negated = |id| compare(CompareOperator::NotEq, column(id), literal(1));
+    let dnf = or(vec![
+        and(vec![atom(0), negated(2)]),
+        and(vec![atom(0), negated(3)]),
+        and(vec![atom(1), negated(2)]),
+        and(vec![atom(1), negated(3)]),
+    ]);
+    assert_eq!(to_dnf(&expr), Some(dnf));
+
+    // Nine pairs of alternatives have 512 combinations.
+    let expr = and((0..9)
+        .map(|pair| or(vec![atom(2 * pair), atom(2 * pair + 1)]))
+        .collect());
+    assert!(to_cnf(&expr).is_some());
+    assert_eq!(to_dnf(&expr), None);
+}
+
+#[test]
+fn null_rejection() {
+    // The columns of the side padded with `NULL`s.
+    let padded = ColumnSet::from([ColumnId(1), ColumnId(2)]);
+    let rejects = |predicate| rejects_nulls(&predicate, &padded);
+
+    assert!(rejects(atom(1)));
+    assert!(rejects(ScalarExpression::eq(column(0), column(2))));
+    assert!(rejects(not(atom(1))));
+    assert!(rejects(Arc::new(ScalarExpression::IsNotNull(column(2)))));
+    assert!(rejects(and(vec![atom(0), atom(1)])));
+    assert!(rejects(or(vec![atom(1), atom(2)])));
+    assert!(rejects(Arc::new(ScalarExpression::Like {
+        expr: column(2),
+        pattern: "a%".to_string(),
+        negated: true,
+    })));
+
+    assert!(!rejects(atom(0)));
+    assert!(!rejects(Arc::new(ScalarExpression::IsNull(column(1)))));
+    assert!(!rejects(or(vec![atom(0), atom(1)])));
+    assert!(!rejects(not(Arc::new(ScalarExpression::IsNotNull(
+        column(1)
+    )))));
+}
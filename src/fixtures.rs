@@ -0,0 +1,37 @@
+//! Fixtures shared by the tests of several modules, for queries over tables with a single column.
+
+use crate::catalog::{Catalog, ColumnId, DataType};
+use crate::expression::scalar::ScalarExpression;
+use crate::{Expression, LogicalExpression, Scan};
+use std::sync::Arc;
+
+/// Creates a catalog with a table per relation, each with a single `id` column, so that the column
+/// of relation `i` is `ColumnId(i)`.
+pub fn catalog(relations: usize) -> Arc<Catalog> {
+    let mut catalog = Catalog::new();
+    for table in 0..relations {
+        catalog.add_table(
+            &format!("t{table}"),
+            &[("id", DataType::Int64, false)],
+            100 * (table + 1),
+        );
+    }
+    Arc::new(catalog)
+}
+
+/// Scans the `id` column of relation `table_id`.
+pub fn scan(table_id: usize) -> Arc<Expression> {
+    Arc::new(Expression::Logical(LogicalExpression::Scan(Scan {
+        table_id,
+        columns: vec![ColumnId(table_id)],
+        filters: vec![],
+    })))
+}
+
+/// `t{left}.id = t{right}.id`
+pub fn edge(left: usize, right: usize) -> Arc<ScalarExpression> {
+    ScalarExpression::eq(
+        ScalarExpression::column(ColumnId(left)),
+        ScalarExpression::column(ColumnId(right)),
+    )
+}
@@ -0,0 +1,149 @@
+//! Join enumeration with DPhyp (Moerkotte and Neumann, "Dynamic Programming Strikes Back"), which
+//! enumerates every connected subgraph / complement pair of a join graph exactly once. On graphs
+//! without hyperedges, it enumerates the same pairs as DPccp.
+//!
+//! Instead of keeping the cheapest plan for every connected subgraph, every pair is added to the
+//! memo table as a join (in both orders) in the group of the subgraph, and the search engine
+//! chooses between them when it costs the physical implementations.
+
+use super::{lowest, order_joins, subsets, JoinGraph, JoinOrderOptions, RelationSet};
+use crate::rules::transformation::JOIN_REORDERING_RULES;
+use crate::{Expression, Group, GroupKey, Guidance, Memo};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Adds a logical plan to the memo table, along with every join order of the join graphs in it
+/// that does not need a cross product (unless the graph is not connected). Returns the group of
+/// the plan.
+pub fn enumerate_joins(memo: &Memo, expr: &Arc<Expression>) -> Arc<Group> {
+    let options = JoinOrderOptions {
+        greedy_threshold: usize::MAX,
+    };
+    order_joins(memo, expr, &options)
+}
+
+/// Adds every join order of a join graph that does not need a cross product to the memo table,
+/// and returns the join of all of its relations, or `None` if the graph is not connected.
+pub fn enumerate(memo: &Memo, graph: &JoinGraph) -> Option<Arc<Expression>> {
+    let mut dphyp = DPhyp {
+        memo,
+        graph,
+        plans: HashMap::new(),
+        guidance: Arc::new(Guidance::disabling(&JOIN_REORDERING_RULES)),
+    };
+    dphyp.solve();
+
+    dphyp.plans.remove(&graph.all()).map(|(joins, _)| joins)
+}
+
+/// The state of the DPhyp enumeration of a single join graph.
+struct DPhyp<'a> {
+    memo: &'a Memo,
+    graph: &'a JoinGraph,
+    /// An expression and the group for every connected subgraph enumerated so far.
+    plans: HashMap<RelationSet, (Arc<Expression>, GroupKey)>,
+    /// The guidance for the enumerated joins.
+    guidance: Arc<Guidance>,
+}
+
+impl DPhyp<'_> {
+    fn solve(&mut self) {
+        for (index, relation) in self.graph.relations.iter().enumerate() {
+            let (group, _) = self.memo.add_expression(relation, None);
+            self.plans
+                .insert(1 << index, (relation.clone(), group.key()));
+        }
+
+        for index in (0..self.graph.relations.len()).rev() {
+            let start = 1 << index;
+            self.emit_csg(start);
+            self.enumerate_csg_rec(start, below(start));
+        }
+    }
+
+    /// Extends the connected subgraph `set` with every subset of its neighborhood (excluding
+    /// `excluded`), emitting the extensions that are connected.
+    fn enumerate_csg_rec(&mut self, set: RelationSet, excluded: RelationSet) {
+        let neighborhood = self.graph.neighborhood(set, excluded);
+        for subset in subsets(neighborhood) {
+            if self.plans.contains_key(&(set | subset)) {
+                self.emit_csg(set | subset);
+            }
+        }
+
+        for subset in subsets(neighborhood) {
+            self.enumerate_csg_rec(set | subset, excluded | neighborhood);
+        }
+    }
+
+    /// Finds every connected complement of the connected subgraph `set` that only contains
+    /// relations after the smallest relation of `set`.
+    fn emit_csg(&mut self, set: RelationSet) {
+        let excluded = set | below(lowest(set));
+        let neighborhood = self.graph.neighborhood(set, excluded);
+
+        for index in (0..self.graph.relations.len()).rev() {
+            let complement = 1 << index;
+            if neighborhood & complement == 0 {
+                continue;
+            }
+
+            if self.graph.is_connected(set, complement) {
+                self.emit_csg_cmp(set, complement);
+            }
+            self.enumerate_cmp_rec(
+                set,
+                complement,
+                excluded | (below(complement) & neighborhood),
+            );
+        }
+    }
+
+    /// Extends the complement `complement` of `set` with every subset of its neighborhood
+    /// (excluding `excluded`), emitting the extensions that are connected to `set`.
+    fn enumerate_cmp_rec(
+        &mut self,
+        set: RelationSet,
+        complement: RelationSet,
+        excluded: RelationSet,
+    ) {
+        let neighborhood = self.graph.neighborhood(complement, excluded);
+        for subset in subsets(neighborhood) {
+            let extended = complement | subset;
+            if self.plans.contains_key(&extended) && self.graph.is_connected(set, extended) {
+                self.emit_csg_cmp(set, extended);
+            }
+        }
+
+        let excluded = excluded | neighborhood;
+        for subset in subsets(neighborhood) {
+            self.enumerate_cmp_rec(set, complement | subset, excluded);
+        }
+    }
+
+    /// Adds the joins of two connected subgraphs (in both orders) to the group of their union.
+    fn emit_csg_cmp(&mut self, left: RelationSet, right: RelationSet) {
+        debug_assert_eq!(left & right, 0);
+
+        let (left_expr, _) = &self.plans[&left];
+        let (right_expr, _) = &self.plans[&right];
+        let joins = [
+            self.graph.join((left, left_expr), (right, right_expr)),
+            self.graph.join((right, right_expr), (left, left_expr)),
+        ];
+
+        let union = left | right;
+        for join in joins {
+            let target = self.plans.get(&union).map(|(_, key)| *key);
+            let (group, _) =
+                self.memo
+                    .add_expression_with_guidance(&join, target, self.guidance.clone());
+            self.plans.entry(union).or_insert((join, group.key()));
+        }
+    }
+}
+
+/// Returns the set of relations up to and including the (single) relation in `set`.
+fn below(set: RelationSet) -> RelationSet {
+    set | (set - 1)
+}
@@ -0,0 +1,94 @@
+//! Greedy operator ordering (Fegaras, "A New Heuristic for Optimizing Large Queries"), which
+//! builds a single join tree for join graphs that are too large to enumerate exhaustively.
+//!
+//! Starting with every relation as its own tree, GOO repeatedly joins the two trees that are
+//! connected by an edge and whose join produces the fewest rows, until there is only one tree
+//! left. This takes `O(n^3)` estimations for `n` relations.
+//!
+//! Both orders of every join are added to the memo table, so that the search engine still chooses
+//! which side of a hash join to build on.
+
+use super::{JoinGraph, RelationSet};
+use crate::expression::logical::LogicalRelation;
+use crate::properties::LogicalProperties;
+use crate::rules::transformation::JOIN_REORDERING_RULES;
+use crate::{Expression, Guidance, LogicalExpression, Memo};
+use std::sync::Arc;
+
+/// A join tree built so far, along with the relations it joins and its logical properties.
+struct Tree {
+    relations: RelationSet,
+    expr: Arc<Expression>,
+    properties: LogicalProperties,
+}
+
+/// Adds a greedily chosen join order of a join graph to the memo table, and returns the join of
+/// all of its relations, or `None` if the graph is not connected.
+pub fn order(memo: &Memo, graph: &JoinGraph) -> Option<Arc<Expression>> {
+    let mut trees: Vec<Tree> = graph
+        .relations
+        .iter()
+        .enumerate()
+        .map(|(index, relation)| {
+            let (group, _) = memo.add_expression(relation, None);
+            Tree {
+                relations: 1 << index,
+                expr: relation.clone(),
+                properties: group.logical_properties().clone(),
+            }
+        })
+        .collect();
+
+    let guidance = Arc::new(Guidance::disabling(&JOIN_REORDERING_RULES));
+    while trees.len() > 1 {
+        let mut best: Option<(usize, usize, Tree)> = None;
+        for i in 0..trees.len() {
+            for j in i + 1..trees.len() {
+                let (left, right) = (&trees[i], &trees[j]);
+                if !graph.is_connected(left.relations, right.relations) {
+                    continue;
+                }
+
+                let candidate = join(memo, graph, left, right);
+                if best.as_ref().is_none_or(|(_, _, best)| {
+                    candidate.properties.row_count < best.properties.row_count
+                }) {
+                    best = Some((i, j, candidate));
+                }
+            }
+        }
+
+        let (i, j, joined) = best?;
+        let commuted = graph.join(
+            (trees[j].relations, &trees[j].expr),
+            (trees[i].relations, &trees[i].expr),
+        );
+        let (group, _) = memo.add_expression_with_guidance(&joined.expr, None, guidance.clone());
+        memo.add_expression_with_guidance(&commuted, Some(group.key()), guidance.clone());
+
+        trees.remove(j);
+        trees[i] = joined;
+    }
+
+    trees.pop().map(|tree| tree.expr)
+}
+
+/// Joins two trees, estimating the properties of the join without adding it to the memo table.
+fn join(memo: &Memo, graph: &JoinGraph, left: &Tree, right: &Tree) -> Tree {
+    let expr = graph.join((left.relations, &left.expr), (right.relations, &right.expr));
+    let Expression::Logical(LogicalExpression::Join(join)) = expr.as_ref() else {
+        unreachable!("the join graph creates joins");
+    };
+
+    let mut properties =
+        join.derive_properties(&[&left.properties, &right.properties], memo.estimator());
+    if let Some(row_count) = memo.estimator().overrides().row_count(&expr, &properties) {
+        properties.row_count = row_count;
+    }
+
+    Tree {
+        relations: left.relations | right.relations,
+        expr,
+        properties,
+    }
+}
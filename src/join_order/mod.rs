@@ -0,0 +1,380 @@
+//! Join ordering outside of the transformation rules.
+//!
+//! Exploring join orders with transformation rules works for a handful of relations, but the rules
+//! have to consider every binding of every expression and generate cross products along the way.
+//! The components in this module instead work on the [`JoinGraph`] of a tree of inner joins, and
+//! add the join orders that they find to the memo table directly.
+//!
+//! The relations of a join graph are the inputs of the join tree: every expression below the inner
+//! joins (and the filters between them) that is not an inner join itself.
+//!
+//! [`order_joins`] enumerates the join graphs of a plan exhaustively with [`dphyp`], unless they
+//! are too large, in which case [`greedy`] picks a single join order for them.
+
+use crate::expression::logical::output_columns;
+use crate::expression::scalar::{conjuncts, ScalarExpression};
+use crate::{Expression, Filter, Group, Join, JoinType, LogicalExpression, Memo, Relation};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub mod dphyp;
+pub mod greedy;
+
+#[cfg(test)]
+mod tests;
+
+/// A set of relations of a join graph, with bit `i` set if it contains relation `i`.
+pub type RelationSet = u64;
+
+/// The maximum number of relations in a join graph.
+pub const MAX_RELATIONS: usize = RelationSet::BITS as usize;
+
+/// The options of [`order_joins`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JoinOrderOptions {
+    /// Join graphs with more relations than this are ordered greedily instead of enumerated
+    /// exhaustively.
+    pub greedy_threshold: usize,
+}
+
+impl Default for JoinOrderOptions {
+    fn default() -> Self {
+        Self {
+            greedy_threshold: 12,
+        }
+    }
+}
+
+/// Adds a logical plan to the memo table, along with the join orders of the join graphs in it, and
+/// returns the group of the plan.
+///
+/// The transformation rules that reorder joins are disabled for the joins that are added, so the
+/// search engine only implements the join orders found here.
+pub fn order_joins(memo: &Memo, expr: &Arc<Expression>, options: &JoinOrderOptions) -> Arc<Group> {
+    let expr = order(memo, expr, options);
+    memo.add_expression(&expr, None).0
+}
+
+/// Orders the join graphs in an expression, and returns an equivalent expression that is in the
+/// memo table.
+fn order(memo: &Memo, expr: &Arc<Expression>, options: &JoinOrderOptions) -> Arc<Expression> {
+    let Some(mut graph) = JoinGraph::extract(expr) else {
+        return order_children(memo, expr, options);
+    };
+
+    // The join graphs below the inputs are independent of this one.
+    graph.relations = graph
+        .relations
+        .iter()
+        .map(|relation| order(memo, relation, options))
+        .collect();
+
+    let joins = if graph.relations.len() > options.greedy_threshold {
+        greedy::order(memo, &graph)
+    } else {
+        dphyp::enumerate(memo, &graph)
+    };
+
+    // Keep the join order of the plan if no join order covers the whole graph, which should not
+    // happen once the components of the graph are connected.
+    match joins {
+        Some(joins) => graph.filtered(joins),
+        None => order_children(memo, expr, options),
+    }
+}
+
+/// Orders the join graphs below an expression, keeping the expression itself as it is.
+fn order_children(
+    memo: &Memo,
+    expr: &Arc<Expression>,
+    options: &JoinOrderOptions,
+) -> Arc<Expression> {
+    let children = expr.children();
+    if children.is_empty() {
+        return expr.clone();
+    }
+
+    let children = children
+        .iter()
+        .map(|child| order(memo, child, options))
+        .collect();
+    Arc::new(expr.with_children(children))
+}
+
+/// An edge between two disjoint sets of relations, which says that a join between a set containing
+/// `left` and a set containing `right` is not a cross product.
+///
+/// Edges between single relations are simple edges, everything else is a hyperedge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hyperedge {
+    pub left: RelationSet,
+    pub right: RelationSet,
+}
+
+/// A predicate of a join graph, along with the relations it references.
+#[derive(Debug, Clone)]
+pub struct JoinPredicate {
+    pub predicate: Arc<ScalarExpression>,
+    pub relations: RelationSet,
+}
+
+/// The join graph (or hypergraph) of a tree of inner joins.
+#[derive(Debug, Clone)]
+pub struct JoinGraph {
+    /// The inputs of the join tree, wrapped in a filter if there are predicates that only reference
+    /// that input.
+    pub relations: Vec<Arc<Expression>>,
+    /// The predicates that reference more than one relation.
+    pub predicates: Vec<JoinPredicate>,
+    /// The predicates that do not reference any relation, which have to be applied on top of the
+    /// joins.
+    pub filters: Vec<Arc<ScalarExpression>>,
+    /// The edges derived from the predicates, plus the edges needed to connect the graph if it is
+    /// not connected. Predicates that are not comparisons between relations do not have an edge.
+    pub edges: Vec<Hyperedge>,
+}
+
+impl JoinGraph {
+    /// Extracts the join graph of an expression that is an inner join, or a filter on top of one.
+    ///
+    /// Returns `None` for other expressions, and for join trees with more than [`MAX_RELATIONS`]
+    /// relations.
+    pub fn extract(expr: &Arc<Expression>) -> Option<JoinGraph> {
+        inner_join_of(expr)?;
+
+        let (mut inputs, mut predicates) = (vec![], vec![]);
+        collect(expr, &mut inputs, &mut predicates);
+        if inputs.len() > MAX_RELATIONS {
+            return None;
+        }
+
+        let mut relation_of = HashMap::new();
+        for (index, input) in inputs.iter().enumerate() {
+            for column in output_columns(input) {
+                relation_of.insert(column, index);
+            }
+        }
+        let relations_of = |predicate: &ScalarExpression| -> RelationSet {
+            predicate
+                .columns()
+                .iter()
+                .filter_map(|column| relation_of.get(column))
+                .fold(0, |set, &relation| set | 1 << relation)
+        };
+
+        let mut local = vec![vec![]; inputs.len()];
+        let (mut join_predicates, mut filters, mut edges) = (vec![], vec![], vec![]);
+        for predicate in predicates {
+            let relations = relations_of(&predicate);
+            match relations.count_ones() {
+                0 => filters.push(predicate),
+                1 => local[relations.trailing_zeros() as usize].push(predicate),
+                _ => {
+                    edges.extend(edge_of(&predicate, relations_of));
+                    join_predicates.push(JoinPredicate {
+                        predicate,
+                        relations,
+                    });
+                }
+            }
+        }
+
+        let relations = inputs
+            .into_iter()
+            .zip(local)
+            .map(|(input, filters)| {
+                if filters.is_empty() {
+                    return input;
+                }
+                Arc::new(Expression::Logical(LogicalExpression::Filter(Filter {
+                    filters,
+                    children: input,
+                })))
+            })
+            .collect();
+
+        let mut graph = JoinGraph {
+            relations,
+            predicates: join_predicates,
+            filters,
+            edges,
+        };
+        graph.connect_components();
+        Some(graph)
+    }
+
+    /// The set of all relations of the graph.
+    pub fn all(&self) -> RelationSet {
+        RelationSet::MAX >> (MAX_RELATIONS - self.relations.len())
+    }
+
+    /// Checks if there is an edge between two disjoint sets of relations.
+    pub fn is_connected(&self, left: RelationSet, right: RelationSet) -> bool {
+        self.edges.iter().any(|edge| {
+            (is_subset(edge.left, left) && is_subset(edge.right, right))
+                || (is_subset(edge.left, right) && is_subset(edge.right, left))
+        })
+    }
+
+    /// Returns the neighborhood of a set of relations, excluding the relations in `excluded`.
+    ///
+    /// For every hyperedge that leads out of the set, only the smallest relation on its other end
+    /// is part of the neighborhood, as in DPhyp.
+    pub fn neighborhood(&self, set: RelationSet, excluded: RelationSet) -> RelationSet {
+        let forbidden = set | excluded;
+        self.edges
+            .iter()
+            .flat_map(|edge| [(edge.left, edge.right), (edge.right, edge.left)])
+            .filter(|&(from, to)| is_subset(from, set) && to & forbidden == 0)
+            .fold(0, |neighborhood, (_, to)| neighborhood | lowest(to))
+    }
+
+    /// Returns the predicates that a join between two disjoint sets of relations evaluates: the
+    /// ones that reference both sets and nothing else.
+    pub fn conditions(&self, left: RelationSet, right: RelationSet) -> Vec<Arc<ScalarExpression>> {
+        self.predicates
+            .iter()
+            .filter(|predicate| {
+                is_subset(predicate.relations, left | right)
+                    && predicate.relations & left != 0
+                    && predicate.relations & right != 0
+            })
+            .map(|predicate| predicate.predicate.clone())
+            .collect()
+    }
+
+    /// Creates the inner join of two expressions that compute disjoint sets of relations.
+    pub fn join(
+        &self,
+        left: (RelationSet, &Arc<Expression>),
+        right: (RelationSet, &Arc<Expression>),
+    ) -> Arc<Expression> {
+        Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+            join_type: JoinType::Inner,
+            conditions: self.conditions(left.0, right.0),
+            left: left.1.clone(),
+            right: right.1.clone(),
+        })))
+    }
+
+    /// Applies the predicates that do not reference any relation on top of the joins.
+    pub fn filtered(&self, joins: Arc<Expression>) -> Arc<Expression> {
+        if self.filters.is_empty() {
+            return joins;
+        }
+
+        Arc::new(Expression::Logical(LogicalExpression::Filter(Filter {
+            filters: self.filters.clone(),
+            children: joins,
+        })))
+    }
+
+    /// Adds an edge between every pair of connected components of the graph, so that cross
+    /// products are only considered between entire components.
+    ///
+    /// A hyperedge only connects its relations once each of its sides is connected on its own: the
+    /// relations of `{t0, t1} - {t2}` are not connected unless `t0` and `t1` are.
+    fn connect_components(&mut self) {
+        let mut components: Vec<RelationSet> = (0..self.relations.len()).map(|i| 1 << i).collect();
+        loop {
+            let mut merged = false;
+            for edge in &self.edges {
+                let connected = |side| {
+                    components
+                        .iter()
+                        .any(|&component| is_subset(side, component))
+                };
+                if !connected(edge.left) || !connected(edge.right) {
+                    continue;
+                }
+
+                let relations = edge.left | edge.right;
+                let (joined, rest): (Vec<_>, Vec<_>) = components
+                    .into_iter()
+                    .partition(|component| component & relations != 0);
+                merged |= joined.len() > 1;
+                components = rest;
+                components.push(joined.into_iter().fold(0, |all, component| all | component));
+            }
+
+            if !merged {
+                break;
+            }
+        }
+
+        for (i, &left) in components.iter().enumerate() {
+            for &right in &components[i + 1..] {
+                self.edges.push(Hyperedge { left, right });
+            }
+        }
+    }
+}
+
+/// Returns the join if the expression is a logical inner join.
+fn inner_join_of(expr: &Arc<Expression>) -> Option<&Join> {
+    match expr.as_ref() {
+        Expression::Logical(LogicalExpression::Join(join)) if join.join_type == JoinType::Inner => {
+            Some(join)
+        }
+        Expression::Logical(LogicalExpression::Filter(filter)) => inner_join_of(&filter.children),
+        _ => None,
+    }
+}
+
+/// Collects the inputs and the predicates of a tree of inner joins and filters.
+fn collect(
+    expr: &Arc<Expression>,
+    inputs: &mut Vec<Arc<Expression>>,
+    predicates: &mut Vec<Arc<ScalarExpression>>,
+) {
+    match expr.as_ref() {
+        Expression::Logical(LogicalExpression::Join(join)) if join.join_type == JoinType::Inner => {
+            predicates.extend(join.conditions.iter().flat_map(conjuncts));
+            collect(&join.left, inputs, predicates);
+            collect(&join.right, inputs, predicates);
+        }
+        Expression::Logical(LogicalExpression::Filter(filter))
+            if inner_join_of(&filter.children).is_some() =>
+        {
+            predicates.extend(filter.filters.iter().flat_map(conjuncts));
+            collect(&filter.children, inputs, predicates);
+        }
+        _ => inputs.push(expr.clone()),
+    }
+}
+
+/// Derives the edge of a predicate that references more than one relation, which connects the
+/// relations of the two sides of a comparison.
+///
+/// Any other predicate does not get an edge: it is evaluated by the first join that has all of
+/// its relations, but it does not make any join possible without a cross product.
+fn edge_of(
+    predicate: &ScalarExpression,
+    relations_of: impl Fn(&ScalarExpression) -> RelationSet,
+) -> Option<Hyperedge> {
+    let ScalarExpression::Compare { left, right, .. } = predicate else {
+        return None;
+    };
+
+    let (left, right) = (relations_of(left), relations_of(right));
+    (left != 0 && right != 0 && left & right == 0).then_some(Hyperedge { left, right })
+}
+
+/// Checks if every relation of `set` is in `of`.
+pub fn is_subset(set: RelationSet, of: RelationSet) -> bool {
+    set & !of == 0
+}
+
+/// Returns the set that only contains the smallest relation of a set.
+pub fn lowest(set: RelationSet) -> RelationSet {
+    set & set.wrapping_neg()
+}
+
+/// Returns every non-empty subset of a set, in increasing order.
+pub fn subsets(set: RelationSet) -> impl Iterator<Item = RelationSet> {
+    let mut subset: RelationSet = 0;
+    std::iter::from_fn(move || {
+        subset = subset.wrapping_sub(set) & set;
+        (subset != 0).then_some(subset)
+    })
+}
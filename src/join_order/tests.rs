@@ -0,0 +1,245 @@
+use crate::catalog::ColumnId;
+use crate::cost::default::DefaultCostModel;
+use crate::engine::SearchEngine;
+use crate::expression::physical::PhysicalExpression;
+use crate::expression::scalar::ScalarExpression;
+use crate::fixtures::{catalog, edge, scan};
+use crate::{
+    Expression, Filter, Group, GroupKey, Join, JoinType, LogicalExpression, Memo, Relation,
+};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use super::dphyp::enumerate_joins;
+use super::*;
+
+/// Joins the relations left-deep in order, with all of the predicates on the top join.
+fn query(relations: usize, predicates: Vec<Arc<ScalarExpression>>) -> Arc<Expression> {
+    let joins = (1..relations).fold(scan(0), |left, table| {
+        Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+            join_type: JoinType::Inner,
+            conditions: vec![],
+            left,
+            right: scan(table),
+        })))
+    });
+
+    let Expression::Logical(LogicalExpression::Join(top)) = joins.as_ref() else {
+        unreachable!();
+    };
+    Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+        conditions: predicates,
+        ..top.clone()
+    })))
+}
+
+/// Returns the number of logical joins in every group below (and including) `group` that has
+/// any, by the tables that the group joins.
+fn joins_by_tables(memo: &Arc<Memo>, group: &Arc<Group>) -> BTreeMap<Vec<usize>, usize> {
+    fn visit(
+        memo: &Arc<Memo>,
+        group: &Arc<Group>,
+        seen: &mut Vec<GroupKey>,
+        joins: &mut BTreeMap<Vec<usize>, usize>,
+    ) {
+        if seen.contains(&group.key()) {
+            return;
+        }
+        seen.push(group.key());
+
+        for expr in group.expressions() {
+            if let Expression::Logical(LogicalExpression::Join(_)) = expr.as_ref() {
+                let tables = group.logical_properties().tables.iter().copied().collect();
+                *joins.entry(tables).or_default() += 1;
+            }
+            for child in expr.children() {
+                visit(memo, &child.group(memo), seen, joins);
+            }
+        }
+    }
+
+    let mut joins = BTreeMap::new();
+    visit(memo, group, &mut vec![], &mut joins);
+    joins
+}
+
+#[test]
+fn chains_and_stars_only_join_connected_subgraphs() {
+    let catalog = catalog(4);
+
+    // A chain has `(n^3 - n) / 6` connected subgraph / complement pairs, and a star has
+    // `(n - 1) * 2^(n - 2)`.
+    for (predicates, groups, pairs) in [
+        (vec![edge(0, 1), edge(1, 2), edge(2, 3)], 6, 10),
+        (vec![edge(0, 1), edge(0, 2), edge(0, 3)], 7, 12),
+    ] {
+        let memo = Arc::new(Memo::new(catalog.clone()));
+        let root = enumerate_joins(&memo, &query(4, predicates));
+
+        let joins = joins_by_tables(&memo, &root);
+        assert_eq!(joins.len(), groups, "unexpected join groups {joins:?}");
+        assert_eq!(joins.values().sum::<usize>(), 2 * pairs);
+    }
+}
+
+#[test]
+fn cross_products_only_join_components() {
+    let catalog = catalog(4);
+
+    // `t0 - t1` and `t2 - t3` are not connected, except by a predicate that is not a comparison.
+    let disjunction = Arc::new(ScalarExpression::Or(vec![edge(0, 2), edge(1, 3)]));
+    let memo = Arc::new(Memo::new(catalog.clone()));
+    let root = enumerate_joins(
+        &memo,
+        &query(4, vec![edge(0, 1), edge(2, 3), disjunction.clone()]),
+    );
+
+    let joins = joins_by_tables(&memo, &root);
+    let expected = BTreeMap::from([(vec![0, 1], 2), (vec![2, 3], 2), (vec![0, 1, 2, 3], 2)]);
+    assert_eq!(joins, expected);
+
+    // The disjunction is evaluated by the cross product.
+    let engine = SearchEngine::new(memo, Arc::new(DefaultCostModel::new(catalog)));
+    let plan = engine.optimize(root).expect("the query should have a plan");
+    let Expression::Physical(PhysicalExpression::HashJoin(top)) = plan.as_ref() else {
+        panic!("the top of the plan should be a hash join, got {plan:?}");
+    };
+    assert_eq!(top.conditions, vec![disjunction]);
+}
+
+#[test]
+fn hyperedges_without_connected_sides_are_connected_by_cross_products() {
+    let catalog = catalog(3);
+
+    // `(t0.id = t1.id) = t2.id` is a hyperedge `{t0, t1} - {t2}`, but `t0` and `t1` are not
+    // connected, so they can only be joined with a cross product.
+    let hyperedge = ScalarExpression::eq(edge(0, 1), ScalarExpression::column(ColumnId(2)));
+    for greedy_threshold in [usize::MAX, 2] {
+        let memo = Arc::new(Memo::new(catalog.clone()));
+        let root = order_joins(
+            &memo,
+            &query(3, vec![hyperedge.clone()]),
+            &JoinOrderOptions { greedy_threshold },
+        );
+
+        // The join orders of the whole graph are added, rather than only the original plan.
+        let joins = joins_by_tables(&memo, &root);
+        assert!(joins[&vec![0, 1, 2]] >= 2, "{joins:?}");
+
+        let engine = SearchEngine::new(memo, Arc::new(DefaultCostModel::new(catalog.clone())));
+        let plan = engine.optimize(root).expect("the query should have a plan");
+        let Expression::Physical(PhysicalExpression::HashJoin(top)) = plan.as_ref() else {
+            panic!("the top of the plan should be a hash join, got {plan:?}");
+        };
+        assert_eq!(top.conditions, vec![hyperedge.clone()]);
+    }
+}
+
+#[test]
+fn join_graphs_are_extracted_through_filters() {
+    // `SELECT * FROM t0, t1, t2 WHERE t0.id = 5 AND t0.id = t2.id AND t1.id = t2.id`
+    let local = ScalarExpression::eq(
+        ScalarExpression::column(ColumnId(0)),
+        ScalarExpression::literal(5),
+    );
+    let expr = Arc::new(Expression::Logical(LogicalExpression::Filter(Filter {
+        filters: vec![local.clone(), edge(0, 2)],
+        children: query(3, vec![edge(1, 2)]),
+    })));
+
+    let graph = JoinGraph::extract(&expr).unwrap();
+    assert_eq!(graph.relations.len(), 3);
+    assert_eq!(graph.all(), 0b111);
+    assert_eq!(
+        graph.edges,
+        vec![
+            Hyperedge {
+                left: 0b001,
+                right: 0b100
+            },
+            Hyperedge {
+                left: 0b010,
+                right: 0b100
+            },
+        ]
+    );
+    assert!(graph.is_connected(0b011, 0b100));
+    assert!(!graph.is_connected(0b001, 0b010));
+    assert_eq!(graph.neighborhood(0b001, 0), 0b100);
+    assert_eq!(graph.conditions(0b011, 0b100).len(), 2);
+
+    let Expression::Logical(LogicalExpression::Filter(filter)) = graph.relations[0].as_ref() else {
+        panic!("the local predicate should filter its relation");
+    };
+    assert_eq!(filter.filters, vec![local]);
+}
+
+#[test]
+fn large_join_graphs_are_ordered_greedily() {
+    let catalog = catalog(4);
+    let options = JoinOrderOptions {
+        greedy_threshold: 3,
+    };
+
+    // Joining `t0` (100 rows) with `t1` (200 rows) produces the fewest rows, after which extending
+    // that join is always cheaper than joining `t2` (300 rows) with `t3` (400 rows).
+    let memo = Arc::new(Memo::new(catalog.clone()));
+    let predicates = vec![edge(0, 1), edge(1, 2), edge(2, 3)];
+    let root = order_joins(&memo, &query(4, predicates), &options);
+
+    let joins = joins_by_tables(&memo, &root);
+    let expected = BTreeMap::from([(vec![0, 1], 2), (vec![0, 1, 2], 2), (vec![0, 1, 2, 3], 2)]);
+    assert_eq!(joins, expected);
+}
+
+#[test]
+fn very_large_joins_have_a_plan() {
+    let relations = 40;
+    let catalog = catalog(relations);
+    let predicates = (1..relations).map(|table| edge(table - 1, table)).collect();
+
+    let memo = Arc::new(Memo::new(catalog.clone()));
+    let root = order_joins(
+        &memo,
+        &query(relations, predicates),
+        &JoinOrderOptions::default(),
+    );
+
+    let engine = SearchEngine::new(memo, Arc::new(DefaultCostModel::new(catalog)));
+    let plan = engine.optimize(root).expect("the query should have a plan");
+
+    fn hash_joins(plan: &Arc<Expression>) -> usize {
+        let own = matches!(
+            plan.as_ref(),
+            Expression::Physical(PhysicalExpression::HashJoin(_))
+        );
+        usize::from(own) + plan.children().iter().map(hash_joins).sum::<usize>()
+    }
+    assert_eq!(hash_joins(&plan), relations - 1);
+}
+
+#[test]
+fn inferred_predicates_avoid_cross_products() {
+    use crate::rules::rewrite::Rewriter;
+
+    let catalog = catalog(3);
+
+    // Without the inferred `t0.id = t2.id`, joining `t0` and `t2` first would be a cross product.
+    for (rewrite, expected) in [
+        (false, vec![vec![0, 1], vec![0, 1, 2], vec![1, 2]]),
+        (
+            true,
+            vec![vec![0, 1], vec![0, 1, 2], vec![0, 2], vec![1, 2]],
+        ),
+    ] {
+        let mut plan = query(3, vec![edge(0, 1), edge(1, 2)]);
+        if rewrite {
+            plan = Rewriter::default().rewrite(&plan);
+        }
+
+        let memo = Arc::new(Memo::new(catalog.clone()));
+        let root = enumerate_joins(&memo, &plan);
+        let tables: Vec<Vec<usize>> = joins_by_tables(&memo, &root).into_keys().collect();
+        assert_eq!(tables, expected);
+    }
+}
@@ -12,16 +12,33 @@
 //!   to finish. However, in an asynchronous environment, there is not blocking, and the runtime can
 //!   figure out which task the current task is dependent on and go help it out.
 
-use arc_swap::ArcSwapOption;
+use arc_swap::{ArcSwap, ArcSwapOption};
+use cardinality::Estimator;
+use catalog::Catalog;
+use cost::{Cost, CostWeights};
 use dashmap::DashMap;
 use enum_dispatch::enum_dispatch;
+use properties::{ColumnSet, Field, LogicalProperties};
 use rules::Rule;
-use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
 
-mod engine;
-mod expression;
-mod rules;
+pub mod cardinality;
+pub mod catalog;
+pub mod cost;
+pub mod engine;
+pub mod expression;
+pub mod join_order;
+pub mod pipeline;
+pub mod properties;
+pub mod rules;
+pub mod statistics;
+pub mod value;
+
+#[cfg(test)]
+mod fixtures;
 
 use expression::logical::*;
 use expression::physical::*;
@@ -33,7 +50,7 @@ use expression::physical::*;
 ///
 /// Note that the only reason why this is not using [`enum_dispatch`] is that it would make
 /// constructing one of these way too verbose.
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum Expression {
     Logical(LogicalExpression),
     Physical(PhysicalExpression),
@@ -53,6 +70,28 @@ impl Relation for Expression {
             Expression::Physical(physical_expression) => physical_expression.physical_properties(),
         }
     }
+
+    fn with_children(&self, children: Vec<Arc<Expression>>) -> Expression {
+        match self {
+            Expression::Logical(logical_expression) => logical_expression.with_children(children),
+            Expression::Physical(physical_expression) => {
+                physical_expression.with_children(children)
+            }
+        }
+    }
+
+    fn fingerprint(&self, state: &mut DefaultHasher) {
+        match self {
+            Expression::Logical(logical_expression) => {
+                std::mem::discriminant(logical_expression).hash(state);
+                logical_expression.fingerprint(state);
+            }
+            Expression::Physical(physical_expression) => {
+                std::mem::discriminant(physical_expression).hash(state);
+                physical_expression.fingerprint(state);
+            }
+        }
+    }
 }
 
 impl Expression {
@@ -64,27 +103,123 @@ impl Expression {
     /// Given an expression, returns an iterator of the possible logical transformations this
     /// expression can take on, ordered by their promise values.
     ///
+    /// Rules that the guidance of the expression disables are left out, and every move carries the
+    /// guidance for the expressions that its rule produces. Rules that need the catalog get it
+    /// through the estimator.
+    ///
     /// TODO:
-    /// Should we store the `Guidance` inside the `Expression` tree or in the memo table?
+    /// The promise of a rule is currently just its position in the rule list.
     pub fn transformation_moves(
         self: &Arc<Expression>,
         guidance: &Guidance,
-    ) -> Vec<(Arc<dyn Rule>, usize)> {
-        todo!("Return an iterator over the possible transformations")
+        estimator: &Estimator,
+    ) -> Vec<Move> {
+        if !matches!(self.as_ref(), Expression::Logical(_)) {
+            return vec![];
+        }
+
+        let catalog_rules = rules::transformation::CATALOG_TRANSFORMATION_RULES
+            .iter()
+            .map(|&rule| rules::bind(rule, estimator));
+        let guidance_lists = rules::transformation::STATIC_TRANSFORMATION_GUIDANCE
+            .iter()
+            .chain(&rules::transformation::CATALOG_TRANSFORMATION_GUIDANCE);
+        let count = rules::transformation::transformation_rule_count();
+
+        rules::transformation::STATIC_TRANSFORMATION_RULES
+            .iter()
+            .map(|&rule| Arc::new(rule) as Arc<dyn Rule>)
+            .chain(catalog_rules)
+            .zip(guidance_lists)
+            .enumerate()
+            .filter(|(index, _)| !guidance.is_disabled(*index))
+            .map(|(index, (rule, disabled))| Move {
+                rule,
+                promise: count - index,
+                guidance: Arc::new(Guidance::disabling(disabled)),
+            })
+            .collect()
     }
 
-    /// Given an expression, returns an iterator of the possible physical and logical
-    /// transformations this expression can take on, ordered by their promise values.
-    ///
-    /// TODO:
-    /// Should we store the `Guidance` inside the `Expression` tree or in the memo table?
-    pub fn all_moves(self: &Arc<Expression>, _guidance: &Guidance) -> Vec<(Arc<dyn Rule>, usize)> {
-        todo!("Return an iterator over the possible transformations")
+    /// Given an expression, returns an iterator of the possible physical implementations this
+    /// expression can take on, ordered by their promise values.
+    pub fn implementation_moves(self: &Arc<Expression>, _guidance: &Guidance) -> Vec<Move> {
+        if !matches!(self.as_ref(), Expression::Logical(_)) {
+            return vec![];
+        }
+
+        rules::with_promises(&rules::implementation::STATIC_IMPLEMENTATION_RULES)
+            .into_iter()
+            .map(|(rule, promise)| Move {
+                rule,
+                promise,
+                guidance: Arc::default(),
+            })
+            .collect()
     }
 
     /// Returns the group / equivalence class of the current expression.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the expression has not been added to the memo table.
     pub fn group(self: &Arc<Expression>, memo: &Arc<Memo>) -> Arc<Group> {
-        todo!("Figure out the `GroupKey` of the current `Expression` and find the group in memo")
+        let key = self
+            .memo_key(memo)
+            .expect("expression must be in the memo table");
+
+        memo.group(key)
+            .expect("group of an expression must be in the memo table")
+    }
+
+    /// Computes a fingerprint of the entire expression tree, which (unlike the fingerprints used by
+    /// the memo table) does not depend on the groups of the children.
+    pub fn structural_fingerprint(&self) -> u64 {
+        let mut state = DefaultHasher::new();
+        std::mem::discriminant(self).hash(&mut state);
+        self.fingerprint(&mut state);
+
+        for child in self.children() {
+            child.structural_fingerprint().hash(&mut state);
+        }
+
+        state.finish()
+    }
+
+    /// Computes the fingerprint of an expression whose children belong to the given groups.
+    ///
+    /// Two expressions have the same fingerprint if they are the same operator and their children
+    /// belong to the same groups, but expressions with the same fingerprint are not necessarily
+    /// the same (see [`Expression::same_operator`]).
+    fn memo_fingerprint(&self, children: &[GroupKey]) -> u64 {
+        let mut state = DefaultHasher::new();
+        std::mem::discriminant(self).hash(&mut state);
+        self.fingerprint(&mut state);
+        children.hash(&mut state);
+        state.finish()
+    }
+
+    /// Returns the key of the group of an expression, or `None` if the expression (or any of its
+    /// children) is not in the memo table.
+    fn memo_key(&self, memo: &Memo) -> Option<GroupKey> {
+        let children = self
+            .children()
+            .iter()
+            .map(|child| child.memo_key(memo))
+            .collect::<Option<Vec<_>>>()?;
+        memo.lookup(self, &children)
+    }
+
+    /// Checks if two expressions are the same operator, ignoring their children.
+    fn same_operator(&self, other: &Expression) -> bool {
+        let without_children = |expr: &Expression| {
+            let placeholder = Arc::new(Expression::Logical(LogicalExpression::Empty(Empty {
+                columns: vec![],
+            })));
+            expr.with_children(vec![placeholder; expr.children().len()])
+        };
+
+        without_children(self) == without_children(other)
     }
 }
 
@@ -96,6 +231,15 @@ pub trait Relation {
     fn children(&self) -> Vec<Arc<Expression>>;
 
     fn physical_properties(&self) -> Vec<PhysicalProperties>;
+
+    /// Creates a copy of this relation with its children replaced by `children` (in the same order
+    /// as [`Relation::children`]).
+    fn with_children(&self, children: Vec<Arc<Expression>>) -> Expression;
+
+    /// Hashes the operator-specific data of this relation (but _not_ its children) into `state`.
+    ///
+    /// The memo table combines this with the groups of the children to fingerprint expressions.
+    fn fingerprint(&self, state: &mut DefaultHasher);
 }
 
 /// The different types of physical properties.
@@ -110,38 +254,100 @@ pub enum PhysicalProperties {
 /// A `Guidance` object that tracks the possible transformations that can be applied to an
 /// `Expression` tree.
 ///
+/// The bitmap holds one bit per transformation rule (by its index in the rule list), which is set
+/// if the rule is disabled for the expression. Rules disable each other on the expressions that
+/// they produce, so that a rule set can avoid generating the same expression more than once.
+///
 /// TODO:
 /// This `Guidance` type will have to support concurrent access and modification so that there is
 /// only one worker applying a transformation at a time.
 #[derive(Default)]
 pub struct Guidance {
-    // TODO create an atomic bitmap instead.
     pub bitmap: Arc<[AtomicU8]>,
-    pub cost_limit: AtomicUsize,
+    /// The largest cost limit that a physical expression has been costed under. An expression that
+    /// was pruned because it exceeded its limit is only costed again under a larger limit.
+    pub cost_limit: ArcSwap<Cost>,
+}
+
+impl Guidance {
+    /// Creates guidance that disables the given rules (by their index in the rule list).
+    pub fn disabling(rules: &[usize]) -> Self {
+        let bytes = rules.iter().max().map_or(0, |max| max / 8 + 1);
+        let bitmap: Arc<[AtomicU8]> = (0..bytes).map(|_| AtomicU8::new(0)).collect();
+        for rule in rules {
+            bitmap[rule / 8].fetch_or(1 << (rule % 8), Ordering::Relaxed);
+        }
+
+        Self {
+            bitmap,
+            ..Self::default()
+        }
+    }
+
+    /// Returns guidance that disables every rule that either `self` or `other` disables.
+    pub fn union(&self, other: &Guidance) -> Guidance {
+        let (longer, shorter) = if self.bitmap.len() >= other.bitmap.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+
+        let bitmap = longer
+            .bitmap
+            .iter()
+            .enumerate()
+            .map(|(index, byte)| {
+                let other = shorter
+                    .bitmap
+                    .get(index)
+                    .map_or(0, |byte| byte.load(Ordering::Relaxed));
+                AtomicU8::new(byte.load(Ordering::Relaxed) | other)
+            })
+            .collect();
+
+        Self {
+            bitmap,
+            ..Self::default()
+        }
+    }
+
+    /// Checks if the rule with the given index must not be applied.
+    pub fn is_disabled(&self, rule: usize) -> bool {
+        self.bitmap
+            .get(rule / 8)
+            .is_some_and(|byte| byte.load(Ordering::Relaxed) & (1 << (rule % 8)) != 0)
+    }
+}
+
+/// A rule that can be applied to an expression, as returned by
+/// [`Expression::transformation_moves`] and [`Expression::implementation_moves`].
+pub struct Move {
+    pub rule: Arc<dyn Rule>,
+    pub promise: usize,
+    /// The guidance for the expressions that the rule produces.
+    pub guidance: Arc<Guidance>,
 }
 
 // The winning / best plan for a given group / equivalence class.
 pub struct Winner {
     expression: Arc<Expression>,
-    cost: usize,
+    cost: Cost,
 }
 
 /// The representation of an equivalence class in the Cascades framework.
 ///
 /// TODO:
 /// - Assuming we have a way to quickly look up a group and get access to the list of equivalent
-///   expressions as well as the current winner, should we store guidance and promise inside the
-///   expressions themselves (literally in the [`Expression`] tree) or can we store them right next
-///   to each other in the memo table?
+///   expressions as well as the current winner, should we store promise inside the expressions
+///   themselves (literally in the [`Expression`] tree) or right next to them in the memo table,
+///   like guidance?
 pub struct Group {
-    /// The equivalent expressions that belong to this group / equivalence class.
+    /// The equivalent expressions that belong to this group / equivalence class, each with the
+    /// guidance for exploring it.
     ///
     /// TODO:
     /// Might even want to put locking on each individual expression within this equivalence class.
-    expressions: RwLock<Vec<Arc<Expression>>>,
-
-    /// Since `Guidance` should be thread-safe, we don't need to protect it with a lock.
-    guides: Vec<Guidance>,
+    expressions: RwLock<Vec<(Arc<Expression>, Arc<Guidance>)>>,
 
     /// By storing this in an atomic `ArcSwapOption`, we can ensure atomic changes to both the
     /// expression and the cost associated with that expression.
@@ -149,17 +355,109 @@ pub struct Group {
 
     /// A flag that represents if exploration of this group has finished.
     explored: AtomicBool,
+
+    /// A flag that represents if optimization of this group has been scheduled.
+    optimized: AtomicBool,
+
+    /// The logical properties shared by all expressions in this group, derived once from the first
+    /// logical expression of the group.
+    logical_properties: LogicalProperties,
+
+    /// The key of this group in the memo table.
+    key: GroupKey,
+}
+
+impl Group {
+    fn new(
+        key: GroupKey,
+        expression: Arc<Expression>,
+        guidance: Arc<Guidance>,
+        logical_properties: LogicalProperties,
+    ) -> Self {
+        Self {
+            expressions: RwLock::new(vec![(expression, guidance)]),
+            winner: ArcSwapOption::empty(),
+            explored: AtomicBool::new(false),
+            optimized: AtomicBool::new(false),
+            logical_properties,
+            key,
+        }
+    }
+
+    pub fn key(&self) -> GroupKey {
+        self.key
+    }
+
+    /// Returns a snapshot of the expressions currently in this group.
+    pub fn expressions(&self) -> Vec<Arc<Expression>> {
+        self.expressions
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(expr, _)| expr.clone())
+            .collect()
+    }
+
+    /// Returns the guidance for exploring an expression of this group.
+    pub fn guidance(&self, expr: &Arc<Expression>) -> Arc<Guidance> {
+        self.expressions
+            .read()
+            .unwrap()
+            .iter()
+            .find(|(other, _)| Arc::ptr_eq(other, expr))
+            .map(|(_, guidance)| guidance.clone())
+            .unwrap_or_default()
+    }
+
+    /// Returns the best physical expression found for this group so far and its cost.
+    pub fn winner(&self) -> Option<(Arc<Expression>, Cost)> {
+        self.winner
+            .load_full()
+            .map(|winner| (winner.expression.clone(), winner.cost))
+    }
+
+    pub fn logical_properties(&self) -> &LogicalProperties {
+        &self.logical_properties
+    }
+
+    /// The output columns of this group and their types and nullability.
+    pub fn schema(&self) -> &[Field] {
+        &self.logical_properties.schema
+    }
+
+    /// The estimated number of rows that this group outputs.
+    pub fn row_count(&self) -> f64 {
+        self.logical_properties.row_count
+    }
+
+    /// The sets of output columns that are guaranteed to be unique.
+    pub fn unique_keys(&self) -> &[ColumnSet] {
+        &self.logical_properties.unique_keys
+    }
+
+    /// The equivalence classes of the output columns.
+    pub fn equivalences(&self) -> &[ColumnSet] {
+        &self.logical_properties.equivalences
+    }
 }
 
 /// The lookup key for a `Group`.
 ///
 /// TODO:
 /// - How do to store and lookup groups efficiently? By ID or hashing? Or some other type of representation?
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct GroupKey {
     // is this the right representation?
     id: usize,
 }
 
+/// An expression in the memo table, along with the groups of its children when it was added.
+struct MemoEntry {
+    expression: Arc<Expression>,
+    children: Vec<GroupKey>,
+    group: GroupKey,
+}
+
 /// The memoization table used for dynamic programming in the Cascades framework.
 ///
 /// TODO:
@@ -170,4 +468,299 @@ pub struct GroupKey {
 pub struct Memo {
     /// A concurrent hash table mapping [`GroupKey`]s to [`Group`]s.
     table: DashMap<GroupKey, Arc<Group>>,
+
+    /// A concurrent hash table mapping expression fingerprints to the expressions with that
+    /// fingerprint, since different expressions can share a fingerprint.
+    fingerprints: DashMap<u64, Vec<MemoEntry>>,
+
+    /// A union find over group keys, mapping every group that was merged into another group to
+    /// that group.
+    merged: DashMap<GroupKey, GroupKey>,
+
+    next_group_id: AtomicUsize,
+
+    /// The estimator used to derive the row counts of new groups.
+    estimator: Estimator,
+
+    /// The weights that the winners of the groups are compared with, which the search engine sets
+    /// to the weights of its cost model.
+    weights: ArcSwap<CostWeights>,
+}
+
+impl Memo {
+    pub fn new(catalog: Arc<Catalog>) -> Self {
+        Self::with_estimator(Estimator::new(catalog))
+    }
+
+    pub fn with_estimator(estimator: Estimator) -> Self {
+        Self {
+            table: DashMap::new(),
+            fingerprints: DashMap::new(),
+            merged: DashMap::new(),
+            next_group_id: AtomicUsize::new(0),
+            estimator,
+            weights: ArcSwap::from_pointee(CostWeights::default()),
+        }
+    }
+
+    pub fn catalog(&self) -> &Arc<Catalog> {
+        self.estimator.catalog()
+    }
+
+    pub fn estimator(&self) -> &Estimator {
+        &self.estimator
+    }
+
+    /// Sets the weights that the winners of the groups are compared with when groups are merged.
+    pub fn set_cost_weights(&self, weights: CostWeights) {
+        self.weights.store(Arc::new(weights));
+    }
+
+    /// Returns the group with the given key, or the group that it has been merged into.
+    pub fn group(&self, key: GroupKey) -> Option<Arc<Group>> {
+        self.table.get(&self.find(key)).map(|group| group.clone())
+    }
+
+    /// Returns the key of the group of the expression that is the same operator as `expr` and
+    /// whose children belong to the given groups, if there is one.
+    fn lookup(&self, expr: &Expression, children: &[GroupKey]) -> Option<GroupKey> {
+        let bucket = self.fingerprints.get(&expr.memo_fingerprint(children))?;
+        self.find_entry(&bucket, expr, children)
+    }
+
+    /// Returns the key of the group of the expression in `bucket` that is the same operator as
+    /// `expr` and whose children belong to the given groups, if there is one.
+    fn find_entry(
+        &self,
+        bucket: &[MemoEntry],
+        expr: &Expression,
+        children: &[GroupKey],
+    ) -> Option<GroupKey> {
+        bucket
+            .iter()
+            .find(|entry| {
+                entry.children.len() == children.len()
+                    && entry
+                        .children
+                        .iter()
+                        .zip(children)
+                        .all(|(&entry_child, &child)| self.find(entry_child) == child)
+                    && entry.expression.same_operator(expr)
+            })
+            .map(|entry| self.find(entry.group))
+    }
+
+    /// Adds an expression (and recursively, its children) to the memo table.
+    ///
+    /// If `target` is given, a new expression is added to that group. Otherwise, a new group is
+    /// created for it. Returns the group that the expression belongs to, and whether or not the
+    /// expression was newly added.
+    ///
+    /// If the expression is already in a different group than `target`, the two groups are
+    /// equivalent and are merged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a physical expression is added without a target group.
+    pub fn add_expression(
+        &self,
+        expr: &Arc<Expression>,
+        target: Option<GroupKey>,
+    ) -> (Arc<Group>, bool) {
+        self.add_expression_with_guidance(expr, target, Arc::default())
+    }
+
+    /// Adds an expression to the memo table like [`Memo::add_expression`], with the guidance for
+    /// exploring the expression. Any children that are added get the default guidance.
+    pub fn add_expression_with_guidance(
+        &self,
+        expr: &Arc<Expression>,
+        target: Option<GroupKey>,
+        guidance: Arc<Guidance>,
+    ) -> (Arc<Group>, bool) {
+        let children: Vec<Arc<Group>> = expr
+            .children()
+            .iter()
+            .map(|child| self.add_expression(child, None).0)
+            .collect();
+
+        let child_keys: Vec<GroupKey> = children.iter().map(|group| self.find(group.key)).collect();
+
+        let mut bucket = self
+            .fingerprints
+            .entry(expr.memo_fingerprint(&child_keys))
+            .or_default();
+        if let Some(existing) = self.find_entry(&bucket, expr, &child_keys) {
+            drop(bucket);
+
+            if let Some(target) = target {
+                self.merge(existing, target);
+            }
+            let group = self.group(existing).unwrap();
+            return (group, false);
+        }
+        let entry = |group| MemoEntry {
+            expression: expr.clone(),
+            children: child_keys.clone(),
+            group,
+        };
+
+        if let Some(key) = target {
+            let group = self.group(key).expect("target group must exist");
+            group
+                .expressions
+                .write()
+                .unwrap()
+                .push((expr.clone(), guidance));
+            bucket.push(entry(group.key()));
+            return (group, true);
+        }
+
+        let Expression::Logical(logical) = expr.as_ref() else {
+            panic!("a new group must be created from a logical expression");
+        };
+
+        let children: Vec<&LogicalProperties> = children
+            .iter()
+            .map(|group| group.logical_properties())
+            .collect();
+        let mut logical_properties = logical.derive_properties(&children, &self.estimator);
+        if let Some(row_count) = self
+            .estimator
+            .overrides()
+            .row_count(expr, &logical_properties)
+        {
+            logical_properties.row_count = row_count;
+        }
+
+        let key = GroupKey {
+            id: self.next_group_id.fetch_add(1, Ordering::Relaxed),
+        };
+        let group = Arc::new(Group::new(key, expr.clone(), guidance, logical_properties));
+
+        // Only publish the fingerprint once the group is in the table.
+        self.table.insert(key, group.clone());
+        bucket.push(entry(key));
+
+        (group, true)
+    }
+
+    /// Returns the key of the group that the group with the given key has been merged into (or the
+    /// key itself if it has not been merged).
+    fn find(&self, mut key: GroupKey) -> GroupKey {
+        while let Some(parent) = self.merged.get(&key).map(|parent| *parent) {
+            key = parent;
+        }
+        key
+    }
+
+    /// Merges two equivalent groups, along with any groups that become equivalent because their
+    /// expressions now have children in the same groups.
+    ///
+    /// The older group survives. It keeps its explored and optimized flags only if both groups had
+    /// them, so that the expressions of the other group are still explored and optimized, and it
+    /// keeps the cheaper of the two winners.
+    ///
+    /// TODO:
+    /// Every merge rehashes the entire memo table, which is fine for exploration but will need an
+    /// index from groups to the expressions that use them as a child for larger memo tables.
+    fn merge(&self, first: GroupKey, second: GroupKey) {
+        let mut pending = vec![(first, second)];
+
+        while let Some((first, second)) = pending.pop() {
+            let (first, second) = (self.find(first), self.find(second));
+            if first == second {
+                continue;
+            }
+
+            let (kept, removed) = if first.id < second.id {
+                (first, second)
+            } else {
+                (second, first)
+            };
+            let kept = self.table.get(&kept).unwrap().clone();
+            let removed = self.table.get(&removed).unwrap().clone();
+            self.merged.insert(removed.key, kept.key);
+
+            let moved = removed.expressions.read().unwrap().clone();
+            kept.expressions.write().unwrap().extend(moved);
+            kept.explored
+                .fetch_and(removed.explored.load(Ordering::Acquire), Ordering::AcqRel);
+            kept.optimized
+                .fetch_and(removed.optimized.load(Ordering::Acquire), Ordering::AcqRel);
+            if let Some(challenger) = removed.winner.load_full() {
+                let weights = self.weights.load();
+                kept.winner.rcu(|winner| match winner {
+                    Some(winner) if weights.compare(&winner.cost, &challenger.cost).is_le() => {
+                        Some(winner.clone())
+                    }
+                    _ => Some(challenger.clone()),
+                });
+            }
+
+            pending.extend(self.rehash());
+        }
+    }
+
+    /// Recomputes the fingerprints of all expressions after a merge and removes the expressions
+    /// that became duplicates within their group.
+    ///
+    /// Returns the pairs of groups that turned out to contain the same expression.
+    fn rehash(&self) -> Vec<(GroupKey, GroupKey)> {
+        let groups: Vec<Arc<Group>> = self
+            .table
+            .iter()
+            .filter(|entry| !self.merged.contains_key(entry.key()))
+            .map(|entry| entry.value().clone())
+            .collect();
+        let mut equivalent = vec![];
+
+        // An expression can only be fingerprinted once its children have been, so keep going until
+        // no new fingerprints are found.
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for group in &groups {
+                let mut seen: Vec<(u64, Arc<Expression>, Vec<GroupKey>)> = vec![];
+                group.expressions.write().unwrap().retain(|(expr, _)| {
+                    let Some(children) = expr
+                        .children()
+                        .iter()
+                        .map(|child| child.memo_key(self))
+                        .collect::<Option<Vec<_>>>()
+                    else {
+                        return true;
+                    };
+                    let fingerprint = expr.memo_fingerprint(&children);
+                    let duplicate = seen.iter().any(|(other, other_expr, other_children)| {
+                        *other == fingerprint
+                            && *other_children == children
+                            && other_expr.same_operator(expr)
+                    });
+                    if duplicate {
+                        return false;
+                    }
+
+                    let mut bucket = self.fingerprints.entry(fingerprint).or_default();
+                    match self.find_entry(&bucket, expr, &children) {
+                        Some(other) if other != group.key => equivalent.push((other, group.key)),
+                        Some(_) => {}
+                        None => {
+                            bucket.push(MemoEntry {
+                                expression: expr.clone(),
+                                children: children.clone(),
+                                group: group.key,
+                            });
+                            changed = true;
+                        }
+                    }
+                    seen.push((fingerprint, expr.clone(), children));
+                    true
+                });
+            }
+        }
+
+        equivalent
+    }
 }
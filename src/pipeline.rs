@@ -0,0 +1,241 @@
+//! An optimizer pipeline, which runs a query through a sequence of phases that get more and more
+//! expensive.
+//!
+//! Most queries do not need the full Cascades search: a point lookup on a single table has exactly
+//! one sensible plan, and exploring it costs far more than the plan itself. As in Orca and SQL
+//! Server, the [`Pipeline`] first runs cheap phases, and skips the expensive ones if the cheap
+//! phases already found a good enough plan.
+//!
+//! The default pipeline runs the following phases:
+//!
+//! 1. A heuristic rewrite of the logical plan with [`Rewriter::heuristic`].
+//! 2. Column pruning with [`prune_columns`], so that scans only read the columns that are needed.
+//! 3. A search without any transformation rules, for queries that only scan a single table.
+//! 4. Join ordering with [`order_joins`].
+//! 5. The full Cascades search.
+//! 6. A post-processing pass over the physical plan with the [`POST_PROCESSING_RULES`].
+
+use crate::cardinality::Estimator;
+use crate::cost::{Cost, CostModel};
+use crate::engine::SearchEngine;
+use crate::join_order::{order_joins, JoinOrderOptions};
+use crate::rules::pruning::prune_columns;
+use crate::rules::rewrite::{Rewriter, POST_PROCESSING_RULES};
+use crate::rules::transformation::transformation_rule_count;
+use crate::{Expression, Group, LogicalExpression, Memo, Relation};
+use std::sync::Arc;
+
+#[cfg(test)]
+mod tests;
+
+/// A phase of a [`Pipeline`].
+pub enum Phase {
+    /// Rewrites the logical plan with a rule set.
+    ///
+    /// Rewriting the plan discards the join orders found by earlier phases.
+    Rewrite(Rewriter),
+    /// Removes the columns that the logical plan does not need to compute its output.
+    ///
+    /// Like a rewrite, pruning discards the join orders found by earlier phases.
+    Prune,
+    /// Adds the join orders of the logical plan to the memo table of the next search phase.
+    JoinOrder(JoinOrderOptions),
+    /// Searches for the cheapest physical plan with the Cascades search engine.
+    Search(SearchPhase),
+    /// Rewrites the physical plan with a rule set. Post-processing phases always run, even if an
+    /// earlier phase short-circuited the pipeline.
+    PostProcess(Rewriter),
+}
+
+/// The options of a [`Phase::Search`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchPhase {
+    /// The transformation rules (by their index in the rule list) that the search does not apply.
+    pub disabled_rules: Vec<usize>,
+    /// The number of transformation rules that the search applies at most.
+    pub budget: usize,
+    /// The phase is skipped for plans that scan more tables than this.
+    pub max_tables: usize,
+    /// If the cheapest plan found so far costs at most this much (as collapsed by the weights of
+    /// the cost model), the remaining join ordering and search phases are skipped.
+    pub good_enough: f64,
+}
+
+impl SearchPhase {
+    /// A search that only implements the logical plan as it is, for plans that scan at most a
+    /// single table. Any plan it finds is good enough.
+    pub fn trivial() -> Self {
+        Self {
+            disabled_rules: (0..transformation_rule_count()).collect(),
+            budget: 0,
+            max_tables: 1,
+            good_enough: f64::INFINITY,
+        }
+    }
+
+    /// A search that applies every rule to every plan.
+    pub fn full() -> Self {
+        Self {
+            disabled_rules: vec![],
+            budget: usize::MAX,
+            max_tables: usize::MAX,
+            good_enough: f64::INFINITY,
+        }
+    }
+}
+
+/// The result of a [`Pipeline`].
+#[derive(Debug)]
+pub struct Optimized {
+    /// The cheapest physical plan.
+    pub plan: Arc<Expression>,
+    /// The cost of the plan, before post-processing.
+    pub cost: Cost,
+    /// The indices of the phases that ran, in order.
+    pub phases: Vec<usize>,
+}
+
+/// A sequence of optimizer phases.
+pub struct Pipeline {
+    estimator: Estimator,
+    cost_model: Arc<dyn CostModel>,
+    phases: Vec<Phase>,
+}
+
+/// The state that is passed from one phase to the next.
+struct State {
+    /// The current logical plan.
+    logical: Arc<Expression>,
+    /// The memo table that a join ordering phase prepared for the next search phase, along with
+    /// the group of the plan.
+    memo: Option<(Arc<Memo>, Arc<Group>)>,
+    /// The cheapest physical plan found so far, along with its cost.
+    best: Option<(Arc<Expression>, Cost)>,
+    /// Whether a search phase found a plan that is good enough.
+    done: bool,
+}
+
+impl Pipeline {
+    /// Creates a pipeline with the default phases.
+    pub fn new(estimator: Estimator, cost_model: Arc<dyn CostModel>) -> Self {
+        Self {
+            phases: vec![
+                Phase::Rewrite(Rewriter::heuristic(&estimator)),
+                Phase::Prune,
+                Phase::Search(SearchPhase::trivial()),
+                Phase::JoinOrder(JoinOrderOptions::default()),
+                Phase::Search(SearchPhase::full()),
+                Phase::PostProcess(Rewriter::from_static(&POST_PROCESSING_RULES)),
+            ],
+            estimator,
+            cost_model,
+        }
+    }
+
+    /// Replaces the phases of the pipeline.
+    pub fn with_phases(mut self, phases: Vec<Phase>) -> Self {
+        self.phases = phases;
+        self
+    }
+
+    /// Runs a logical plan through every phase of the pipeline.
+    ///
+    /// Returns `None` if none of the search phases found a physical plan.
+    pub fn optimize(&self, plan: &Arc<Expression>) -> Option<Optimized> {
+        let mut state = State {
+            logical: plan.clone(),
+            memo: None,
+            best: None,
+            done: false,
+        };
+        let mut phases = vec![];
+        let mut post_processors = vec![];
+
+        for (index, phase) in self.phases.iter().enumerate() {
+            match phase {
+                Phase::Rewrite(rewriter) => {
+                    if state.done {
+                        continue;
+                    }
+                    state.logical = rewriter.rewrite(&state.logical);
+                    state.memo = None;
+                }
+                Phase::Prune => {
+                    if state.done {
+                        continue;
+                    }
+                    state.logical = prune_columns(&state.logical);
+                    state.memo = None;
+                }
+                Phase::JoinOrder(options) => {
+                    if state.done {
+                        continue;
+                    }
+                    let memo = Arc::new(Memo::with_estimator(self.estimator.clone()));
+                    let group = order_joins(&memo, &state.logical, options);
+                    state.memo = Some((memo, group));
+                }
+                Phase::Search(options) => {
+                    if state.done || scans(&state.logical) > options.max_tables {
+                        continue;
+                    }
+                    self.search(&mut state, options);
+                }
+                Phase::PostProcess(rewriter) => {
+                    post_processors.push((index, rewriter));
+                    continue;
+                }
+            }
+            phases.push(index);
+        }
+
+        let (mut plan, cost) = state.best?;
+        for (index, rewriter) in post_processors {
+            plan = rewriter.rewrite(&plan);
+            phases.push(index);
+        }
+
+        Some(Optimized { plan, cost, phases })
+    }
+
+    /// Runs a search phase, and keeps its plan if it is cheaper than the plans of earlier phases.
+    fn search(&self, state: &mut State, options: &SearchPhase) {
+        let (memo, group) = state.memo.take().unwrap_or_else(|| {
+            let memo = Arc::new(Memo::with_estimator(self.estimator.clone()));
+            let (group, _) = memo.add_expression(&state.logical, None);
+            (memo, group)
+        });
+
+        let engine = SearchEngine::new(memo.clone(), self.cost_model.clone())
+            .with_disabled_rules(&options.disabled_rules)
+            .with_transformation_budget(options.budget);
+        let Some(plan) = engine.optimize(group.clone()) else {
+            return;
+        };
+        let (_, cost) = memo
+            .group(group.key())
+            .and_then(|group| group.winner())
+            .expect("the group of an optimized plan has a winner");
+
+        let weights = self.cost_model.weights();
+        if state
+            .best
+            .as_ref()
+            .is_none_or(|(_, best)| weights.compare(&cost, best).is_lt())
+        {
+            state.best = Some((plan, cost));
+        }
+
+        let (_, best) = state.best.as_ref().expect("a plan was just found");
+        state.done = weights.collapse(best) <= options.good_enough;
+    }
+}
+
+/// Returns the number of table scans in a logical plan.
+fn scans(plan: &Arc<Expression>) -> usize {
+    let own = matches!(
+        plan.as_ref(),
+        Expression::Logical(LogicalExpression::Scan(_))
+    );
+    usize::from(own) + plan.children().iter().map(scans).sum::<usize>()
+}
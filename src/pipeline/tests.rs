@@ -0,0 +1,364 @@
+use crate::catalog::{Catalog, ColumnId, DataType};
+use crate::cost::default::DefaultCostModel;
+use crate::expression::physical::PhysicalExpression;
+use crate::expression::scalar::ScalarExpression;
+use crate::fixtures::{catalog, edge, scan};
+use crate::{Expression, Filter, Join, JoinType, LogicalExpression, Scan};
+use std::sync::Arc;
+
+use super::*;
+
+fn pipeline(catalog: &Arc<Catalog>) -> Pipeline {
+    Pipeline::new(
+        Estimator::new(catalog.clone()),
+        Arc::new(DefaultCostModel::new(catalog.clone())),
+    )
+}
+
+/// `SELECT * FROM t0, t1, t2 WHERE t0.id = t1.id AND t1.id = t2.id`, as cross products below a
+/// filter.
+fn chain() -> Arc<Expression> {
+    let cross = |left, right| {
+        Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+            join_type: JoinType::Inner,
+            conditions: vec![],
+            left,
+            right,
+        })))
+    };
+    Arc::new(Expression::Logical(LogicalExpression::Filter(Filter {
+        filters: vec![edge(0, 1), edge(1, 2)],
+        children: cross(cross(scan(0), scan(1)), scan(2)),
+    })))
+}
+
+fn hash_joins(plan: &Arc<Expression>) -> usize {
+    let own = matches!(
+        plan.as_ref(),
+        Expression::Physical(PhysicalExpression::HashJoin(_))
+    );
+    usize::from(own) + plan.children().iter().map(hash_joins).sum::<usize>()
+}
+
+#[test]
+fn single_table_queries_skip_exploration() {
+    let catalog = catalog(1);
+
+    // `SELECT * FROM t0 WHERE t0.id = 5`
+    let point_lookup = Arc::new(Expression::Logical(LogicalExpression::Filter(Filter {
+        filters: vec![ScalarExpression::eq(
+            ScalarExpression::column(ColumnId(0)),
+            ScalarExpression::literal(5),
+        )],
+        children: scan(0),
+    })));
+
+    let optimized = pipeline(&catalog)
+        .optimize(&point_lookup)
+        .expect("the query should have a plan");
+
+    // The rewrite, the pruning, the trivial search and the post-processing.
+    assert_eq!(optimized.phases, vec![0, 1, 2, 5]);
+    let Expression::Physical(PhysicalExpression::TableScan(scan)) = optimized.plan.as_ref() else {
+        panic!("expected a table scan, got {:?}", optimized.plan);
+    };
+    assert_eq!(scan.filters.len(), 1);
+}
+
+#[test]
+fn join_queries_run_every_phase() {
+    let catalog = catalog(3);
+
+    let optimized = pipeline(&catalog)
+        .optimize(&chain())
+        .expect("the query should have a plan");
+
+    // Everything but the trivial search.
+    assert_eq!(optimized.phases, vec![0, 1, 3, 4, 5]);
+    assert_eq!(hash_joins(&optimized.plan), 2);
+}
+
+#[test]
+fn scans_only_read_needed_columns() {
+    use crate::Project;
+
+    let mut catalog = Catalog::new();
+    let a = catalog.add_table(
+        "a",
+        &[
+            ("id", DataType::Int64, false),
+            ("x", DataType::Int64, false),
+            ("y", DataType::Utf8, false),
+        ],
+        100,
+    );
+    let b = catalog.add_table(
+        "b",
+        &[("id", DataType::Int64, false), ("z", DataType::Utf8, false)],
+        200,
+    );
+    let catalog = Arc::new(catalog);
+    let full_scan = |table_id| {
+        Arc::new(Expression::Logical(LogicalExpression::Scan(Scan {
+            table_id,
+            columns: catalog.table(table_id).unwrap().column_ids(),
+            filters: vec![],
+        })))
+    };
+
+    // `SELECT a.x FROM a JOIN b ON a.id = b.id`
+    let query = Arc::new(Expression::Logical(LogicalExpression::Project(Project {
+        columns: vec![ColumnId(1)],
+        children: Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+            join_type: JoinType::Inner,
+            conditions: vec![edge(0, 3)],
+            left: full_scan(a),
+            right: full_scan(b),
+        }))),
+    })));
+
+    let optimized = pipeline(&catalog)
+        .optimize(&query)
+        .expect("the query should have a plan");
+
+    fn scanned_columns(plan: &Arc<Expression>, columns: &mut Vec<(usize, Vec<ColumnId>)>) {
+        if let Expression::Physical(PhysicalExpression::TableScan(scan)) = plan.as_ref() {
+            let mut read = scan.read_columns();
+            read.sort();
+            columns.push((scan.table_id, read));
+        }
+        for child in plan.children() {
+            scanned_columns(&child, columns);
+        }
+    }
+    let mut columns = vec![];
+    scanned_columns(&optimized.plan, &mut columns);
+    columns.sort();
+    assert_eq!(
+        columns,
+        vec![(a, vec![ColumnId(0), ColumnId(1)]), (b, vec![ColumnId(3)])],
+        "{:?}",
+        optimized.plan
+    );
+}
+
+#[test]
+fn good_enough_plans_short_circuit_later_phases() {
+    let catalog = catalog(3);
+
+    // A search that only implements the rewritten plan, followed by the full search.
+    let phases = |good_enough| {
+        vec![
+            Phase::Rewrite(Rewriter::default()),
+            Phase::Search(SearchPhase {
+                budget: 0,
+                good_enough,
+                ..SearchPhase::full()
+            }),
+            Phase::JoinOrder(JoinOrderOptions::default()),
+            Phase::Search(SearchPhase::full()),
+        ]
+    };
+
+    let cheap = pipeline(&catalog)
+        .with_phases(phases(f64::INFINITY))
+        .optimize(&chain())
+        .expect("the query should have a plan");
+    assert_eq!(cheap.phases, vec![0, 1]);
+
+    let full = pipeline(&catalog)
+        .with_phases(phases(0.0))
+        .optimize(&chain())
+        .expect("the query should have a plan");
+    assert_eq!(full.phases, vec![0, 1, 2, 3]);
+
+    // The later phases can only make the plan cheaper.
+    let weights = DefaultCostModel::new(catalog).weights();
+    assert!(weights.compare(&full.cost, &cheap.cost).is_le());
+}
+
+fn operators(plan: &Arc<Expression>, matches: &dyn Fn(&PhysicalExpression) -> bool) -> usize {
+    let own = matches!(plan.as_ref(), Expression::Physical(physical) if matches(physical));
+    usize::from(own)
+        + plan
+            .children()
+            .iter()
+            .map(|child| operators(child, matches))
+            .sum::<usize>()
+}
+
+#[test]
+fn correlated_subqueries_are_unnested() {
+    use crate::{Aggregate, AggregateCall, AggregateFunction, AggregatePhase, Apply};
+
+    let mut catalog = Catalog::new();
+    for table in 0..2 {
+        catalog.add_table(&format!("t{table}"), &[("id", DataType::Int64, false)], 100);
+    }
+    let result = catalog.add_derived_column("result", DataType::Int64, true);
+    let catalog = Arc::new(catalog);
+
+    // `SELECT t0.id, (SELECT MAX(t1.id) FROM t1 WHERE t1.id = t0.id) FROM t0`
+    let query = |function| {
+        Arc::new(Expression::Logical(LogicalExpression::Apply(Apply {
+            join_type: JoinType::LeftOuter,
+            conditions: vec![],
+            left: scan(0),
+            right: Arc::new(Expression::Logical(LogicalExpression::Aggregate(
+                Aggregate {
+                    phase: AggregatePhase::Complete,
+                    group_by: vec![],
+                    aggregates: vec![AggregateCall {
+                        function,
+                        argument: Some(ColumnId(1)),
+                        output: result,
+                    }],
+                    children: Arc::new(Expression::Logical(LogicalExpression::Filter(Filter {
+                        filters: vec![edge(1, 0)],
+                        children: scan(1),
+                    }))),
+                },
+            ))),
+        })))
+    };
+    let is_apply =
+        |expr: &PhysicalExpression| matches!(expr, PhysicalExpression::NestedLoopApply(_));
+    let is_aggregate =
+        |expr: &PhysicalExpression| matches!(expr, PhysicalExpression::HashAggregate(_));
+
+    let unnested = pipeline(&catalog)
+        .optimize(&query(AggregateFunction::Max))
+        .expect("the query should have a plan");
+    assert_eq!(operators(&unnested.plan, &is_apply), 0);
+    assert_eq!(operators(&unnested.plan, &is_aggregate), 1);
+    assert_eq!(hash_joins(&unnested.plan), 1);
+
+    // A correlated `COUNT` cannot be unnested, so the subquery is evaluated for every row.
+    let nested = pipeline(&catalog)
+        .optimize(&query(AggregateFunction::Count))
+        .expect("the query should have a plan");
+    assert_eq!(operators(&nested.plan, &is_apply), 1);
+
+    let weights = DefaultCostModel::new(catalog).weights();
+    assert!(weights.compare(&unnested.cost, &nested.cost).is_lt());
+}
+
+#[test]
+fn sorts_followed_by_limits_become_top_n() {
+    use crate::{Limit, Project, Sort, SortKey};
+
+    let catalog = catalog(2);
+
+    // `SELECT t0.id FROM t0 JOIN t1 ON t0.id = t1.id ORDER BY t1.id DESC LIMIT 100`
+    let query = Arc::new(Expression::Logical(LogicalExpression::Limit(Limit {
+        limit: 100,
+        offset: 0,
+        children: Arc::new(Expression::Logical(LogicalExpression::Project(Project {
+            columns: vec![ColumnId(0)],
+            children: Arc::new(Expression::Logical(LogicalExpression::Sort(Sort {
+                order: vec![SortKey {
+                    column: ColumnId(1),
+                    descending: true,
+                }],
+                children: Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+                    join_type: JoinType::Inner,
+                    conditions: vec![edge(0, 1)],
+                    left: scan(0),
+                    right: scan(1),
+                }))),
+            }))),
+        }))),
+    })));
+
+    let optimized = pipeline(&catalog)
+        .optimize(&query)
+        .expect("the query should have a plan");
+    let plan = &optimized.plan;
+    assert_eq!(
+        operators(plan, &|expr| matches!(expr, PhysicalExpression::TopN(_))),
+        1,
+        "{plan:?}"
+    );
+    assert_eq!(
+        operators(plan, &|expr| matches!(
+            expr,
+            PhysicalExpression::MergeSort(_) | PhysicalExpression::StreamingLimit(_)
+        )),
+        0,
+        "{plan:?}"
+    );
+}
+
+#[test]
+fn set_operations_are_implemented() {
+    use crate::{Except, Intersect, Union};
+
+    let mut catalog = Catalog::new();
+    for table in 0..3 {
+        catalog.add_table(
+            &format!("t{table}"),
+            &[("id", DataType::Int64, false)],
+            1000,
+        );
+    }
+    let inner = catalog.add_derived_column("id", DataType::Int64, false);
+    let outer = catalog.add_derived_column("id", DataType::Int64, false);
+    let catalog = Arc::new(catalog);
+    let plan = |query: &Arc<Expression>| {
+        pipeline(&catalog)
+            .optimize(query)
+            .expect("the query should have a plan")
+            .plan
+    };
+
+    // `(SELECT id FROM t0 UNION ALL SELECT id FROM t1) UNION ALL SELECT id FROM t2`
+    let union = |columns, inputs| {
+        Arc::new(Expression::Logical(LogicalExpression::Union(Union {
+            all: true,
+            columns,
+            inputs,
+        })))
+    };
+    let unions = plan(&union(
+        vec![outer],
+        vec![union(vec![inner], vec![scan(0), scan(1)]), scan(2)],
+    ));
+    let Expression::Physical(PhysicalExpression::UnionAll(flattened)) = unions.as_ref() else {
+        panic!("expected a union all, got {unions:?}");
+    };
+    assert_eq!(flattened.children.len(), 3);
+
+    // `INTERSECT ALL` has to count the duplicates, which the hash table does more cheaply than
+    // sorting both inputs.
+    let intersection = plan(&Arc::new(Expression::Logical(
+        LogicalExpression::Intersect(Intersect {
+            all: true,
+            left: scan(0),
+            right: scan(1),
+        }),
+    )));
+    assert!(
+        matches!(
+            intersection.as_ref(),
+            Expression::Physical(PhysicalExpression::HashSetOperation(_))
+        ),
+        "{intersection:?}"
+    );
+
+    // `EXCEPT` can also be an anti join, but is never sorted.
+    let difference = plan(&Arc::new(Expression::Logical(LogicalExpression::Except(
+        Except {
+            all: false,
+            left: scan(0),
+            right: scan(1),
+        },
+    ))));
+    let is_hashed = |expr: &PhysicalExpression| {
+        matches!(expr, PhysicalExpression::HashSetOperation(_))
+            || matches!(expr, PhysicalExpression::HashJoin(join) if join.join_type == JoinType::LeftAnti)
+    };
+    let is_sorted =
+        |expr: &PhysicalExpression| matches!(expr, PhysicalExpression::SortSetOperation(_));
+    assert_eq!(operators(&difference, &is_hashed), 1, "{difference:?}");
+    assert_eq!(operators(&difference, &is_sorted), 0, "{difference:?}");
+}
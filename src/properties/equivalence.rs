@@ -0,0 +1,129 @@
+//! Column equivalence classes, and the predicates that can be inferred from them.
+//!
+//! Two columns are equivalent in the output of an expression if every output row has the same
+//! value in both (or `NULL` in both, for columns padded by an outer join). Equalities between
+//! columns create equivalences, which is what makes it possible to infer `a = c` from `a = b AND
+//! b = c`, and `b = 5` from `a = 5 AND a = b`.
+
+use super::ColumnSet;
+use crate::catalog::ColumnId;
+use crate::expression::scalar::ScalarExpression;
+use std::sync::Arc;
+
+/// Merges equalities between columns into a list of disjoint equivalence classes.
+pub fn add_equalities(
+    classes: &mut Vec<ColumnSet>,
+    equalities: impl IntoIterator<Item = (ColumnId, ColumnId)>,
+) {
+    for (left, right) in equalities {
+        if left == right {
+            continue;
+        }
+
+        let (joined, rest): (Vec<ColumnSet>, Vec<ColumnSet>) = std::mem::take(classes)
+            .into_iter()
+            .partition(|class| class.contains(&left) || class.contains(&right));
+        *classes = rest;
+
+        let mut class = ColumnSet::from([left, right]);
+        class.extend(joined.into_iter().flatten());
+        classes.push(class);
+    }
+}
+
+/// Returns the equalities between columns in a conjunction of predicates.
+pub fn equalities(
+    predicates: &[Arc<ScalarExpression>],
+) -> impl Iterator<Item = (ColumnId, ColumnId)> + '_ {
+    predicates
+        .iter()
+        .filter_map(|predicate| predicate.as_column_equality())
+}
+
+/// Restricts equivalence classes to a set of columns, dropping the classes that are left with a
+/// single column.
+pub fn restrict(classes: &[ColumnSet], columns: &[ColumnId]) -> Vec<ColumnSet> {
+    classes
+        .iter()
+        .map(|class| {
+            class
+                .iter()
+                .copied()
+                .filter(|column| columns.contains(column))
+                .collect::<ColumnSet>()
+        })
+        .filter(|class| class.len() > 1)
+        .collect()
+}
+
+/// Returns the class that a column belongs to.
+pub fn class_of(classes: &[ColumnSet], column: ColumnId) -> Option<&ColumnSet> {
+    classes.iter().find(|class| class.contains(&column))
+}
+
+/// Infers the predicates that a conjunction of predicates implies, given the equivalence classes
+/// of the input that the predicates are evaluated on. Returns only the predicates that are new.
+///
+/// Every pair of equivalent columns gets an equality, unless the input already guarantees it, and
+/// every comparison between a column and a literal is copied to the columns that are equivalent to
+/// it.
+pub fn infer_predicates(
+    predicates: &[Arc<ScalarExpression>],
+    input: &[ColumnSet],
+) -> Vec<Arc<ScalarExpression>> {
+    let mut classes = input.to_vec();
+    add_equalities(&mut classes, equalities(predicates));
+
+    let mut inferred: Vec<Arc<ScalarExpression>> = vec![];
+    let mut add = |predicate: Arc<ScalarExpression>| {
+        if !predicates.contains(&predicate) && !inferred.contains(&predicate) {
+            inferred.push(predicate);
+        }
+    };
+
+    for class in &classes {
+        let columns: Vec<ColumnId> = class.iter().copied().collect();
+        for (index, &left) in columns.iter().enumerate() {
+            for &right in &columns[index + 1..] {
+                let implied = class_of(input, left).is_some_and(|class| class.contains(&right))
+                    || predicates.iter().any(|predicate| {
+                        matches!(
+                            predicate.as_column_equality(),
+                            Some(pair) if pair == (left, right) || pair == (right, left)
+                        )
+                    });
+                if !implied {
+                    add(ScalarExpression::eq(
+                        ScalarExpression::column(left),
+                        ScalarExpression::column(right),
+                    ));
+                }
+            }
+        }
+    }
+
+    for predicate in predicates {
+        let Some((column, op, value)) = predicate.as_column_comparison() else {
+            continue;
+        };
+        let Some(class) = class_of(&classes, column) else {
+            continue;
+        };
+
+        for &other in class.iter().filter(|&&other| other != column) {
+            let copy = ScalarExpression::compare(
+                op,
+                ScalarExpression::column(other),
+                ScalarExpression::literal(value.clone()),
+            );
+            let present = predicates
+                .iter()
+                .any(|predicate| predicate.as_column_comparison() == Some((other, op, value)));
+            if !present {
+                add(copy);
+            }
+        }
+    }
+
+    inferred
+}
@@ -0,0 +1,95 @@
+//! Logical properties of groups / equivalence classes.
+//!
+//! Every expression in a [`Group`](crate::Group) produces the same set of rows, so properties like
+//! the output schema, the number of rows, and unique keys are shared by the entire group. They are
+//! derived exactly once when a group is created (from the first logical expression that is added to
+//! it) and cached for the rules and the cost model to use.
+
+use crate::catalog::{ColumnId, DataType};
+use std::collections::BTreeSet;
+
+pub mod equivalence;
+
+#[cfg(test)]
+mod tests;
+
+/// A set of columns, used for keys.
+pub type ColumnSet = BTreeSet<ColumnId>;
+
+/// A single output column of a group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub id: ColumnId,
+    pub data_type: DataType,
+    pub nullable: bool,
+}
+
+/// The logical properties shared by every expression in a group.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogicalProperties {
+    /// The output columns, in order.
+    pub schema: Vec<Field>,
+    /// The estimated number of output rows.
+    pub row_count: f64,
+    /// Sets of columns that are guaranteed to be unique in the output.
+    ///
+    /// This is not necessarily minimal, but no key in this list is a superset of another key.
+    pub unique_keys: Vec<ColumnSet>,
+    /// The base tables that the group reads from.
+    pub tables: BTreeSet<usize>,
+    /// Disjoint sets of output columns that have the same value in every output row (see
+    /// [`equivalence`]).
+    pub equivalences: Vec<ColumnSet>,
+}
+
+impl LogicalProperties {
+    /// Returns the output field with the given column identifier.
+    pub fn field(&self, id: ColumnId) -> Option<&Field> {
+        self.schema.iter().find(|field| field.id == id)
+    }
+
+    /// Returns the identifiers of all of the output columns, in order.
+    pub fn column_ids(&self) -> Vec<ColumnId> {
+        self.schema.iter().map(|field| field.id).collect()
+    }
+
+    /// Returns `true` if the output contains the given column.
+    pub fn contains(&self, id: ColumnId) -> bool {
+        self.field(id).is_some()
+    }
+
+    /// The estimated width of an output row in bytes.
+    pub fn row_width(&self) -> usize {
+        self.schema
+            .iter()
+            .map(|field| field.data_type.width())
+            .sum()
+    }
+
+    /// Returns `true` if two columns have the same value in every output row.
+    pub fn are_equivalent(&self, left: ColumnId, right: ColumnId) -> bool {
+        left == right
+            || equivalence::class_of(&self.equivalences, left)
+                .is_some_and(|class| class.contains(&right))
+    }
+
+    /// Returns `true` if the given set of columns is guaranteed to be unique in the output.
+    pub fn is_unique(&self, columns: &ColumnSet) -> bool {
+        self.unique_keys.iter().any(|key| key.is_subset(columns))
+    }
+}
+
+/// Removes any key that is a superset of another key, since it adds no information.
+pub fn minimize_keys(mut keys: Vec<ColumnSet>) -> Vec<ColumnSet> {
+    keys.sort_by_key(|key| key.len());
+    keys.dedup();
+
+    let mut minimal: Vec<ColumnSet> = vec![];
+    for key in keys {
+        if !minimal.iter().any(|smaller| smaller.is_subset(&key)) {
+            minimal.push(key);
+        }
+    }
+
+    minimal
+}
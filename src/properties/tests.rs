@@ -0,0 +1,301 @@
+use crate::catalog::{Catalog, ColumnId, DataType};
+use crate::cost::Cost;
+use crate::expression::physical::{EmptyScan, PhysicalExpression};
+use crate::{Expression, Group, Join, JoinType, LogicalExpression, Memo, Scan, Winner};
+use std::sync::Arc;
+
+use super::*;
+
+/// Creates a catalog with an `orders` table keyed by `o_id` and a `customers` table keyed by
+/// `c_id`.
+fn catalog() -> Catalog {
+    let mut catalog = Catalog::new();
+
+    let orders = catalog.add_table(
+        "orders",
+        &[
+            ("o_id", DataType::Int64, false),
+            ("o_customer", DataType::Int64, false),
+        ],
+        1000,
+    );
+    catalog.add_unique_key(orders, vec![ColumnId(0)]);
+
+    let customers = catalog.add_table(
+        "customers",
+        &[
+            ("c_id", DataType::Int64, false),
+            ("c_name", DataType::Utf8, true),
+        ],
+        100,
+    );
+    catalog.add_unique_key(customers, vec![ColumnId(2)]);
+
+    catalog
+}
+
+fn scan(catalog: &Catalog, table_id: usize) -> Arc<Expression> {
+    Arc::new(Expression::Logical(LogicalExpression::Scan(Scan {
+        table_id,
+        columns: catalog.table(table_id).unwrap().column_ids(),
+        filters: vec![],
+    })))
+}
+
+#[test]
+fn scan_properties() {
+    let catalog = Arc::new(catalog());
+    let memo = Memo::new(catalog.clone());
+
+    let (group, _) = memo.add_expression(&scan(&catalog, 1), None);
+
+    assert_eq!(group.row_count(), 100.0);
+    assert_eq!(group.schema().len(), 2);
+    assert_eq!(group.schema()[1].data_type, DataType::Utf8);
+    assert!(group.schema()[1].nullable);
+    assert_eq!(group.unique_keys(), &[ColumnSet::from([ColumnId(2)])]);
+}
+
+#[test]
+fn scans_of_the_same_table_have_their_own_columns() {
+    use crate::expression::scalar::ScalarExpression;
+
+    let mut catalog = catalog();
+    let columns = catalog.add_scan(0);
+    let catalog = Arc::new(catalog);
+    let memo = Arc::new(Memo::new(catalog.clone()));
+
+    // `orders o1 JOIN orders o2 ON o1.o_customer = o2.o_id`
+    let other = Arc::new(Expression::Logical(LogicalExpression::Scan(Scan {
+        table_id: 0,
+        columns: columns.clone(),
+        filters: vec![],
+    })));
+    let (other_group, _) = memo.add_expression(&other, None);
+    let (group, _) = memo.add_expression(&scan(&catalog, 0), None);
+    assert_ne!(other_group.key(), group.key());
+    assert_eq!(other_group.schema()[0].data_type, DataType::Int64);
+    assert_eq!(other_group.unique_keys(), &[ColumnSet::from([columns[0]])]);
+
+    let join = Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+        join_type: JoinType::Inner,
+        conditions: vec![ScalarExpression::eq(
+            ScalarExpression::column(ColumnId(1)),
+            ScalarExpression::column(columns[0]),
+        )],
+        left: scan(&catalog, 0),
+        right: other,
+    })));
+    let (joined, _) = memo.add_expression(&join, None);
+    assert_eq!(joined.schema().len(), 4);
+    assert_eq!(
+        joined.unique_keys(),
+        &[ColumnSet::from([ColumnId(0), columns[0]])]
+    );
+    assert_eq!(joined.row_count(), 1000.0);
+}
+
+#[test]
+fn join_properties_are_shared_by_the_group() {
+    let catalog = Arc::new(catalog());
+    let memo = Arc::new(Memo::new(catalog.clone()));
+
+    let orders = scan(&catalog, 0);
+    let customers = scan(&catalog, 1);
+
+    let join = Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+        join_type: JoinType::LeftOuter,
+        conditions: vec![],
+        left: orders.clone(),
+        right: customers.clone(),
+    })));
+    let (group, added) = memo.add_expression(&join, None);
+    assert!(added);
+
+    // The right side of a left outer join becomes nullable.
+    let c_id = group.logical_properties().field(ColumnId(2)).unwrap();
+    assert!(c_id.nullable);
+    assert!(
+        !group
+            .logical_properties()
+            .field(ColumnId(0))
+            .unwrap()
+            .nullable
+    );
+    assert!(group
+        .logical_properties()
+        .is_unique(&ColumnSet::from([ColumnId(0), ColumnId(2)])));
+    assert_eq!(group.row_count(), 100_000.0);
+
+    // The commuted join belongs to the same group and does not change its properties.
+    let commuted = Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+        join_type: JoinType::RightOuter,
+        conditions: vec![],
+        left: customers,
+        right: orders,
+    })));
+    let (same_group, added) = memo.add_expression(&commuted, Some(group.key()));
+    assert!(added);
+    assert_eq!(same_group.key(), group.key());
+    assert_eq!(same_group.expressions().len(), 2);
+    assert_eq!(commuted.group(&memo).key(), group.key());
+
+    // Adding the same expression again is deduplicated.
+    let (_, added) = memo.add_expression(&commuted, None);
+    assert!(!added);
+    assert_eq!(group.expressions().len(), 2);
+}
+
+#[test]
+fn expressions_with_the_same_fingerprint_are_told_apart() {
+    let catalog = Arc::new(catalog());
+    let memo = Arc::new(Memo::new(catalog.clone()));
+    let (orders, customers) = (scan(&catalog, 0), scan(&catalog, 1));
+
+    // Pretend that the scan of the orders has the fingerprint of the scan of the customers.
+    let (orders_group, _) = memo.add_expression(&orders, None);
+    let (_, entries) = memo
+        .fingerprints
+        .remove(&orders.memo_fingerprint(&[]))
+        .unwrap();
+    memo.fingerprints
+        .insert(customers.memo_fingerprint(&[]), entries);
+
+    // The scan of the customers is still a new expression in its own group.
+    let (customers_group, added) = memo.add_expression(&customers, None);
+    assert!(added);
+    assert_ne!(customers_group.key(), orders_group.key());
+    assert_eq!(customers.group(&memo).key(), customers_group.key());
+    assert_eq!(orders.memo_key(&memo), None);
+}
+
+#[test]
+fn equivalent_groups_are_merged() {
+    let catalog = Arc::new(catalog());
+    let memo = Arc::new(Memo::new(catalog.clone()));
+
+    let join = |join_type, left: &Arc<Expression>, right: &Arc<Expression>| {
+        Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+            join_type,
+            conditions: vec![],
+            left: left.clone(),
+            right: right.clone(),
+        })))
+    };
+    let (orders, customers) = (scan(&catalog, 0), scan(&catalog, 1));
+    let left = join(JoinType::LeftOuter, &orders, &customers);
+    let right = join(JoinType::RightOuter, &customers, &orders);
+
+    // Both joins end up in their own group, and so do the self joins on top of them.
+    let (left_group, _) = memo.add_expression(&join(JoinType::Inner, &left, &left), None);
+    let (right_group, _) = memo.add_expression(&join(JoinType::Inner, &right, &left), None);
+    assert_ne!(left_group.key(), right_group.key());
+
+    // The group that is merged away has the cheaper plan.
+    let win = |group: &Arc<Group>, cpu| {
+        let expression = Arc::new(Expression::Physical(PhysicalExpression::EmptyScan(
+            EmptyScan { columns: vec![] },
+        )));
+        group.winner.store(Some(Arc::new(Winner {
+            expression,
+            cost: Cost::cpu(cpu),
+        })));
+    };
+    win(&left.group(&memo), 10);
+    win(&right.group(&memo), 5);
+
+    // Finding out that the joins are equivalent merges their groups, and therefore also the groups
+    // of the self joins.
+    let (group, added) = memo.add_expression(&right, Some(left.group(&memo).key()));
+    assert!(!added);
+    assert_eq!(group.key(), left.group(&memo).key());
+    assert_eq!(right.group(&memo).key(), group.key());
+    assert_eq!(group.expressions().len(), 2);
+    assert_eq!(group.winner().map(|(_, cost)| cost), Some(Cost::cpu(5)));
+
+    let merged = memo.group(right_group.key()).unwrap();
+    assert_eq!(merged.key(), memo.group(left_group.key()).unwrap().key());
+    assert_eq!(merged.expressions().len(), 1);
+}
+
+#[test]
+fn equivalences_are_derived_from_equalities() {
+    use crate::expression::scalar::ScalarExpression;
+    use crate::{Filter, Project};
+
+    let catalog = Arc::new(catalog());
+    let memo = Arc::new(Memo::new(catalog.clone()));
+    let column = |id| ScalarExpression::column(ColumnId(id));
+
+    // `orders JOIN customers ON o_customer = c_id`
+    let join = |join_type| {
+        Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+            join_type,
+            conditions: vec![ScalarExpression::eq(column(1), column(2))],
+            left: scan(&catalog, 0),
+            right: scan(&catalog, 1),
+        })))
+    };
+
+    let (inner, _) = memo.add_expression(&join(JoinType::Inner), None);
+    assert_eq!(
+        inner.equivalences(),
+        &[ColumnSet::from([ColumnId(1), ColumnId(2)])]
+    );
+    assert!(inner
+        .logical_properties()
+        .are_equivalent(ColumnId(2), ColumnId(1)));
+
+    // The customers that an outer join pads with `NULL`s do not match any order.
+    let (outer, _) = memo.add_expression(&join(JoinType::LeftOuter), None);
+    assert!(outer.equivalences().is_empty());
+
+    // `o_id = o_customer` on top makes all three columns equivalent, until they are projected away.
+    let filter = Arc::new(Expression::Logical(LogicalExpression::Filter(Filter {
+        filters: vec![ScalarExpression::eq(column(0), column(1))],
+        children: join(JoinType::Inner),
+    })));
+    let (filtered, _) = memo.add_expression(&filter, None);
+    assert_eq!(
+        filtered.equivalences(),
+        &[ColumnSet::from([ColumnId(0), ColumnId(1), ColumnId(2)])]
+    );
+
+    let project = Arc::new(Expression::Logical(LogicalExpression::Project(Project {
+        columns: vec![ColumnId(0), ColumnId(3)],
+        children: filter,
+    })));
+    let (projected, _) = memo.add_expression(&project, None);
+    assert!(projected.equivalences().is_empty());
+}
+
+#[test]
+fn correlated_equivalences_do_not_leave_dependent_joins() {
+    use crate::expression::scalar::ScalarExpression;
+    use crate::{Apply, Filter};
+
+    let catalog = Arc::new(catalog());
+    let memo = Arc::new(Memo::new(catalog.clone()));
+
+    // `orders LEFT JOIN LATERAL (SELECT * FROM customers WHERE c_id = o_customer)`, which pads the
+    // orders without a customer with a `NULL` `c_id`.
+    let apply = Arc::new(Expression::Logical(LogicalExpression::Apply(Apply {
+        join_type: JoinType::LeftOuter,
+        conditions: vec![],
+        left: scan(&catalog, 0),
+        right: Arc::new(Expression::Logical(LogicalExpression::Filter(Filter {
+            filters: vec![ScalarExpression::eq(
+                ScalarExpression::column(ColumnId(2)),
+                ScalarExpression::column(ColumnId(1)),
+            )],
+            children: scan(&catalog, 1),
+        }))),
+    })));
+
+    let (group, _) = memo.add_expression(&apply, None);
+    assert!(
+        group.equivalences().is_empty(),
+        "{:?}",
+        group.equivalences()
+    );
+}
@@ -1,5 +1,10 @@
 use super::StaticRule;
-use crate::{Expression, HashJoin, LogicalExpression, PhysicalExpression, TableScan};
+use crate::expression::logical::{output_columns, SetOperation};
+use crate::{
+    EmptyScan, Expression, HashAggregate, HashJoin, HashSetOperation, LogicalExpression, MergeSort,
+    NestedLoopApply, PhysicalExpression, Projection, Relation, Selection, SortSetOperation,
+    StreamingLimit, TableScan, TopN, UnionAll,
+};
 use std::sync::Arc;
 
 /// Static implementation rules transforming logical expressions into both logical and physical
@@ -8,7 +13,21 @@ use std::sync::Arc;
 /// TODO:
 /// We may want to represent this differently to keep track of promise values.
 /// Should this allow easy reordering of the rules?
-static STATIC_IMPLEMENTATION_RULES: [StaticRule; 2] = [table_scan, hash_join];
+pub static STATIC_IMPLEMENTATION_RULES: [StaticRule; 13] = [
+    table_scan,
+    projection,
+    selection,
+    hash_join,
+    nested_loop_apply,
+    hash_aggregate,
+    top_n,
+    merge_sort,
+    streaming_limit,
+    union_all,
+    hash_set_operation,
+    sort_set_operation,
+    empty_scan,
+];
 
 /// An implementation rule that turns a logical scan into a table scan.
 pub fn table_scan(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
@@ -19,11 +38,44 @@ pub fn table_scan(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
     Some(Arc::new(Expression::Physical(
         PhysicalExpression::TableScan(TableScan {
             table_id: scan.table_id,
-            filters: scan.filters,
+            columns: scan.columns.clone(),
+            filters: scan.filters.clone(),
         }),
     )))
 }
 
+/// An implementation rule that turns a logical projection into a physical projection.
+pub fn projection(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let Expression::Logical(LogicalExpression::Project(project)) = expr.as_ref() else {
+        return None;
+    };
+
+    Some(Arc::new(Expression::Physical(
+        PhysicalExpression::Projection(Projection {
+            columns: project.columns.clone(),
+            child: project.children.clone(),
+        }),
+    )))
+}
+
+/// An implementation rule that turns a logical filter into a selection, for the filters that could
+/// not be merged into the scan or join below them.
+pub fn selection(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let Expression::Logical(LogicalExpression::Filter(filter)) = expr.as_ref() else {
+        return None;
+    };
+
+    Some(Arc::new(Expression::Physical(
+        PhysicalExpression::Selection(Selection {
+            filters: filter.filters.clone(),
+            child: filter.children.clone(),
+        }),
+    )))
+}
+
+/// The amount of memory given to the hash table of a hash join by default.
+pub const DEFAULT_HASH_TABLE_SIZE: usize = 64 * 1024 * 1024;
+
 /// An implementation rule that turns a logical join into a hash join.
 pub fn hash_join(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
     let Expression::Logical(LogicalExpression::Join(join)) = expr.as_ref() else {
@@ -32,11 +84,169 @@ pub fn hash_join(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
 
     Some(Arc::new(Expression::Physical(
         PhysicalExpression::HashJoin(HashJoin {
-            join_type: (),
-            hash_table_size: 42,
-            partitions: 42,
+            join_type: join.join_type,
+            conditions: join.conditions.clone(),
+            hash_table_size: DEFAULT_HASH_TABLE_SIZE,
+            partitions: 1,
             left: join.left.clone(),
             right: join.right.clone(),
         }),
     )))
 }
+
+/// An implementation rule that turns a dependent join into a nested loop apply.
+pub fn nested_loop_apply(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let Expression::Logical(LogicalExpression::Apply(apply)) = expr.as_ref() else {
+        return None;
+    };
+
+    Some(Arc::new(Expression::Physical(
+        PhysicalExpression::NestedLoopApply(NestedLoopApply {
+            join_type: apply.join_type,
+            conditions: apply.conditions.clone(),
+            left: apply.left.clone(),
+            right: apply.right.clone(),
+        }),
+    )))
+}
+
+/// An implementation rule that turns a logical aggregate into a hash aggregate.
+pub fn hash_aggregate(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let Expression::Logical(LogicalExpression::Aggregate(aggregate)) = expr.as_ref() else {
+        return None;
+    };
+
+    Some(Arc::new(Expression::Physical(
+        PhysicalExpression::HashAggregate(HashAggregate {
+            phase: aggregate.phase,
+            group_by: aggregate.group_by.clone(),
+            aggregates: aggregate.aggregates.clone(),
+            child: aggregate.children.clone(),
+        }),
+    )))
+}
+
+/// An implementation rule that turns a logical sort into a merge sort.
+pub fn merge_sort(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let Expression::Logical(LogicalExpression::Sort(sort)) = expr.as_ref() else {
+        return None;
+    };
+
+    Some(Arc::new(Expression::Physical(
+        PhysicalExpression::MergeSort(MergeSort {
+            order: sort.order.clone(),
+            child: sort.children.clone(),
+        }),
+    )))
+}
+
+/// An implementation rule that turns a limit on top of a sort into a top-n, which only keeps the
+/// rows that can still be among the first ones in memory.
+pub fn top_n(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let Expression::Logical(LogicalExpression::Limit(limit)) = expr.as_ref() else {
+        return None;
+    };
+
+    let Expression::Logical(LogicalExpression::Sort(sort)) = limit.children.as_ref() else {
+        return None;
+    };
+
+    Some(Arc::new(Expression::Physical(PhysicalExpression::TopN(
+        TopN {
+            order: sort.order.clone(),
+            limit: limit.limit,
+            offset: limit.offset,
+            child: sort.children.clone(),
+        },
+    ))))
+}
+
+/// An implementation rule that turns a logical limit into a streaming limit.
+pub fn streaming_limit(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let Expression::Logical(LogicalExpression::Limit(limit)) = expr.as_ref() else {
+        return None;
+    };
+
+    Some(Arc::new(Expression::Physical(
+        PhysicalExpression::StreamingLimit(StreamingLimit {
+            limit: limit.limit,
+            offset: limit.offset,
+            child: limit.children.clone(),
+        }),
+    )))
+}
+
+/// An implementation rule that turns a logical `UNION ALL` into a union all.
+pub fn union_all(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let Expression::Logical(LogicalExpression::Union(union)) = expr.as_ref() else {
+        return None;
+    };
+    if !union.all {
+        return None;
+    }
+
+    Some(Arc::new(Expression::Physical(
+        PhysicalExpression::UnionAll(UnionAll {
+            columns: union.columns.clone(),
+            children: union.inputs.clone(),
+        }),
+    )))
+}
+
+/// Returns the set operation and whether it keeps duplicates if the expression is a set operation
+/// that has to compare rows, which is any set operation except for `UNION ALL`.
+fn set_operation_of(expr: &Arc<Expression>) -> Option<(SetOperation, bool)> {
+    match expr.as_ref() {
+        Expression::Logical(LogicalExpression::Union(union)) if !union.all => {
+            Some((SetOperation::Union, false))
+        }
+        Expression::Logical(LogicalExpression::Intersect(intersect)) => {
+            Some((SetOperation::Intersect, intersect.all))
+        }
+        Expression::Logical(LogicalExpression::Except(except)) => {
+            Some((SetOperation::Except, except.all))
+        }
+        _ => None,
+    }
+}
+
+/// An implementation rule that turns a set operation that compares rows into a hash set operation.
+pub fn hash_set_operation(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let (operation, all) = set_operation_of(expr)?;
+
+    Some(Arc::new(Expression::Physical(
+        PhysicalExpression::HashSetOperation(HashSetOperation {
+            operation,
+            all,
+            columns: output_columns(expr),
+            children: expr.children(),
+        }),
+    )))
+}
+
+/// An implementation rule that turns a set operation that compares rows into a sort set operation.
+pub fn sort_set_operation(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let (operation, all) = set_operation_of(expr)?;
+
+    Some(Arc::new(Expression::Physical(
+        PhysicalExpression::SortSetOperation(SortSetOperation {
+            operation,
+            all,
+            columns: output_columns(expr),
+            children: expr.children(),
+        }),
+    )))
+}
+
+/// An implementation rule that turns an empty relation into a scan that produces no rows.
+pub fn empty_scan(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let Expression::Logical(LogicalExpression::Empty(empty)) = expr.as_ref() else {
+        return None;
+    };
+
+    Some(Arc::new(Expression::Physical(
+        PhysicalExpression::EmptyScan(EmptyScan {
+            columns: empty.columns.clone(),
+        }),
+    )))
+}
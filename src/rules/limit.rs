@@ -0,0 +1,167 @@
+//! Rules that push limits down towards the scans, so that fewer rows are produced and joined only
+//! to be thrown away.
+//!
+//! A limit without a sort below it may output any of the rows of its input, so it can be copied
+//! into every input that is guaranteed to produce at least as many rows as it needs: both branches
+//! of a union ([`limit_union_pushdown`]) and the preserved side of an outer join
+//! ([`limit_outer_join_pushdown`]). The original limit stays on top, since the inputs may still
+//! produce too many rows together. A limit on top of a sort is implemented as a top-n (see
+//! [`top_n`](super::implementation::top_n)), which is why projections are moved below limits
+//! ([`limit_project_pushdown`]): they would otherwise hide the sort.
+
+use crate::{Expression, Join, JoinType, Limit, LogicalExpression, Project, Union};
+use std::sync::Arc;
+
+/// Returns the limit if the expression is one.
+fn limit_of(expr: &Arc<Expression>) -> Option<&Limit> {
+    match expr.as_ref() {
+        Expression::Logical(LogicalExpression::Limit(limit)) => Some(limit),
+        _ => None,
+    }
+}
+
+/// Returns `true` if an expression is limited to at most `count` rows, possibly below projections
+/// (which [`limit_project_pushdown`] moves limits below).
+fn is_limited(expr: &Arc<Expression>, count: usize) -> bool {
+    match expr.as_ref() {
+        Expression::Logical(LogicalExpression::Limit(limit)) => {
+            limit.offset == 0 && limit.limit <= count
+        }
+        Expression::Logical(LogicalExpression::Project(project)) => {
+            is_limited(&project.children, count)
+        }
+        _ => false,
+    }
+}
+
+/// Puts a limit of `count` rows on top of an expression, or returns `None` if the expression is
+/// already limited to at most that many rows.
+fn limited(expr: &Arc<Expression>, count: usize) -> Option<Arc<Expression>> {
+    if is_limited(expr, count) {
+        return None;
+    }
+
+    Some(Arc::new(Expression::Logical(LogicalExpression::Limit(
+        Limit {
+            limit: count,
+            offset: 0,
+            children: expr.clone(),
+        },
+    ))))
+}
+
+/// A rule that merges adjacent limits.
+///
+/// `Limit(l1, offset: o1, Limit(l2, offset: o2, A))` outputs the same rows as `Limit(min(l1, l2 -
+/// o1), offset: o1 + o2, A)`.
+pub fn limit_merge(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let top = limit_of(expr)?;
+    let bottom = limit_of(&top.children)?;
+
+    Some(Arc::new(Expression::Logical(LogicalExpression::Limit(
+        Limit {
+            limit: top.limit.min(bottom.limit.saturating_sub(top.offset)),
+            offset: top.offset + bottom.offset,
+            children: bottom.children.clone(),
+        },
+    ))))
+}
+
+/// A rule that moves a limit below the projection under it.
+///
+/// `Limit(n, Project(a, A))` is logically equivalent to `Project(a, Limit(n, A))`, since a
+/// projection neither adds, removes nor reorders rows.
+pub fn limit_project_pushdown(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let limit = limit_of(expr)?;
+
+    let Expression::Logical(LogicalExpression::Project(project)) = limit.children.as_ref() else {
+        return None;
+    };
+
+    let pushed = Arc::new(Expression::Logical(LogicalExpression::Limit(Limit {
+        children: project.children.clone(),
+        ..limit.clone()
+    })));
+
+    Some(Arc::new(Expression::Logical(LogicalExpression::Project(
+        Project {
+            columns: project.columns.clone(),
+            children: pushed,
+        },
+    ))))
+}
+
+/// A rule that copies a limit into the preserved side of the outer join under it.
+///
+/// `Limit(n, LeftOuterJoin(A, B))` is logically equivalent to `Limit(n, LeftOuterJoin(Limit(n, A),
+/// B))`, since every row of `A` produces at least one row of the join. The same holds for the
+/// right side of a right outer join.
+pub fn limit_outer_join_pushdown(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let limit = limit_of(expr)?;
+
+    let Expression::Logical(LogicalExpression::Join(join)) = limit.children.as_ref() else {
+        return None;
+    };
+
+    let count = limit.limit + limit.offset;
+    let join = match join.join_type {
+        JoinType::LeftOuter => Join {
+            left: limited(&join.left, count)?,
+            ..join.clone()
+        },
+        JoinType::RightOuter => Join {
+            right: limited(&join.right, count)?,
+            ..join.clone()
+        },
+        _ => return None,
+    };
+
+    Some(Arc::new(Expression::Logical(LogicalExpression::Limit(
+        Limit {
+            children: Arc::new(Expression::Logical(LogicalExpression::Join(join))),
+            ..limit.clone()
+        },
+    ))))
+}
+
+/// A rule that copies a limit into every input of the `UNION ALL` under it.
+///
+/// `Limit(n, UnionAll(A, B))` is logically equivalent to `Limit(n, UnionAll(Limit(n, A), Limit(n,
+/// B)))`, since any `n` rows of the union will do.
+pub fn limit_union_pushdown(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let limit = limit_of(expr)?;
+
+    // The inputs of a union without duplicates may have fewer distinct rows than they need.
+    let Expression::Logical(LogicalExpression::Union(union)) = limit.children.as_ref() else {
+        return None;
+    };
+    if !union.all {
+        return None;
+    }
+
+    let count = limit.limit + limit.offset;
+    let limited: Vec<Option<Arc<Expression>>> = union
+        .inputs
+        .iter()
+        .map(|input| limited(input, count))
+        .collect();
+    if limited.iter().all(Option::is_none) {
+        return None;
+    }
+
+    let inputs = limited
+        .into_iter()
+        .zip(&union.inputs)
+        .map(|(limited, input)| limited.unwrap_or_else(|| input.clone()))
+        .collect();
+
+    Some(Arc::new(Expression::Logical(LogicalExpression::Limit(
+        Limit {
+            children: Arc::new(Expression::Logical(LogicalExpression::Union(Union {
+                inputs,
+                ..union.clone()
+            }))),
+            ..limit.clone()
+        },
+    ))))
+}
@@ -1,10 +1,16 @@
 #![allow(dead_code)] // TODO remove this
 
+use crate::cardinality::Estimator;
 use crate::Expression;
 use std::sync::Arc;
 
 pub mod implementation;
+pub mod limit;
+pub mod pruning;
+pub mod rewrite;
+pub mod set_operations;
 pub mod transformation;
+pub mod unnesting;
 
 #[cfg(test)]
 mod tests;
@@ -27,4 +33,24 @@ pub trait Rule: Fn(&Arc<Expression>) -> Option<Arc<Expression>> {}
 
 pub type StaticRule = fn(&Arc<Expression>) -> Option<Arc<Expression>>;
 
-impl Rule for StaticRule {}
+impl<F: Fn(&Arc<Expression>) -> Option<Arc<Expression>>> Rule for F {}
+
+/// A rule that needs to know more about an expression than its structure, such as the unique keys
+/// of the tables it reads. The estimator gives access to the catalog.
+pub type CatalogRule = fn(&Arc<Expression>, &Estimator) -> Option<Arc<Expression>>;
+
+/// Turns a rule that needs the catalog into a regular rule.
+pub fn bind(rule: CatalogRule, estimator: &Estimator) -> Arc<dyn Rule> {
+    let estimator = estimator.clone();
+    Arc::new(move |expr: &Arc<Expression>| rule(expr, &estimator))
+}
+
+/// Pairs every rule in a static rule list with a promise value, where earlier rules are more
+/// promising than later rules.
+pub fn with_promises(rules: &[StaticRule]) -> Vec<(Arc<dyn Rule>, usize)> {
+    rules
+        .iter()
+        .enumerate()
+        .map(|(index, &rule)| (Arc::new(rule) as Arc<dyn Rule>, rules.len() - index))
+        .collect()
+}
@@ -0,0 +1,246 @@
+//! Column pruning, a heuristic rewrite that removes the columns that are never used from a logical
+//! plan before it is added to the memo table.
+//!
+//! The columns required at every operator are computed top-down: an operator needs the columns
+//! that its parent needs, plus the columns referenced by its own predicates. Scans only output the
+//! columns that are needed, projections are narrowed to the needed columns (and removed if they do
+//! not remove anything), and the inputs of joins are narrowed with a projection when they produce
+//! columns that neither the join nor anything above it uses.
+//!
+//! Projections are never placed directly on top of a join that is the input of another join, since
+//! that would hide the joins from the join reordering rules.
+
+use crate::catalog::ColumnId;
+use crate::expression::logical::{outer_references, output_columns};
+use crate::properties::ColumnSet;
+use crate::{
+    Aggregate, Apply, Empty, Except, Expression, Filter, Intersect, Join, Limit, LogicalExpression,
+    Project, Scan, Sort, Union,
+};
+use std::sync::Arc;
+
+/// Removes the columns that are not needed to compute the output of a logical plan.
+///
+/// The output columns of the plan (and their order) do not change.
+///
+/// # Panics
+///
+/// Panics if the plan contains physical expressions.
+pub fn prune_columns(expr: &Arc<Expression>) -> Arc<Expression> {
+    let output = output_columns(expr);
+    let pruned = prune(expr, &output.iter().copied().collect());
+    project(pruned, output)
+}
+
+/// Prunes an expression so that it outputs the required columns, and as few others as possible.
+fn prune(expr: &Arc<Expression>, required: &ColumnSet) -> Arc<Expression> {
+    let Expression::Logical(logical) = expr.as_ref() else {
+        panic!("only logical plans can be pruned");
+    };
+
+    match logical {
+        LogicalExpression::Scan(scan) => {
+            let columns = scan
+                .columns
+                .iter()
+                .copied()
+                .filter(|column| required.contains(column))
+                .collect();
+
+            Arc::new(Expression::Logical(LogicalExpression::Scan(Scan {
+                columns,
+                ..scan.clone()
+            })))
+        }
+        LogicalExpression::Filter(filter) => {
+            let mut needed = required.clone();
+            needed.extend(
+                filter
+                    .filters
+                    .iter()
+                    .flat_map(|predicate| predicate.columns()),
+            );
+
+            Arc::new(Expression::Logical(LogicalExpression::Filter(Filter {
+                filters: filter.filters.clone(),
+                children: prune(&filter.children, &needed),
+            })))
+        }
+        LogicalExpression::Project(project_expr) => {
+            let columns: Vec<ColumnId> = project_expr
+                .columns
+                .iter()
+                .copied()
+                .filter(|column| required.contains(column))
+                .collect();
+
+            let child = prune(&project_expr.children, &columns.iter().copied().collect());
+            project(child, columns)
+        }
+        LogicalExpression::Join(join) => {
+            let mut needed = required.clone();
+            needed.extend(
+                join.conditions
+                    .iter()
+                    .flat_map(|condition| condition.columns()),
+            );
+
+            Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+                left: prune_side(&join.left, &needed),
+                right: prune_side(&join.right, &needed),
+                ..join.clone()
+            })))
+        }
+        LogicalExpression::Apply(apply) => {
+            // The left side also has to output the columns that the right side is correlated on.
+            let mut needed = required.clone();
+            needed.extend(
+                apply
+                    .conditions
+                    .iter()
+                    .flat_map(|condition| condition.columns()),
+            );
+            let left_columns = output_columns(&apply.left).into_iter().collect();
+            needed.extend(outer_references(&apply.right, &left_columns));
+
+            Arc::new(Expression::Logical(LogicalExpression::Apply(Apply {
+                left: prune_side(&apply.left, &needed),
+                right: prune_side(&apply.right, &needed),
+                ..apply.clone()
+            })))
+        }
+        LogicalExpression::Aggregate(aggregate) => {
+            // The grouping columns are needed to form the groups, even if nothing above uses them.
+            let aggregates = aggregate
+                .aggregates
+                .iter()
+                .filter(|call| required.contains(&call.output))
+                .cloned()
+                .collect();
+            let pruned = Aggregate {
+                aggregates,
+                ..aggregate.clone()
+            };
+
+            Arc::new(Expression::Logical(LogicalExpression::Aggregate(
+                Aggregate {
+                    children: prune(&aggregate.children, &pruned.input_columns()),
+                    ..pruned
+                },
+            )))
+        }
+        LogicalExpression::Sort(sort) => {
+            let mut needed = required.clone();
+            needed.extend(sort.order.iter().map(|key| key.column));
+
+            Arc::new(Expression::Logical(LogicalExpression::Sort(Sort {
+                order: sort.order.clone(),
+                children: prune(&sort.children, &needed),
+            })))
+        }
+        LogicalExpression::Limit(limit) => {
+            Arc::new(Expression::Logical(LogicalExpression::Limit(Limit {
+                children: prune(&limit.children, required),
+                ..limit.clone()
+            })))
+        }
+        LogicalExpression::Union(union) => {
+            // The columns of the inputs are matched by position, so every input is projected onto
+            // the columns at the positions that are still needed. Without duplicates, every column
+            // is needed to tell the rows apart.
+            let positions: Vec<usize> = (0..union.columns.len())
+                .filter(|&index| !union.all || required.contains(&union.columns[index]))
+                .collect();
+            let inputs = union
+                .inputs
+                .iter()
+                .map(|input| {
+                    let columns = output_columns(input);
+                    let columns: Vec<ColumnId> =
+                        positions.iter().map(|&index| columns[index]).collect();
+                    project(prune(input, &columns.iter().copied().collect()), columns)
+                })
+                .collect();
+
+            Arc::new(Expression::Logical(LogicalExpression::Union(Union {
+                all: union.all,
+                columns: positions
+                    .iter()
+                    .map(|&index| union.columns[index])
+                    .collect(),
+                inputs,
+            })))
+        }
+        LogicalExpression::Intersect(intersect) => {
+            // Whole rows are compared, so every column of both inputs is needed.
+            Arc::new(Expression::Logical(LogicalExpression::Intersect(
+                Intersect {
+                    all: intersect.all,
+                    left: prune_columns(&intersect.left),
+                    right: prune_columns(&intersect.right),
+                },
+            )))
+        }
+        LogicalExpression::Except(except) => {
+            Arc::new(Expression::Logical(LogicalExpression::Except(Except {
+                all: except.all,
+                left: prune_columns(&except.left),
+                right: prune_columns(&except.right),
+            })))
+        }
+        LogicalExpression::Empty(empty) => {
+            let columns = empty
+                .columns
+                .iter()
+                .copied()
+                .filter(|column| required.contains(column))
+                .collect();
+
+            Arc::new(Expression::Logical(LogicalExpression::Empty(Empty {
+                columns,
+            })))
+        }
+    }
+}
+
+/// Prunes one side of a join so that it outputs the columns that are needed above the join, and
+/// narrows it with a projection if it outputs any others.
+fn prune_side(side: &Arc<Expression>, needed: &ColumnSet) -> Arc<Expression> {
+    let side_needed: ColumnSet = output_columns(side)
+        .into_iter()
+        .filter(|column| needed.contains(column))
+        .collect();
+    let pruned = prune(side, &side_needed);
+
+    if matches!(
+        pruned.as_ref(),
+        Expression::Logical(LogicalExpression::Join(_))
+    ) {
+        return pruned;
+    }
+
+    let columns = output_columns(&pruned)
+        .into_iter()
+        .filter(|column| side_needed.contains(column))
+        .collect();
+    project(pruned, columns)
+}
+
+/// Projects an expression onto the given columns, unless it already outputs exactly those columns.
+///
+/// A projection directly on top of another projection replaces it.
+fn project(expr: Arc<Expression>, columns: Vec<ColumnId>) -> Arc<Expression> {
+    if output_columns(&expr) == columns {
+        return expr;
+    }
+
+    let children = match expr.as_ref() {
+        Expression::Logical(LogicalExpression::Project(below)) => below.children.clone(),
+        _ => expr,
+    };
+
+    Arc::new(Expression::Logical(LogicalExpression::Project(Project {
+        columns,
+        children,
+    })))
+}
@@ -0,0 +1,168 @@
+//! A heuristic rewrite phase, which applies rules that are always beneficial to a logical plan
+//! before it is added to the memo table.
+//!
+//! Unlike the search engine, the [`Rewriter`] does not keep the original expressions around: every
+//! time a rule matches, its result replaces the expression. This is much cheaper than exploring the
+//! rules in the memo table, and the smaller plan makes the search space smaller as well.
+
+use super::limit::{
+    limit_merge, limit_outer_join_pushdown, limit_project_pushdown, limit_union_pushdown,
+};
+use super::set_operations::{filter_set_operation_pushdown, union_flattening};
+use super::transformation::join_elimination;
+use super::transformation::{
+    empty_propagation, filter_into_scan, filter_join_pushdown, filter_merge,
+    join_condition_pushdown, outer_join_simplification, predicate_inference,
+    predicate_simplification, project_merge, project_removal,
+};
+use super::unnesting::{
+    apply_aggregate_unnesting, apply_decorrelation, apply_project_pullup,
+    correlated_predicate_pullup,
+};
+use super::{bind, CatalogRule, Rule, StaticRule};
+use crate::cardinality::Estimator;
+use crate::expression::physical::{PhysicalExpression, Projection};
+use crate::{Expression, Relation};
+use std::sync::Arc;
+
+/// Rules that always make a plan cheaper (or at least never more expensive): they simplify
+/// predicates, infer new predicates from column equivalences, turn outer joins into inner joins,
+/// unnest correlated subqueries, merge filters, projections, limits and unions, push predicates
+/// and limits as far down as possible, and remove projections that do not do anything and
+/// relations that cannot have any rows.
+pub static HEURISTIC_REWRITE_RULES: [StaticRule; 20] = [
+    predicate_simplification,
+    empty_propagation,
+    predicate_inference,
+    outer_join_simplification,
+    apply_decorrelation,
+    correlated_predicate_pullup,
+    apply_project_pullup,
+    apply_aggregate_unnesting,
+    filter_merge,
+    filter_into_scan,
+    filter_join_pushdown,
+    filter_set_operation_pushdown,
+    join_condition_pushdown,
+    project_merge,
+    project_removal,
+    union_flattening,
+    limit_merge,
+    limit_project_pushdown,
+    limit_outer_join_pushdown,
+    limit_union_pushdown,
+];
+
+/// Rules that always make a plan cheaper, but need the catalog: they remove joins that do not
+/// contribute anything to the result.
+pub static HEURISTIC_CATALOG_REWRITE_RULES: [CatalogRule; 1] = [join_elimination];
+
+/// Rules that clean up the physical plan that the search engine picked.
+pub static POST_PROCESSING_RULES: [StaticRule; 1] = [projection_merge];
+
+/// A rule that merges adjacent physical projections.
+///
+/// `Projection(a, Projection(a, b, A))` outputs the same rows as `Projection(a, A)`.
+pub fn projection_merge(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let Expression::Physical(PhysicalExpression::Projection(top)) = expr.as_ref() else {
+        return None;
+    };
+
+    let Expression::Physical(PhysicalExpression::Projection(bottom)) = top.child.as_ref() else {
+        return None;
+    };
+
+    Some(Arc::new(Expression::Physical(
+        PhysicalExpression::Projection(Projection {
+            columns: top.columns.clone(),
+            child: bottom.child.clone(),
+        }),
+    )))
+}
+
+/// Applies a rule set to a plan bottom-up until none of the rules match anymore.
+///
+/// The rule set must terminate: a rule set with a rule that can undo another rule (such as join
+/// commutativity) never reaches a fixpoint, so the rewriter stops after a fixed number of rewrites.
+pub struct Rewriter {
+    rules: Vec<Arc<dyn Rule>>,
+    max_rewrites: usize,
+}
+
+impl Default for Rewriter {
+    /// A rewriter with the [`HEURISTIC_REWRITE_RULES`].
+    fn default() -> Self {
+        Self::from_static(&HEURISTIC_REWRITE_RULES)
+    }
+}
+
+impl Rewriter {
+    pub fn new(rules: Vec<Arc<dyn Rule>>) -> Self {
+        Self {
+            rules,
+            max_rewrites: 10_000,
+        }
+    }
+
+    /// A rewriter with the [`HEURISTIC_REWRITE_RULES`], followed by the
+    /// [`HEURISTIC_CATALOG_REWRITE_RULES`] for the catalog of the estimator.
+    pub fn heuristic(estimator: &Estimator) -> Self {
+        let mut rewriter = Self::default();
+        rewriter.rules.extend(
+            HEURISTIC_CATALOG_REWRITE_RULES
+                .iter()
+                .map(|&rule| bind(rule, estimator)),
+        );
+        rewriter
+    }
+
+    pub fn from_static(rules: &[StaticRule]) -> Self {
+        Self::new(
+            rules
+                .iter()
+                .map(|&rule| Arc::new(rule) as Arc<dyn Rule>)
+                .collect(),
+        )
+    }
+
+    /// Limits the number of rewrites, after which the plan is returned as is.
+    pub fn with_max_rewrites(mut self, max_rewrites: usize) -> Self {
+        self.max_rewrites = max_rewrites;
+        self
+    }
+
+    /// Rewrites a plan until none of the rules match any of its expressions.
+    pub fn rewrite(&self, expr: &Arc<Expression>) -> Arc<Expression> {
+        let mut budget = self.max_rewrites;
+        self.rewrite_bottom_up(expr, &mut budget)
+    }
+
+    /// Rewrites the children of an expression, and then the expression itself. When a rule
+    /// matches, the result may have new children that have not been rewritten yet, so it is
+    /// rewritten from the bottom up again.
+    fn rewrite_bottom_up(&self, expr: &Arc<Expression>, budget: &mut usize) -> Arc<Expression> {
+        let children = expr.children();
+        let expr = if children.is_empty() {
+            expr.clone()
+        } else {
+            let children = children
+                .iter()
+                .map(|child| self.rewrite_bottom_up(child, budget))
+                .collect();
+            Arc::new(expr.with_children(children))
+        };
+
+        for rule in &self.rules {
+            if *budget == 0 {
+                break;
+            }
+
+            if let Some(rewritten) = rule(&expr) {
+                *budget -= 1;
+                return self.rewrite_bottom_up(&rewritten, budget);
+            }
+        }
+
+        expr
+    }
+}
@@ -0,0 +1,207 @@
+//! Rules for the set operations ([`Union`], [`Intersect`] and [`Except`]).
+//!
+//! Set operations match the columns of their inputs by position rather than by identifier, so
+//! predicates that are pushed into an input have to be renamed to the columns of that input first
+//! ([`filter_set_operation_pushdown`]). Nested unions are flattened into a single union
+//! ([`union_flattening`]), and intersections and differences without duplicates can be executed as
+//! semi and anti joins followed by an aggregation that removes the duplicates
+//! ([`set_operation_to_join`]), which lets them take part in the join rules.
+
+use super::transformation::filtered;
+use crate::cardinality::Estimator;
+use crate::catalog::ColumnId;
+use crate::expression::logical::{logical_properties, output_columns};
+use crate::expression::scalar::ScalarExpression;
+use crate::{
+    Aggregate, AggregatePhase, Except, Expression, Intersect, Join, JoinType, LogicalExpression,
+    Union,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Renames predicates over the columns `from` to the columns `to` at the same positions.
+fn renamed(
+    predicates: &[Arc<ScalarExpression>],
+    from: &[ColumnId],
+    to: &[ColumnId],
+) -> Vec<Arc<ScalarExpression>> {
+    let renames: HashMap<ColumnId, ColumnId> =
+        from.iter().copied().zip(to.iter().copied()).collect();
+    predicates
+        .iter()
+        .map(|predicate| predicate.rename_columns(&renames))
+        .collect()
+}
+
+/// A rule that merges the unions below a union into it.
+///
+/// `UnionAll(UnionAll(A, B), C)` is logically equivalent to `UnionAll(A, B, C)`. A union without
+/// duplicates removes the duplicates of the unions below it as well, so `Union(UnionAll(A, B), C)`
+/// is logically equivalent to `Union(A, B, C)`.
+pub fn union_flattening(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let Expression::Logical(LogicalExpression::Union(union)) = expr.as_ref() else {
+        return None;
+    };
+
+    let mut flattened = false;
+    let mut inputs = vec![];
+    for input in &union.inputs {
+        match input.as_ref() {
+            Expression::Logical(LogicalExpression::Union(below)) if below.all || !union.all => {
+                inputs.extend(below.inputs.iter().cloned());
+                flattened = true;
+            }
+            _ => inputs.push(input.clone()),
+        }
+    }
+
+    flattened.then(|| {
+        Arc::new(Expression::Logical(LogicalExpression::Union(Union {
+            inputs,
+            ..union.clone()
+        })))
+    })
+}
+
+/// A rule that pushes the predicates of a filter into every input of the set operation under it.
+///
+/// `Filter(p, Union(A, B))` is logically equivalent to `Union(Filter(p, A), Filter(p, B))`, with
+/// the columns of `p` renamed to the columns of each input. The same holds for intersections and
+/// differences: the rows of their right input can only match rows of the left input that satisfy
+/// `p` if they satisfy `p` as well.
+pub fn filter_set_operation_pushdown(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let Expression::Logical(LogicalExpression::Filter(filter)) = expr.as_ref() else {
+        return None;
+    };
+
+    let Expression::Logical(logical) = filter.children.as_ref() else {
+        return None;
+    };
+
+    // Pushes the predicates (over the columns of the left input) into both inputs.
+    let both = |left: &Arc<Expression>, right: &Arc<Expression>| {
+        let right_filters = renamed(
+            &filter.filters,
+            &output_columns(left),
+            &output_columns(right),
+        );
+        (
+            filtered(left, filter.filters.clone()),
+            filtered(right, right_filters),
+        )
+    };
+
+    let pushed = match logical {
+        LogicalExpression::Union(union) => {
+            let inputs = union
+                .inputs
+                .iter()
+                .map(|input| {
+                    let filters = renamed(&filter.filters, &union.columns, &output_columns(input));
+                    filtered(input, filters)
+                })
+                .collect();
+            LogicalExpression::Union(Union {
+                inputs,
+                ..union.clone()
+            })
+        }
+        LogicalExpression::Intersect(intersect) => {
+            let (left, right) = both(&intersect.left, &intersect.right);
+            LogicalExpression::Intersect(Intersect {
+                all: intersect.all,
+                left,
+                right,
+            })
+        }
+        LogicalExpression::Except(except) => {
+            let (left, right) = both(&except.left, &except.right);
+            LogicalExpression::Except(Except {
+                all: except.all,
+                left,
+                right,
+            })
+        }
+        _ => return None,
+    };
+
+    Some(Arc::new(Expression::Logical(pushed)))
+}
+
+/// A rule that turns an intersection or a difference without duplicates into a semi or an anti
+/// join, followed by an aggregation that removes the duplicates.
+///
+/// `Intersect(A, B)` is logically equivalent to `Aggregate(group by a, SemiJoin(a = b, A, B))`, and
+/// `Except(A, B)` to `Aggregate(group by a, AntiJoin(a = b, A, B))`, where `a` and `b` are the
+/// columns of `A` and `B`. Set operations consider two `NULL`s to be equal, so the columns that can
+/// be `NULL` on both sides are compared with `a = b OR (a IS NULL AND b IS NULL)`, which needs the
+/// catalog.
+///
+/// The conditions cannot tell the sides apart if they share columns (such as two scans of the same
+/// table that both use the columns of the table instead of their own, see
+/// [`Catalog::add_scan`](crate::catalog::Catalog::add_scan)), in which case the set operation is
+/// left alone.
+pub fn set_operation_to_join(
+    expr: &Arc<Expression>,
+    estimator: &Estimator,
+) -> Option<Arc<Expression>> {
+    let (join_type, left, right) = match expr.as_ref() {
+        Expression::Logical(LogicalExpression::Intersect(intersect)) if !intersect.all => {
+            (JoinType::LeftSemi, &intersect.left, &intersect.right)
+        }
+        Expression::Logical(LogicalExpression::Except(except)) if !except.all => {
+            (JoinType::LeftAnti, &except.left, &except.right)
+        }
+        _ => return None,
+    };
+
+    let left_columns = output_columns(left);
+    if output_columns(right)
+        .iter()
+        .any(|column| left_columns.contains(column))
+    {
+        return None;
+    }
+
+    let (left_properties, right_properties) = (
+        logical_properties(left, estimator),
+        logical_properties(right, estimator),
+    );
+    let conditions = left_properties
+        .schema
+        .iter()
+        .zip(&right_properties.schema)
+        .map(|(left, right)| {
+            let (left_column, right_column) = (
+                ScalarExpression::column(left.id),
+                ScalarExpression::column(right.id),
+            );
+            let equal = ScalarExpression::eq(left_column.clone(), right_column.clone());
+            if !left.nullable || !right.nullable {
+                return equal;
+            }
+
+            let both_null = Arc::new(ScalarExpression::And(vec![
+                Arc::new(ScalarExpression::IsNull(left_column)),
+                Arc::new(ScalarExpression::IsNull(right_column)),
+            ]));
+            Arc::new(ScalarExpression::Or(vec![equal, both_null]))
+        })
+        .collect();
+
+    let join = Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+        join_type,
+        conditions,
+        left: left.clone(),
+        right: right.clone(),
+    })));
+
+    Some(Arc::new(Expression::Logical(LogicalExpression::Aggregate(
+        Aggregate {
+            phase: AggregatePhase::Complete,
+            group_by: left_columns,
+            aggregates: vec![],
+            children: join,
+        },
+    ))))
+}
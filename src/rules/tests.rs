@@ -1,5 +1,8 @@
-use crate::rules::{transformation, StaticRule};
-use crate::{Join, LogicalExpression, Scan};
+use crate::catalog::ColumnId;
+use crate::expression::logical::output_columns;
+use crate::expression::scalar::ScalarExpression;
+use crate::rules::{pruning, transformation, StaticRule};
+use crate::{Filter, Join, JoinType, LogicalExpression, Project, Relation, Scan};
 
 use super::*;
 
@@ -7,18 +10,21 @@ use super::*;
 fn basic_transformation() {
     let table1: Arc<Expression> = Arc::new(Expression::Logical(LogicalExpression::Scan(Scan {
         table_id: 1,
-        filters: (),
+        columns: vec![],
+        filters: vec![],
     })));
 
     let table2 = Arc::new(Expression::Logical(LogicalExpression::Scan(Scan {
         table_id: 2,
-        filters: (),
+        columns: vec![],
+        filters: vec![],
     })));
 
     let join = Arc::new(Expression::Logical(LogicalExpression::Join(Join {
         left: table1,
         right: table2,
-        join_type: (),
+        join_type: JoinType::Inner,
+        conditions: vec![],
     })));
 
     // Have to use the `as StaticRule` to coerce correctly.
@@ -32,3 +38,1250 @@ fn basic_transformation() {
     println!("Commutativity Applied:\n{:?}\n", commute_join);
     println!("Back to Original:\n{:?}\n", revert);
 }
+
+fn scan<const N: usize>(table_id: usize, columns: [usize; N]) -> Arc<Expression> {
+    Arc::new(Expression::Logical(LogicalExpression::Scan(Scan {
+        table_id,
+        columns: columns.into_iter().map(ColumnId).collect(),
+        filters: vec![],
+    })))
+}
+
+fn filter(filters: Vec<Arc<ScalarExpression>>, children: Arc<Expression>) -> Arc<Expression> {
+    Arc::new(Expression::Logical(LogicalExpression::Filter(Filter {
+        filters,
+        children,
+    })))
+}
+
+fn join(
+    join_type: JoinType,
+    conditions: Vec<Arc<ScalarExpression>>,
+    left: Arc<Expression>,
+    right: Arc<Expression>,
+) -> Arc<Expression> {
+    Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+        join_type,
+        conditions,
+        left,
+        right,
+    })))
+}
+
+/// `column = value`
+fn equals(column: usize, value: i64) -> Arc<ScalarExpression> {
+    ScalarExpression::eq(
+        ScalarExpression::column(ColumnId(column)),
+        ScalarExpression::literal(value),
+    )
+}
+
+/// `left = right` for two columns.
+fn same(left: usize, right: usize) -> Arc<ScalarExpression> {
+    ScalarExpression::eq(
+        ScalarExpression::column(ColumnId(left)),
+        ScalarExpression::column(ColumnId(right)),
+    )
+}
+
+#[test]
+fn filters_merge_into_scans() {
+    let merged = transformation::filter_merge(&filter(
+        vec![equals(0, 1), equals(1, 2)],
+        filter(vec![equals(1, 2)], scan(1, [0, 1])),
+    ))
+    .expect("adjacent filters should merge");
+    let Expression::Logical(LogicalExpression::Filter(merged)) = merged.as_ref() else {
+        panic!("expected a filter, got {merged:?}");
+    };
+    assert_eq!(merged.filters, vec![equals(1, 2), equals(0, 1)]);
+
+    let scanned = transformation::filter_into_scan(&filter(vec![equals(0, 1)], scan(1, [0, 1])))
+        .expect("a filter over a scan should merge");
+    let Expression::Logical(LogicalExpression::Scan(scanned)) = scanned.as_ref() else {
+        panic!("expected a scan, got {scanned:?}");
+    };
+    assert_eq!(scanned.filters, vec![equals(0, 1)]);
+
+    assert!(transformation::filter_into_scan(&scan(1, [0, 1])).is_none());
+}
+
+#[test]
+fn filters_are_pushed_through_joins() {
+    let predicates = vec![equals(0, 1), equals(2, 2), same(1, 3)];
+
+    // Through an inner join, every predicate finds a place below the filter.
+    let pushed = transformation::filter_join_pushdown(&filter(
+        predicates.clone(),
+        join(JoinType::Inner, vec![], scan(1, [0, 1]), scan(2, [2, 3])),
+    ))
+    .expect("the filter should be pushed down");
+    let Expression::Logical(LogicalExpression::Join(pushed)) = pushed.as_ref() else {
+        panic!("expected a join, got {pushed:?}");
+    };
+    assert_eq!(pushed.conditions, vec![same(1, 3)]);
+    let filters = |expr: &Arc<Expression>| match expr.as_ref() {
+        Expression::Logical(LogicalExpression::Filter(filter)) => filter.filters.clone(),
+        _ => vec![],
+    };
+    assert_eq!(filters(&pushed.left), vec![equals(0, 1)]);
+    assert_eq!(filters(&pushed.right), vec![equals(2, 2)]);
+
+    // Through a left outer join, only predicates on the left side can be pushed.
+    let pushed = transformation::filter_join_pushdown(&filter(
+        predicates.clone(),
+        join(
+            JoinType::LeftOuter,
+            vec![],
+            scan(1, [0, 1]),
+            scan(2, [2, 3]),
+        ),
+    ))
+    .expect("the left predicate should be pushed down");
+    let Expression::Logical(LogicalExpression::Filter(rest)) = pushed.as_ref() else {
+        panic!("expected a filter, got {pushed:?}");
+    };
+    assert_eq!(rest.filters, vec![equals(2, 2), same(1, 3)]);
+    let Expression::Logical(LogicalExpression::Join(pushed)) = rest.children.as_ref() else {
+        panic!("expected a join, got {:?}", rest.children);
+    };
+    assert!(pushed.conditions.is_empty());
+    assert_eq!(filters(&pushed.left), vec![equals(0, 1)]);
+    assert!(filters(&pushed.right).is_empty());
+
+    // Nothing can be pushed through a full outer join.
+    assert!(transformation::filter_join_pushdown(&filter(
+        predicates,
+        join(
+            JoinType::FullOuter,
+            vec![],
+            scan(1, [0, 1]),
+            scan(2, [2, 3])
+        ),
+    ))
+    .is_none());
+}
+
+#[test]
+fn join_conditions_are_pushed_into_their_side() {
+    let conditions = vec![equals(0, 1), equals(2, 2), same(1, 3)];
+
+    // The left side of a left outer join is preserved, so only the right condition moves.
+    let pushed = transformation::join_condition_pushdown(&join(
+        JoinType::LeftOuter,
+        conditions.clone(),
+        scan(1, [0, 1]),
+        scan(2, [2, 3]),
+    ))
+    .expect("the right condition should be pushed down");
+    let Expression::Logical(LogicalExpression::Join(pushed)) = pushed.as_ref() else {
+        panic!("expected a join, got {pushed:?}");
+    };
+    assert_eq!(pushed.conditions, vec![equals(0, 1), same(1, 3)]);
+    assert!(matches!(
+        pushed.left.as_ref(),
+        Expression::Logical(LogicalExpression::Scan(_))
+    ));
+    assert!(matches!(
+        pushed.right.as_ref(),
+        Expression::Logical(LogicalExpression::Filter(filter)) if filter.filters == vec![equals(2, 2)]
+    ));
+
+    // Conditions between both sides stay where they are.
+    assert!(transformation::join_condition_pushdown(&join(
+        JoinType::Inner,
+        vec![same(1, 3)],
+        scan(1, [0, 1]),
+        scan(2, [2, 3]),
+    ))
+    .is_none());
+}
+
+fn project(columns: &[usize], children: Arc<Expression>) -> Arc<Expression> {
+    Arc::new(Expression::Logical(LogicalExpression::Project(Project {
+        columns: columns.iter().copied().map(ColumnId).collect(),
+        children,
+    })))
+}
+
+#[test]
+fn unused_columns_are_pruned() {
+    // `SELECT a0 FROM a JOIN (SELECT b2, b3 FROM b) ON a0 = b2 WHERE a1 = 5`, where `a` also has an
+    // unused column `a4`.
+    let a = Arc::new(Expression::Logical(LogicalExpression::Scan(Scan {
+        table_id: 1,
+        columns: vec![ColumnId(0), ColumnId(1), ColumnId(4)],
+        filters: vec![],
+    })));
+    let query = project(
+        &[0],
+        filter(
+            vec![equals(1, 5)],
+            join(
+                JoinType::Inner,
+                vec![same(0, 2)],
+                a,
+                project(&[2, 3], scan(2, [2, 3])),
+            ),
+        ),
+    );
+
+    let pruned = pruning::prune_columns(&query);
+    let expected = project(
+        &[0],
+        filter(
+            vec![equals(1, 5)],
+            join(
+                JoinType::Inner,
+                vec![same(0, 2)],
+                scan(1, [0, 1]),
+                Arc::new(Expression::Logical(LogicalExpression::Scan(Scan {
+                    table_id: 2,
+                    columns: vec![ColumnId(2)],
+                    filters: vec![],
+                }))),
+            ),
+        ),
+    );
+    assert_eq!(
+        pruned.structural_fingerprint(),
+        expected.structural_fingerprint(),
+        "expected {expected:?}, got {pruned:?}"
+    );
+
+    // A filter below a join keeps its columns to itself.
+    let query = join(
+        JoinType::Inner,
+        vec![same(0, 2)],
+        filter(vec![equals(1, 5)], scan(1, [0, 1])),
+        scan(2, [2, 3]),
+    );
+    let pruned = pruning::prune_columns(&project(&[0, 3], query));
+    let Expression::Logical(LogicalExpression::Project(top)) = pruned.as_ref() else {
+        panic!("expected a projection, got {pruned:?}");
+    };
+    let Expression::Logical(LogicalExpression::Join(join)) = top.children.as_ref() else {
+        panic!("expected a join, got {:?}", top.children);
+    };
+    assert_eq!(output_columns(&join.left), vec![ColumnId(0)]);
+    assert!(matches!(
+        join.left.as_ref(),
+        Expression::Logical(LogicalExpression::Project(_))
+    ));
+
+    // Projections that do not remove anything are dropped.
+    let pruned = pruning::prune_columns(&project(&[0, 1], scan(1, [0, 1])));
+    assert!(matches!(
+        pruned.as_ref(),
+        Expression::Logical(LogicalExpression::Scan(_))
+    ));
+}
+
+#[test]
+fn rewriter_reaches_a_fixpoint() {
+    use crate::rules::rewrite::Rewriter;
+
+    // `Project(0, 1, 2, 3, Project(0, 1, 2, 3, Filter(0 = 1, Filter(2 = 1 AND 0 = 2, A x B))))`
+    let plan = project(
+        &[0, 1, 2, 3],
+        project(
+            &[0, 1, 2, 3],
+            filter(
+                vec![equals(0, 1)],
+                filter(
+                    vec![equals(2, 1), same(0, 2)],
+                    join(JoinType::Inner, vec![], scan(0, [0, 1]), scan(1, [2, 3])),
+                ),
+            ),
+        ),
+    );
+
+    // The projections disappear, and the filters end up in the scans and the join.
+    let rewritten = Rewriter::default().rewrite(&plan);
+    let Expression::Logical(LogicalExpression::Join(top)) = rewritten.as_ref() else {
+        panic!("expected a join, got {rewritten:?}");
+    };
+    assert_eq!(top.conditions, vec![same(0, 2)]);
+
+    let filters = |side: &Arc<Expression>| match side.as_ref() {
+        Expression::Logical(LogicalExpression::Scan(scan)) => scan.filters.clone(),
+        _ => panic!("expected a scan, got {side:?}"),
+    };
+    assert_eq!(filters(&top.left), vec![equals(0, 1)]);
+    assert_eq!(filters(&top.right), vec![equals(2, 1)]);
+
+    // A custom rule set only applies its own rules.
+    let merge_only = Rewriter::from_static(&[transformation::filter_merge]);
+    let partial = merge_only.rewrite(&plan);
+    let Expression::Logical(LogicalExpression::Project(project)) = partial.as_ref() else {
+        panic!("the projections should stay");
+    };
+    let Expression::Logical(LogicalExpression::Project(project)) = project.children.as_ref() else {
+        panic!("the projections should stay");
+    };
+    let Expression::Logical(LogicalExpression::Filter(merged)) = project.children.as_ref() else {
+        panic!("the filters should be merged");
+    };
+    assert_eq!(merged.filters, vec![equals(2, 1), same(0, 2), equals(0, 1)]);
+    assert!(matches!(
+        merged.children.as_ref(),
+        Expression::Logical(LogicalExpression::Join(_))
+    ));
+
+    // Rule sets that never reach a fixpoint stop after the maximum number of rewrites.
+    let commute = Rewriter::from_static(&[transformation::join_commutativity]).with_max_rewrites(3);
+    let commuted = commute.rewrite(&join(
+        JoinType::Inner,
+        vec![],
+        scan(0, [0, 1]),
+        scan(1, [2, 3]),
+    ));
+    assert_eq!(
+        output_columns(&commuted),
+        vec![ColumnId(2), ColumnId(3), ColumnId(0), ColumnId(1)]
+    );
+}
+
+#[test]
+fn contradictions_empty_the_plan() {
+    use crate::expression::scalar::CompareOperator;
+    use crate::rules::rewrite::Rewriter;
+
+    let compare = |op, value: i64| {
+        ScalarExpression::compare(
+            op,
+            ScalarExpression::column(ColumnId(0)),
+            ScalarExpression::literal(value),
+        )
+    };
+
+    // `#0 > 5 AND 1 = 1` on the left side of a join with `#0 < 3 OR FALSE` on top.
+    let plan = filter(
+        vec![Arc::new(ScalarExpression::Or(vec![
+            compare(CompareOperator::Lt, 3),
+            ScalarExpression::literal(false),
+        ]))],
+        join(
+            JoinType::Inner,
+            vec![same(0, 2)],
+            filter(
+                vec![
+                    compare(CompareOperator::Gt, 5),
+                    ScalarExpression::eq(
+                        ScalarExpression::literal(1),
+                        ScalarExpression::literal(1),
+                    ),
+                ],
+                scan(0, [0, 1]),
+            ),
+            scan(1, [2, 3]),
+        ),
+    );
+
+    // The filters meet in the scan of the left side, which empties the join.
+    let rewritten = Rewriter::default().rewrite(&plan);
+    let Expression::Logical(LogicalExpression::Empty(empty)) = rewritten.as_ref() else {
+        panic!("expected an empty relation, got {rewritten:?}");
+    };
+    assert_eq!(empty.columns, output_columns(&plan));
+
+    // The always true predicate disappears on its own.
+    let simplified = transformation::predicate_simplification(&filter(
+        vec![equals(0, 1), ScalarExpression::literal(true)],
+        scan(0, [0, 1]),
+    ))
+    .expect("the filter should be simplified");
+    let Expression::Logical(LogicalExpression::Filter(simplified)) = simplified.as_ref() else {
+        panic!("expected a filter, got {simplified:?}");
+    };
+    assert_eq!(simplified.filters, vec![equals(0, 1)]);
+
+    // An outer join keeps the rows of its preserved side.
+    let outer = join(
+        JoinType::LeftOuter,
+        vec![],
+        scan(0, [0, 1]),
+        Arc::new(Expression::Logical(LogicalExpression::Empty(
+            crate::Empty {
+                columns: vec![ColumnId(2), ColumnId(3)],
+            },
+        ))),
+    );
+    assert!(transformation::empty_propagation(&outer).is_none());
+}
+
+#[test]
+fn predicates_are_inferred_from_equivalences() {
+    use crate::rules::rewrite::Rewriter;
+
+    // `Join(1 = 2, Join(0 = 1, A, B), C)` implies `0 = 2`, which connects `A` and `C`.
+    let chain = join(
+        JoinType::Inner,
+        vec![same(1, 2)],
+        join(
+            JoinType::Inner,
+            vec![same(0, 1)],
+            scan(0, [0, 4]),
+            scan(1, [1, 5]),
+        ),
+        scan(2, [2, 6]),
+    );
+    let inferred = transformation::predicate_inference(&chain).expect("0 = 2 should be inferred");
+    let Expression::Logical(LogicalExpression::Join(top)) = inferred.as_ref() else {
+        panic!("expected a join, got {inferred:?}");
+    };
+    assert_eq!(top.conditions, vec![same(1, 2), same(0, 2)]);
+    assert!(transformation::predicate_inference(&inferred).is_none());
+
+    // `0 = 7` on top of the chain reaches every scan.
+    let rewritten = Rewriter::default().rewrite(&filter(vec![equals(0, 7)], chain));
+    fn scan_filters(expr: &Arc<Expression>, filters: &mut Vec<Vec<Arc<ScalarExpression>>>) {
+        match expr.as_ref() {
+            Expression::Logical(LogicalExpression::Scan(scan)) => {
+                filters.push(scan.filters.clone())
+            }
+            _ => expr
+                .children()
+                .iter()
+                .for_each(|child| scan_filters(child, filters)),
+        }
+    }
+    let mut filters = vec![];
+    scan_filters(&rewritten, &mut filters);
+    assert_eq!(
+        filters,
+        vec![vec![equals(0, 7)], vec![equals(1, 7)], vec![equals(2, 7)]]
+    );
+}
+
+#[test]
+fn null_rejecting_filters_simplify_outer_joins() {
+    use crate::rules::rewrite::Rewriter;
+
+    let is_null = |column| {
+        Arc::new(ScalarExpression::IsNull(ScalarExpression::column(
+            ColumnId(column),
+        )))
+    };
+    let simplified = |filters, join_type| {
+        let plan = filter(
+            filters,
+            join(
+                join_type,
+                vec![same(0, 2)],
+                scan(0, [0, 1]),
+                scan(1, [2, 3]),
+            ),
+        );
+        transformation::outer_join_simplification(&plan).map(|simplified| {
+            let Expression::Logical(LogicalExpression::Filter(filter)) = simplified.as_ref() else {
+                panic!("expected a filter, got {simplified:?}");
+            };
+            let Expression::Logical(LogicalExpression::Join(join)) = filter.children.as_ref()
+            else {
+                panic!("expected a join, got {:?}", filter.children);
+            };
+            join.join_type
+        })
+    };
+
+    assert_eq!(
+        simplified(vec![equals(3, 5)], JoinType::LeftOuter),
+        Some(JoinType::Inner)
+    );
+    assert_eq!(
+        simplified(vec![equals(0, 5)], JoinType::RightOuter),
+        Some(JoinType::Inner)
+    );
+    assert_eq!(
+        simplified(vec![equals(0, 5)], JoinType::FullOuter),
+        Some(JoinType::LeftOuter)
+    );
+    assert_eq!(
+        simplified(vec![equals(0, 5), equals(3, 5)], JoinType::FullOuter),
+        Some(JoinType::Inner)
+    );
+
+    // Filters that keep the padded rows, or only reject rows of the preserved side.
+    assert_eq!(simplified(vec![is_null(3)], JoinType::LeftOuter), None);
+    assert_eq!(simplified(vec![equals(0, 5)], JoinType::LeftOuter), None);
+    assert_eq!(simplified(vec![equals(3, 5)], JoinType::Inner), None);
+
+    // Once the join is an inner join, the filter is pushed into both sides.
+    let plan = filter(
+        vec![equals(2, 5)],
+        join(
+            JoinType::LeftOuter,
+            vec![same(0, 2)],
+            scan(0, [0, 1]),
+            scan(1, [2, 3]),
+        ),
+    );
+    let rewritten = Rewriter::default().rewrite(&plan);
+    let Expression::Logical(LogicalExpression::Join(top)) = rewritten.as_ref() else {
+        panic!("expected a join, got {rewritten:?}");
+    };
+    assert_eq!(top.join_type, JoinType::Inner);
+    let Expression::Logical(LogicalExpression::Scan(left)) = top.left.as_ref() else {
+        panic!("expected a scan, got {:?}", top.left);
+    };
+    assert_eq!(left.filters, vec![equals(0, 5)]);
+}
+
+fn apply(
+    join_type: JoinType,
+    conditions: Vec<Arc<ScalarExpression>>,
+    left: Arc<Expression>,
+    right: Arc<Expression>,
+) -> Arc<Expression> {
+    Arc::new(Expression::Logical(LogicalExpression::Apply(
+        crate::Apply {
+            join_type,
+            conditions,
+            left,
+            right,
+        },
+    )))
+}
+
+/// An aggregate that computes a single function of a column into column `output`.
+fn aggregate(
+    group_by: &[usize],
+    function: crate::AggregateFunction,
+    argument: usize,
+    output: usize,
+    children: Arc<Expression>,
+) -> Arc<Expression> {
+    Arc::new(Expression::Logical(LogicalExpression::Aggregate(
+        crate::Aggregate {
+            phase: crate::AggregatePhase::Complete,
+            group_by: group_by.iter().copied().map(ColumnId).collect(),
+            aggregates: vec![crate::AggregateCall {
+                function,
+                argument: Some(ColumnId(argument)),
+                output: ColumnId(output),
+            }],
+            children,
+        },
+    )))
+}
+
+#[test]
+fn correlated_subqueries_are_unnested() {
+    use crate::rules::rewrite::Rewriter;
+    use crate::AggregateFunction;
+
+    let rewriter = Rewriter::default();
+    let assert_rewrites_to = |plan: Arc<Expression>, expected: Arc<Expression>| {
+        let rewritten = rewriter.rewrite(&plan);
+        assert_eq!(
+            rewritten.structural_fingerprint(),
+            expected.structural_fingerprint(),
+            "expected {expected:?}, got {rewritten:?}"
+        );
+    };
+    let a = || scan(1, [0, 1]);
+    let b = || scan(2, [2, 3]);
+
+    // `SELECT * FROM a WHERE EXISTS (SELECT * FROM b WHERE b2 = a0)`
+    assert_rewrites_to(
+        apply(
+            JoinType::LeftSemi,
+            vec![],
+            a(),
+            filter(vec![same(2, 0)], b()),
+        ),
+        join(JoinType::LeftSemi, vec![same(2, 0)], a(), b()),
+    );
+
+    // `SELECT * FROM a WHERE NOT EXISTS (SELECT b2 FROM b WHERE b2 = a0 AND b3 = 1)`
+    assert_rewrites_to(
+        apply(
+            JoinType::LeftAnti,
+            vec![],
+            a(),
+            project(&[2], filter(vec![same(2, 0), equals(3, 1)], b())),
+        ),
+        join(
+            JoinType::LeftAnti,
+            vec![same(2, 0)],
+            a(),
+            Arc::new(Expression::Logical(LogicalExpression::Scan(Scan {
+                table_id: 2,
+                columns: vec![ColumnId(2), ColumnId(3)],
+                filters: vec![equals(3, 1)],
+            }))),
+        ),
+    );
+
+    // `SELECT a0, a1, (SELECT MAX(b3) FROM b WHERE b2 = a0) FROM a`, where the maximum is column 4.
+    let scalar = |function| {
+        apply(
+            JoinType::LeftOuter,
+            vec![],
+            a(),
+            aggregate(&[], function, 3, 4, filter(vec![same(2, 0)], b())),
+        )
+    };
+    assert_rewrites_to(
+        scalar(AggregateFunction::Max),
+        project(
+            &[0, 1, 4],
+            join(
+                JoinType::LeftOuter,
+                vec![same(2, 0)],
+                a(),
+                aggregate(&[2], AggregateFunction::Max, 3, 4, b()),
+            ),
+        ),
+    );
+
+    // `COUNT` is `0` rather than `NULL` for the rows of `a` without a match, which an outer join
+    // cannot produce.
+    let counted = rewriter.rewrite(&scalar(AggregateFunction::Count));
+    assert!(matches!(
+        counted.as_ref(),
+        Expression::Logical(LogicalExpression::Apply(_))
+    ));
+}
+
+#[test]
+fn aggregates_are_split_pushed_down_and_removed() {
+    use crate::cardinality::Estimator;
+    use crate::catalog::{Catalog, DataType};
+    use crate::{AggregateFunction, AggregatePhase};
+
+    let assert_same = |actual: Option<Arc<Expression>>, expected: Arc<Expression>| {
+        let actual = actual.expect("the rule should match");
+        assert_eq!(
+            actual.structural_fingerprint(),
+            expected.structural_fingerprint(),
+            "expected {expected:?}, got {actual:?}"
+        );
+    };
+    let phased = |phase, expr: Arc<Expression>| {
+        let Expression::Logical(LogicalExpression::Aggregate(aggregate)) = expr.as_ref() else {
+            unreachable!();
+        };
+        Arc::new(Expression::Logical(LogicalExpression::Aggregate(
+            crate::Aggregate {
+                phase,
+                ..aggregate.clone()
+            },
+        )))
+    };
+    let a = || scan(1, [0, 1]);
+    let b = || scan(2, [2, 3]);
+
+    // `SELECT b2, MAX(b3) FROM b GROUP BY b2`, where the maximum is column 4.
+    let max = aggregate(&[2], AggregateFunction::Max, 3, 4, b());
+    assert_same(
+        transformation::aggregate_split(&max),
+        phased(
+            AggregatePhase::Final,
+            aggregate(
+                &[2],
+                AggregateFunction::Max,
+                4,
+                4,
+                phased(AggregatePhase::Partial, max.clone()),
+            ),
+        ),
+    );
+
+    // `SELECT a0, COUNT(b3) FROM a JOIN b ON a0 = b2 GROUP BY a0` counts the rows of `b` per `b2`
+    // before the join, and adds up the counts after it.
+    let count = |group_by: &[usize]| {
+        aggregate(
+            group_by,
+            AggregateFunction::Count,
+            3,
+            4,
+            join(JoinType::Inner, vec![same(0, 2)], a(), b()),
+        )
+    };
+    assert_same(
+        transformation::eager_aggregation(&count(&[0])),
+        phased(
+            AggregatePhase::Final,
+            aggregate(
+                &[0],
+                AggregateFunction::Sum,
+                4,
+                4,
+                join(
+                    JoinType::Inner,
+                    vec![same(0, 2)],
+                    a(),
+                    phased(
+                        AggregatePhase::Partial,
+                        aggregate(&[2], AggregateFunction::Count, 3, 4, b()),
+                    ),
+                ),
+            ),
+        ),
+    );
+
+    // The count of an empty join is `0`, but the sum of no counts is `NULL`.
+    assert!(transformation::eager_aggregation(&count(&[])).is_none());
+    // `SELECT a0, SUM(b3) FROM a, b GROUP BY a0` would aggregate all of `b` into a single row, even
+    // if `b` is empty.
+    let cross_sum = aggregate(
+        &[0],
+        AggregateFunction::Sum,
+        3,
+        4,
+        join(JoinType::Inner, vec![], a(), b()),
+    );
+    assert!(transformation::eager_aggregation(&cross_sum).is_none());
+    // Neither phase of a split aggregate is split again.
+    let split = transformation::aggregate_split(&max).unwrap();
+    assert!(transformation::aggregate_split(&split).is_none());
+    assert!(transformation::aggregate_split(&split.children()[0]).is_none());
+
+    // `SELECT DISTINCT t0 FROM t` is `SELECT t0 FROM t` if `t0` is unique.
+    let mut catalog = Catalog::new();
+    let table = catalog.add_table(
+        "t",
+        &[
+            ("t0", DataType::Int64, false),
+            ("t1", DataType::Int64, false),
+            ("t2", DataType::Int64, true),
+        ],
+        100,
+    );
+    catalog.add_unique_key(table, vec![ColumnId(0)]);
+    catalog.add_unique_key(table, vec![ColumnId(2)]);
+    let estimator = Estimator::new(Arc::new(catalog));
+    let distinct = |column: usize| {
+        Arc::new(Expression::Logical(LogicalExpression::Aggregate(
+            crate::Aggregate {
+                phase: AggregatePhase::Complete,
+                group_by: vec![ColumnId(column)],
+                aggregates: vec![],
+                children: scan(table, [0, 1, 2]),
+            },
+        )))
+    };
+    assert_same(
+        transformation::aggregate_removal(&distinct(0), &estimator),
+        project(&[0], scan(table, [0, 1, 2])),
+    );
+    assert!(transformation::aggregate_removal(&distinct(1), &estimator).is_none());
+    // All of the `NULL`s of the unique `t2` end up in a single group.
+    assert!(transformation::aggregate_removal(&distinct(2), &estimator).is_none());
+}
+
+#[test]
+fn joins_are_eliminated_with_keys() {
+    use crate::cardinality::Estimator;
+    use crate::catalog::{Catalog, DataType};
+    use crate::rules::rewrite::Rewriter;
+
+    // `facts(f0, f1, f2)` refers to `dimension(d3)` with `f1` and to `other(o4, o5)` with the
+    // nullable `f2`.
+    let mut catalog = Catalog::new();
+    let facts = catalog.add_table(
+        "facts",
+        &[
+            ("f0", DataType::Int64, false),
+            ("f1", DataType::Int64, false),
+            ("f2", DataType::Int64, true),
+        ],
+        1000,
+    );
+    let dimension = catalog.add_table("dimension", &[("d3", DataType::Int64, false)], 10);
+    let other = catalog.add_table(
+        "other",
+        &[
+            ("o4", DataType::Int64, false),
+            ("o5", DataType::Utf8, false),
+        ],
+        10,
+    );
+    catalog.add_unique_key(dimension, vec![ColumnId(3)]);
+    catalog.add_unique_key(other, vec![ColumnId(4)]);
+    catalog.add_foreign_key(facts, vec![ColumnId(1)], dimension, vec![ColumnId(3)]);
+    catalog.add_foreign_key(facts, vec![ColumnId(2)], other, vec![ColumnId(4)]);
+    let estimator = Estimator::new(Arc::new(catalog));
+
+    let table = |table_id, columns: &[usize], filters| {
+        Arc::new(Expression::Logical(LogicalExpression::Scan(Scan {
+            table_id,
+            columns: columns.iter().copied().map(ColumnId).collect(),
+            filters,
+        })))
+    };
+    let facts = || table(facts, &[0, 1, 2], vec![]);
+    let dimension = || table(dimension, &[3], vec![]);
+    let other = || table(other, &[4, 5], vec![]);
+    let eliminate = |plan: Arc<Expression>| transformation::join_elimination(&plan, &estimator);
+    let assert_eliminated = |plan: Arc<Expression>, expected: Arc<Expression>| {
+        let eliminated = eliminate(plan).expect("the join should be eliminated");
+        assert_eq!(
+            eliminated.structural_fingerprint(),
+            expected.structural_fingerprint(),
+            "expected {expected:?}, got {eliminated:?}"
+        );
+    };
+
+    // `SELECT f0 FROM facts JOIN dimension ON f1 = d3`
+    assert_eliminated(
+        project(
+            &[0],
+            join(JoinType::Inner, vec![same(1, 3)], facts(), dimension()),
+        ),
+        project(&[0], facts()),
+    );
+
+    // The rows of `facts` without an `other` are still removed.
+    assert_eliminated(
+        project(
+            &[0],
+            join(JoinType::Inner, vec![same(4, 2)], other(), facts()),
+        ),
+        project(
+            &[0],
+            filter(
+                vec![Arc::new(ScalarExpression::IsNotNull(
+                    ScalarExpression::column(ColumnId(2)),
+                ))],
+                facts(),
+            ),
+        ),
+    );
+
+    // A left outer join on a unique key, whose columns are not used.
+    assert_eliminated(
+        project(
+            &[0],
+            join(JoinType::LeftOuter, vec![same(0, 4)], facts(), other()),
+        ),
+        project(&[0], facts()),
+    );
+
+    // Joins whose columns are used, that filter the referenced table, that are not on a key, or
+    // that have other conditions stay.
+    for plan in [
+        project(
+            &[0, 3],
+            join(JoinType::Inner, vec![same(1, 3)], facts(), dimension()),
+        ),
+        project(
+            &[0],
+            join(
+                JoinType::Inner,
+                vec![same(1, 3)],
+                facts(),
+                table(1, &[3], vec![equals(3, 1)]),
+            ),
+        ),
+        project(
+            &[0],
+            join(JoinType::LeftOuter, vec![same(0, 5)], facts(), other()),
+        ),
+        project(
+            &[0],
+            join(
+                JoinType::Inner,
+                vec![same(1, 3), same(0, 3)],
+                facts(),
+                dimension(),
+            ),
+        ),
+    ] {
+        assert!(eliminate(plan.clone()).is_none(), "{plan:?}");
+    }
+
+    // Unused joins are removed from the whole join tree, even below joins that are used.
+    let view = project(
+        &[0, 5],
+        join(
+            JoinType::Inner,
+            vec![same(2, 4)],
+            join(JoinType::Inner, vec![same(1, 3)], facts(), dimension()),
+            other(),
+        ),
+    );
+    let rewritten = Rewriter::heuristic(&estimator).rewrite(&view);
+    assert_eq!(
+        rewritten.structural_fingerprint(),
+        project(
+            &[0, 5],
+            join(JoinType::Inner, vec![same(2, 4)], facts(), other())
+        )
+        .structural_fingerprint(),
+        "got {rewritten:?}"
+    );
+}
+
+fn limit(limit: usize, offset: usize, children: Arc<Expression>) -> Arc<Expression> {
+    Arc::new(Expression::Logical(LogicalExpression::Limit(
+        crate::Limit {
+            limit,
+            offset,
+            children,
+        },
+    )))
+}
+
+#[test]
+fn limits_are_pushed_down() {
+    use crate::rules::rewrite::Rewriter;
+
+    let rewriter = Rewriter::default();
+    let assert_rewritten = |plan: Arc<Expression>, expected: Arc<Expression>| {
+        let rewritten = rewriter.rewrite(&plan);
+        assert_eq!(
+            rewritten.structural_fingerprint(),
+            expected.structural_fingerprint(),
+            "expected {expected:?}, got {rewritten:?}"
+        );
+    };
+
+    // Through a projection, and into the preserved side of an outer join (but not the other).
+    assert_rewritten(
+        limit(
+            10,
+            0,
+            project(
+                &[0, 2],
+                join(
+                    JoinType::LeftOuter,
+                    vec![same(1, 3)],
+                    scan(1, [0, 1]),
+                    scan(2, [2, 3]),
+                ),
+            ),
+        ),
+        project(
+            &[0, 2],
+            limit(
+                10,
+                0,
+                join(
+                    JoinType::LeftOuter,
+                    vec![same(1, 3)],
+                    limit(10, 0, scan(1, [0, 1])),
+                    scan(2, [2, 3]),
+                ),
+            ),
+        ),
+    );
+
+    // The inputs of an inner join may not have a match, so they are not limited.
+    let inner = limit(
+        10,
+        0,
+        join(
+            JoinType::Inner,
+            vec![same(1, 3)],
+            scan(1, [0, 1]),
+            scan(2, [2, 3]),
+        ),
+    );
+    assert_rewritten(inner.clone(), inner);
+
+    // Into every input of a union, including the skipped rows, unless an input is already limited
+    // to fewer rows.
+    let union = |inputs| {
+        Arc::new(Expression::Logical(LogicalExpression::Union(
+            crate::Union {
+                all: true,
+                columns: vec![ColumnId(4), ColumnId(5)],
+                inputs,
+            },
+        )))
+    };
+    assert_rewritten(
+        limit(
+            5,
+            2,
+            union(vec![scan(1, [0, 1]), limit(3, 0, scan(2, [2, 3]))]),
+        ),
+        limit(
+            5,
+            2,
+            union(vec![
+                limit(7, 0, scan(1, [0, 1])),
+                limit(3, 0, scan(2, [2, 3])),
+            ]),
+        ),
+    );
+
+    // Adjacent limits merge, and a limit of zero rows is empty.
+    assert_rewritten(
+        limit(5, 2, limit(4, 1, scan(1, [0, 1]))),
+        limit(2, 3, scan(1, [0, 1])),
+    );
+    assert!(matches!(
+        rewriter.rewrite(&limit(0, 0, scan(1, [0, 1]))).as_ref(),
+        Expression::Logical(LogicalExpression::Empty(_))
+    ));
+}
+
+fn union(all: bool, inputs: Vec<Arc<Expression>>) -> Arc<Expression> {
+    Arc::new(Expression::Logical(LogicalExpression::Union(
+        crate::Union {
+            all,
+            columns: vec![ColumnId(6), ColumnId(7)],
+            inputs,
+        },
+    )))
+}
+
+fn intersect(all: bool, left: Arc<Expression>, right: Arc<Expression>) -> Arc<Expression> {
+    Arc::new(Expression::Logical(LogicalExpression::Intersect(
+        crate::Intersect { all, left, right },
+    )))
+}
+
+#[test]
+fn set_operations_are_flattened_and_filtered() {
+    use crate::rules::set_operations;
+
+    let assert_same = |actual: Arc<Expression>, expected: Arc<Expression>| {
+        assert_eq!(
+            actual.structural_fingerprint(),
+            expected.structural_fingerprint(),
+            "expected {expected:?}, got {actual:?}"
+        );
+    };
+
+    // A union without duplicates absorbs any union, but a `UNION ALL` only absorbs `UNION ALL`s.
+    let nested = |all| union(all, vec![scan(1, [0, 1]), scan(2, [2, 3])]);
+    assert_same(
+        set_operations::union_flattening(&union(true, vec![nested(true), scan(3, [4, 5])]))
+            .expect("nested unions should be flattened"),
+        union(
+            true,
+            vec![scan(1, [0, 1]), scan(2, [2, 3]), scan(3, [4, 5])],
+        ),
+    );
+    assert_same(
+        set_operations::union_flattening(&union(false, vec![nested(true), scan(3, [4, 5])]))
+            .expect("nested unions should be flattened"),
+        union(
+            false,
+            vec![scan(1, [0, 1]), scan(2, [2, 3]), scan(3, [4, 5])],
+        ),
+    );
+    assert!(
+        set_operations::union_flattening(&union(true, vec![nested(false), scan(3, [4, 5])]))
+            .is_none()
+    );
+
+    // Predicates over the output of a union are renamed to the columns of every input.
+    assert_same(
+        set_operations::filter_set_operation_pushdown(&filter(
+            vec![equals(7, 1)],
+            union(true, vec![scan(1, [0, 1]), scan(2, [2, 3])]),
+        ))
+        .expect("the filter should be pushed into the union"),
+        union(
+            true,
+            vec![
+                filter(vec![equals(1, 1)], scan(1, [0, 1])),
+                filter(vec![equals(3, 1)], scan(2, [2, 3])),
+            ],
+        ),
+    );
+
+    // The output columns of an intersection are the columns of its left input.
+    assert_same(
+        set_operations::filter_set_operation_pushdown(&filter(
+            vec![same(0, 1)],
+            intersect(false, scan(1, [0, 1]), scan(2, [2, 3])),
+        ))
+        .expect("the filter should be pushed into the intersection"),
+        intersect(
+            false,
+            filter(vec![same(0, 1)], scan(1, [0, 1])),
+            filter(vec![same(2, 3)], scan(2, [2, 3])),
+        ),
+    );
+}
+
+#[test]
+fn intersections_become_semi_joins() {
+    use crate::cardinality::Estimator;
+    use crate::catalog::{Catalog, DataType};
+    use crate::expression::scalar::CompareOperator;
+    use crate::rules::set_operations;
+
+    let mut catalog = Catalog::new();
+    for table in ["t0", "t1"] {
+        catalog.add_table(
+            table,
+            &[
+                ("id", DataType::Int64, false),
+                ("name", DataType::Utf8, true),
+            ],
+            100,
+        );
+    }
+    let [other_id, _] = catalog.add_scan(0)[..] else {
+        panic!("t0 has two columns");
+    };
+    let estimator = Estimator::new(Arc::new(catalog));
+
+    // `SELECT id, name FROM t0 INTERSECT SELECT id, name FROM t1`, where the `NULL` names of both
+    // sides are equal.
+    let joined = set_operations::set_operation_to_join(
+        &intersect(false, scan(0, [0, 1]), scan(1, [2, 3])),
+        &estimator,
+    )
+    .expect("the intersection should become a join");
+    let both_null = Arc::new(ScalarExpression::And(vec![
+        Arc::new(ScalarExpression::IsNull(ScalarExpression::column(
+            ColumnId(1),
+        ))),
+        Arc::new(ScalarExpression::IsNull(ScalarExpression::column(
+            ColumnId(3),
+        ))),
+    ]));
+    let expected = Arc::new(Expression::Logical(LogicalExpression::Aggregate(
+        crate::Aggregate {
+            phase: crate::AggregatePhase::Complete,
+            group_by: vec![ColumnId(0), ColumnId(1)],
+            aggregates: vec![],
+            children: join(
+                JoinType::LeftSemi,
+                vec![
+                    same(0, 2),
+                    Arc::new(ScalarExpression::Or(vec![same(1, 3), both_null])),
+                ],
+                scan(0, [0, 1]),
+                scan(1, [2, 3]),
+            ),
+        },
+    )));
+    assert_eq!(
+        joined.structural_fingerprint(),
+        expected.structural_fingerprint(),
+        "got {joined:?}"
+    );
+
+    // `INTERSECT ALL` keeps duplicates, which a semi join cannot count.
+    assert!(set_operations::set_operation_to_join(
+        &intersect(true, scan(0, [0, 1]), scan(1, [2, 3])),
+        &estimator,
+    )
+    .is_none());
+
+    // `(SELECT id FROM t0 WHERE id > 5) INTERSECT (SELECT id FROM t0 WHERE id < 3)` would join on
+    // `id = id`, which holds for every row of the left side.
+    let compare = |column, op, value: i64| {
+        ScalarExpression::compare(
+            op,
+            ScalarExpression::column(column),
+            ScalarExpression::literal(value),
+        )
+    };
+    let greater = filter(
+        vec![compare(ColumnId(0), CompareOperator::Gt, 5)],
+        scan(0, [0]),
+    );
+    let less = |column: ColumnId| {
+        filter(
+            vec![compare(column, CompareOperator::Lt, 3)],
+            scan(0, [column.0]),
+        )
+    };
+    assert!(set_operations::set_operation_to_join(
+        &intersect(false, greater.clone(), less(ColumnId(0))),
+        &estimator
+    )
+    .is_none());
+
+    // A second scan of `t0` with its own columns can be told apart from the first one.
+    let joined = set_operations::set_operation_to_join(
+        &intersect(false, greater.clone(), less(other_id)),
+        &estimator,
+    )
+    .expect("the intersection of two scans of the same table should become a join");
+    let expected = Arc::new(Expression::Logical(LogicalExpression::Aggregate(
+        crate::Aggregate {
+            phase: crate::AggregatePhase::Complete,
+            group_by: vec![ColumnId(0)],
+            aggregates: vec![],
+            children: join(
+                JoinType::LeftSemi,
+                vec![same(0, other_id.0)],
+                greater,
+                less(other_id),
+            ),
+        },
+    )));
+    assert_eq!(
+        joined.structural_fingerprint(),
+        expected.structural_fingerprint(),
+        "got {joined:?}"
+    );
+}
+
+#[test]
+fn join_reordering_keeps_correlated_conditions() {
+    fn conditions(expr: &Arc<Expression>) -> Vec<Arc<ScalarExpression>> {
+        let mut all = match expr.as_ref() {
+            Expression::Logical(LogicalExpression::Join(join)) => join.conditions.clone(),
+            _ => vec![],
+        };
+        for child in expr.children() {
+            all.extend(conditions(&child));
+        }
+        all
+    }
+
+    // `c.id = outer.x`, where `outer.x` (column 9) is an outer reference of a correlated subquery.
+    let correlated = same(5, 9);
+    let inner = |left, right, conditions| join(JoinType::Inner, conditions, left, right);
+    let (a, b, c, d) = (
+        scan(1, [0, 1]),
+        scan(2, [2, 3]),
+        scan(3, [4, 5]),
+        scan(4, [6, 7]),
+    );
+
+    for (rule, plan) in [
+        (
+            transformation::join_left_associativity as StaticRule,
+            inner(
+                a.clone(),
+                inner(b.clone(), c.clone(), vec![same(2, 4), correlated.clone()]),
+                vec![same(0, 2)],
+            ),
+        ),
+        (
+            transformation::join_exchange,
+            inner(
+                inner(a.clone(), b.clone(), vec![same(0, 2)]),
+                inner(c.clone(), d.clone(), vec![same(4, 6), correlated.clone()]),
+                vec![same(0, 4)],
+            ),
+        ),
+        (
+            transformation::join_left_exchange,
+            inner(
+                inner(a.clone(), b.clone(), vec![same(0, 2)]),
+                c.clone(),
+                vec![same(0, 4), correlated.clone()],
+            ),
+        ),
+        (
+            transformation::join_right_exchange,
+            inner(
+                a.clone(),
+                inner(b.clone(), c.clone(), vec![same(2, 4), correlated.clone()]),
+                vec![same(0, 4)],
+            ),
+        ),
+    ] {
+        let rewritten = rule(&plan).expect("the join rule should match");
+        let mut expected = conditions(&plan);
+        let mut actual = conditions(&rewritten);
+        expected.sort_by_key(|condition| format!("{condition:?}"));
+        actual.sort_by_key(|condition| format!("{condition:?}"));
+        assert_eq!(actual, expected, "{rewritten:?}");
+    }
+}
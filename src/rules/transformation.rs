@@ -1,15 +1,100 @@
-use super::StaticRule;
-use crate::{Expression, Join, LogicalExpression};
+use super::set_operations::set_operation_to_join;
+use super::{CatalogRule, StaticRule};
+use crate::cardinality::Estimator;
+use crate::catalog::ColumnId;
+use crate::expression::logical::{
+    equivalences, logical_properties, output_columns, AggregateCall, AggregateFunction,
+    AggregatePhase,
+};
+use crate::expression::scalar::ScalarExpression;
+use crate::expression::simplify::{rejects_nulls, simplify_conjunction};
+use crate::properties::equivalence::infer_predicates;
+use crate::{
+    Aggregate, Empty, Expression, Filter, Join, JoinType, LogicalExpression, Project, Relation,
+    Scan,
+};
+use std::collections::BTreeSet;
 use std::sync::Arc;
 
 /// Static transformation rules transforming logical expressions into equivalent but different
 /// logical expressions.
 ///
+/// The join rules form the RS-B1 rule set of Pellenkoft et al., "The Complexity of
+/// Transformation-Based Join Enumeration", which generates every bushy join tree (including the
+/// ones with cross products) exactly once when combined with [`STATIC_TRANSFORMATION_GUIDANCE`].
+///
 /// TODO:
 /// We may want to represent this differently to keep track of promise values.
 /// Should this allow easy reordering of the rules?
-static STATIC_TRANSFORMATION_RULES: [StaticRule; 2] =
-    [join_commutativity, join_right_associativity];
+pub static STATIC_TRANSFORMATION_RULES: [StaticRule; 11] = [
+    filter_merge,
+    filter_into_scan,
+    filter_join_pushdown,
+    join_condition_pushdown,
+    join_commutativity,
+    join_right_associativity,
+    join_left_associativity,
+    join_exchange,
+    outer_join_simplification,
+    aggregate_split,
+    eager_aggregation,
+];
+
+/// Transformation rules that need the catalog. In guidance and in the rules disabled for a search,
+/// they are numbered after the [`STATIC_TRANSFORMATION_RULES`].
+pub static CATALOG_TRANSFORMATION_RULES: [CatalogRule; 2] =
+    [aggregate_removal, set_operation_to_join];
+
+/// Returns the number of transformation rules, including the rules that need the catalog.
+pub fn transformation_rule_count() -> usize {
+    STATIC_TRANSFORMATION_RULES.len() + CATALOG_TRANSFORMATION_RULES.len()
+}
+
+const COMMUTATIVITY: usize = 4;
+const RIGHT_ASSOCIATIVITY: usize = 5;
+const LEFT_ASSOCIATIVITY: usize = 6;
+const EXCHANGE: usize = 7;
+
+/// The rules (by their index in [`STATIC_TRANSFORMATION_RULES`]) that reorder joins.
+pub static JOIN_REORDERING_RULES: [usize; 4] = [
+    COMMUTATIVITY,
+    RIGHT_ASSOCIATIVITY,
+    LEFT_ASSOCIATIVITY,
+    EXCHANGE,
+];
+
+/// For every rule in [`STATIC_TRANSFORMATION_RULES`], the rules (by their index) that are disabled
+/// on the expressions that the rule produces.
+///
+/// Without this, the join rules would keep generating trees that were already generated by a
+/// different sequence of rules: commuting a join twice, for example, gives back the original join.
+pub static STATIC_TRANSFORMATION_GUIDANCE: [&[usize]; 11] = [
+    &[],
+    &[],
+    &[],
+    &[],
+    &[
+        COMMUTATIVITY,
+        RIGHT_ASSOCIATIVITY,
+        LEFT_ASSOCIATIVITY,
+        EXCHANGE,
+    ],
+    &[RIGHT_ASSOCIATIVITY, LEFT_ASSOCIATIVITY, EXCHANGE],
+    &[RIGHT_ASSOCIATIVITY, LEFT_ASSOCIATIVITY, EXCHANGE],
+    &[
+        COMMUTATIVITY,
+        RIGHT_ASSOCIATIVITY,
+        LEFT_ASSOCIATIVITY,
+        EXCHANGE,
+    ],
+    &[],
+    &[],
+    &[],
+];
+
+/// For every rule in [`CATALOG_TRANSFORMATION_RULES`], the rules that are disabled on the
+/// expressions that the rule produces.
+pub static CATALOG_TRANSFORMATION_GUIDANCE: [&[usize]; 2] = [&[], &[]];
 
 /// A rule that defines join commutativity.
 ///
@@ -22,7 +107,8 @@ pub fn join_commutativity(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
     let new_join = Join {
         left: join.right.clone(),
         right: join.left.clone(),
-        join_type: (),
+        join_type: join.join_type.commute()?,
+        conditions: join.conditions.clone(),
     };
 
     Some(Arc::new(Expression::Logical(LogicalExpression::Join(
@@ -33,6 +119,8 @@ pub fn join_commutativity(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
 /// A rule that defines join right associativity.
 ///
 /// `Join(Join(A, B), C)` is logically equivalent to `Join(A, Join(B, C))`.
+///
+/// This only holds for inner joins.
 pub fn join_right_associativity(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
     let Expression::Logical(LogicalExpression::Join(top_join)) = expr.as_ref() else {
         return None;
@@ -42,19 +130,944 @@ pub fn join_right_associativity(expr: &Arc<Expression>) -> Option<Arc<Expression
         return None;
     };
 
+    if top_join.join_type != JoinType::Inner || left_join.join_type != JoinType::Inner {
+        return None;
+    }
+
+    // The conditions that only reference `B` and `C` move down into the new join.
+    let mut right_columns: BTreeSet<ColumnId> =
+        output_columns(&left_join.right).into_iter().collect();
+    right_columns.extend(output_columns(&top_join.right));
+    let (right_conditions, top_conditions) = split_predicates(
+        left_join.conditions.iter().chain(&top_join.conditions),
+        &right_columns,
+    );
+
     let new_right_join = Join {
         left: left_join.right.clone(),
         right: top_join.right.clone(),
-        join_type: (),
+        join_type: JoinType::Inner,
+        conditions: right_conditions,
     };
 
     let new_top_join = Join {
         left: left_join.left.clone(),
         right: Arc::new(Expression::Logical(LogicalExpression::Join(new_right_join))),
-        join_type: (),
+        join_type: JoinType::Inner,
+        conditions: top_conditions,
     };
 
     Some(Arc::new(Expression::Logical(LogicalExpression::Join(
         new_top_join,
     ))))
 }
+
+/// A rule that defines join left associativity.
+///
+/// `Join(A, Join(B, C))` is logically equivalent to `Join(Join(A, B), C)`.
+///
+/// This only holds for inner joins.
+pub fn join_left_associativity(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let top_join = inner_join_of(expr)?;
+    let right_join = inner_join_of(&top_join.right)?;
+
+    let mut predicates = conditions_of(&[top_join, right_join]);
+    let left = inner_join(&top_join.left, &right_join.left, &mut predicates);
+    Some(top_inner_join(&left, &right_join.right, predicates))
+}
+
+/// A rule that exchanges the inputs of two joins below a join.
+///
+/// `Join(Join(A, B), Join(C, D))` is logically equivalent to `Join(Join(A, C), Join(B, D))`.
+///
+/// This only holds for inner joins.
+pub fn join_exchange(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let top_join = inner_join_of(expr)?;
+    let left_join = inner_join_of(&top_join.left)?;
+    let right_join = inner_join_of(&top_join.right)?;
+
+    let mut predicates = conditions_of(&[top_join, left_join, right_join]);
+    let left = inner_join(&left_join.left, &right_join.left, &mut predicates);
+    let right = inner_join(&left_join.right, &right_join.right, &mut predicates);
+    Some(top_inner_join(&left, &right, predicates))
+}
+
+/// A rule that exchanges the right input of a join with the right input of the join to its left.
+///
+/// `Join(Join(A, B), C)` is logically equivalent to `Join(Join(A, C), B)`.
+///
+/// This only holds for inner joins. It is not part of [`STATIC_TRANSFORMATION_RULES`], since the
+/// other join rules already generate the same trees.
+pub fn join_left_exchange(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let top_join = inner_join_of(expr)?;
+    let left_join = inner_join_of(&top_join.left)?;
+
+    let mut predicates = conditions_of(&[top_join, left_join]);
+    let left = inner_join(&left_join.left, &top_join.right, &mut predicates);
+    Some(top_inner_join(&left, &left_join.right, predicates))
+}
+
+/// A rule that exchanges the left input of a join with the left input of the join to its right.
+///
+/// `Join(A, Join(B, C))` is logically equivalent to `Join(B, Join(A, C))`.
+///
+/// This only holds for inner joins. It is not part of [`STATIC_TRANSFORMATION_RULES`], since the
+/// other join rules already generate the same trees.
+pub fn join_right_exchange(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let top_join = inner_join_of(expr)?;
+    let right_join = inner_join_of(&top_join.right)?;
+
+    let mut predicates = conditions_of(&[top_join, right_join]);
+    let right = inner_join(&top_join.left, &right_join.right, &mut predicates);
+    Some(top_inner_join(&right_join.left, &right, predicates))
+}
+
+/// Returns the join if the expression is a logical inner join.
+fn inner_join_of(expr: &Arc<Expression>) -> Option<&Join> {
+    match expr.as_ref() {
+        Expression::Logical(LogicalExpression::Join(join)) if join.join_type == JoinType::Inner => {
+            Some(join)
+        }
+        _ => None,
+    }
+}
+
+/// Collects the conditions of several joins.
+fn conditions_of(joins: &[&Join]) -> Vec<Arc<ScalarExpression>> {
+    joins
+        .iter()
+        .flat_map(|join| join.conditions.iter().cloned())
+        .collect()
+}
+
+/// Creates an inner join, taking the predicates that only reference its inputs out of `predicates`
+/// as its conditions.
+fn inner_join(
+    left: &Arc<Expression>,
+    right: &Arc<Expression>,
+    predicates: &mut Vec<Arc<ScalarExpression>>,
+) -> Arc<Expression> {
+    let mut columns: BTreeSet<ColumnId> = output_columns(left).into_iter().collect();
+    columns.extend(output_columns(right));
+    let (conditions, rest) = split_predicates(predicates.iter(), &columns);
+    *predicates = rest;
+
+    Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+        join_type: JoinType::Inner,
+        conditions,
+        left: left.clone(),
+        right: right.clone(),
+    })))
+}
+
+/// Creates the top inner join of a rewritten join tree, with all of the remaining predicates as its
+/// conditions: the ones that reference both of its inputs, as well as the ones that reference
+/// columns of neither input (such as the outer references of a correlated subquery).
+fn top_inner_join(
+    left: &Arc<Expression>,
+    right: &Arc<Expression>,
+    predicates: Vec<Arc<ScalarExpression>>,
+) -> Arc<Expression> {
+    Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+        join_type: JoinType::Inner,
+        conditions: predicates,
+        left: left.clone(),
+        right: right.clone(),
+    })))
+}
+
+/// Splits predicates into the ones that only reference the given columns and the rest.
+pub fn split_predicates<'a>(
+    predicates: impl IntoIterator<Item = &'a Arc<ScalarExpression>>,
+    columns: &BTreeSet<ColumnId>,
+) -> (Vec<Arc<ScalarExpression>>, Vec<Arc<ScalarExpression>>) {
+    predicates
+        .into_iter()
+        .cloned()
+        .partition(|predicate| predicate.columns().is_subset(columns))
+}
+
+/// Appends predicates to a conjunction, skipping the ones that it already contains.
+fn and_predicates<'a>(
+    predicates: &[Arc<ScalarExpression>],
+    more: impl IntoIterator<Item = &'a Arc<ScalarExpression>>,
+) -> Vec<Arc<ScalarExpression>> {
+    let mut predicates = predicates.to_vec();
+    for predicate in more {
+        if !predicates.contains(predicate) {
+            predicates.push(predicate.clone());
+        }
+    }
+    predicates
+}
+
+/// Wraps an expression in a filter, or returns it unchanged if there are no predicates.
+pub fn filtered(expr: &Arc<Expression>, filters: Vec<Arc<ScalarExpression>>) -> Arc<Expression> {
+    if filters.is_empty() {
+        return expr.clone();
+    }
+
+    Arc::new(Expression::Logical(LogicalExpression::Filter(Filter {
+        filters,
+        children: expr.clone(),
+    })))
+}
+
+/// A rule that merges adjacent filters.
+///
+/// `Filter(p, Filter(q, A))` is logically equivalent to `Filter(p AND q, A)`.
+pub fn filter_merge(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let Expression::Logical(LogicalExpression::Filter(top)) = expr.as_ref() else {
+        return None;
+    };
+
+    let Expression::Logical(LogicalExpression::Filter(bottom)) = top.children.as_ref() else {
+        return None;
+    };
+
+    Some(filtered(
+        &bottom.children,
+        and_predicates(&bottom.filters, &top.filters),
+    ))
+}
+
+/// A rule that merges a filter into the scan below it.
+///
+/// `Filter(p, Scan(A))` is logically equivalent to `Scan(A, filters: p)`.
+pub fn filter_into_scan(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let Expression::Logical(LogicalExpression::Filter(filter)) = expr.as_ref() else {
+        return None;
+    };
+
+    let Expression::Logical(LogicalExpression::Scan(scan)) = filter.children.as_ref() else {
+        return None;
+    };
+
+    Some(Arc::new(Expression::Logical(LogicalExpression::Scan(
+        Scan {
+            filters: and_predicates(&scan.filters, &filter.filters),
+            ..scan.clone()
+        },
+    ))))
+}
+
+/// A rule that pushes the predicates of a filter below the join under it.
+///
+/// `Filter(p(A) AND q(B) AND r(A, B), Join(A, B))` is logically equivalent to
+/// `Join(Filter(p, A), Filter(q, B), conditions: r)`.
+///
+/// Predicates may only be pushed into a side whose rows are never padded with `NULL`s, and only
+/// inner joins accept predicates on both sides as join conditions. Everything else stays in a
+/// filter above the join.
+pub fn filter_join_pushdown(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let Expression::Logical(LogicalExpression::Filter(filter)) = expr.as_ref() else {
+        return None;
+    };
+
+    let Expression::Logical(LogicalExpression::Join(join)) = filter.children.as_ref() else {
+        return None;
+    };
+
+    let (into_left, into_right, into_conditions) = match join.join_type {
+        JoinType::Inner => (true, true, true),
+        JoinType::LeftOuter | JoinType::LeftSemi | JoinType::LeftAnti => (true, false, false),
+        JoinType::RightOuter => (false, true, false),
+        JoinType::FullOuter => (false, false, false),
+    };
+
+    let left_columns: BTreeSet<ColumnId> = output_columns(&join.left).into_iter().collect();
+    let right_columns: BTreeSet<ColumnId> = output_columns(&join.right).into_iter().collect();
+
+    let (mut left_filters, mut right_filters, mut conditions, mut rest) =
+        (vec![], vec![], vec![], vec![]);
+    for predicate in &filter.filters {
+        let columns = predicate.columns();
+        if into_left && columns.is_subset(&left_columns) {
+            left_filters.push(predicate.clone());
+        } else if into_right && columns.is_subset(&right_columns) {
+            right_filters.push(predicate.clone());
+        } else if into_conditions {
+            conditions.push(predicate.clone());
+        } else {
+            rest.push(predicate.clone());
+        }
+    }
+
+    if rest.len() == filter.filters.len() {
+        return None;
+    }
+
+    let new_join = Join {
+        join_type: join.join_type,
+        conditions: and_predicates(&join.conditions, &conditions),
+        left: filtered(&join.left, left_filters),
+        right: filtered(&join.right, right_filters),
+    };
+
+    Some(filtered(
+        &Arc::new(Expression::Logical(LogicalExpression::Join(new_join))),
+        rest,
+    ))
+}
+
+/// A rule that pushes the join conditions that only reference one side of a join into a filter
+/// on that side.
+///
+/// `Join(A, B, conditions: p(A) AND q(B) AND r(A, B))` is logically equivalent to
+/// `Join(Filter(p, A), Filter(q, B), conditions: r)` for inner joins.
+///
+/// For the other join types, a condition can only be pushed into a side whose rows are only output
+/// when they have a match (the right side of a left outer join, for example).
+pub fn join_condition_pushdown(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let Expression::Logical(LogicalExpression::Join(join)) = expr.as_ref() else {
+        return None;
+    };
+
+    let (into_left, into_right) = match join.join_type {
+        JoinType::Inner => (true, true),
+        JoinType::LeftSemi => (true, true),
+        JoinType::LeftOuter | JoinType::LeftAnti => (false, true),
+        JoinType::RightOuter => (true, false),
+        JoinType::FullOuter => (false, false),
+    };
+
+    let left_columns: BTreeSet<ColumnId> = output_columns(&join.left).into_iter().collect();
+    let right_columns: BTreeSet<ColumnId> = output_columns(&join.right).into_iter().collect();
+
+    let (mut left_filters, mut right_filters, mut conditions) = (vec![], vec![], vec![]);
+    for condition in &join.conditions {
+        let columns = condition.columns();
+        if into_left && columns.is_subset(&left_columns) {
+            left_filters.push(condition.clone());
+        } else if into_right && columns.is_subset(&right_columns) {
+            right_filters.push(condition.clone());
+        } else {
+            conditions.push(condition.clone());
+        }
+    }
+
+    if conditions.len() == join.conditions.len() {
+        return None;
+    }
+
+    let new_join = Join {
+        join_type: join.join_type,
+        conditions,
+        left: filtered(&join.left, left_filters),
+        right: filtered(&join.right, right_filters),
+    };
+
+    Some(Arc::new(Expression::Logical(LogicalExpression::Join(
+        new_join,
+    ))))
+}
+
+/// A rule that merges adjacent projections.
+///
+/// `Project(a, Project(a, b, A))` is logically equivalent to `Project(a, A)`.
+pub fn project_merge(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let Expression::Logical(LogicalExpression::Project(top)) = expr.as_ref() else {
+        return None;
+    };
+
+    let Expression::Logical(LogicalExpression::Project(bottom)) = top.children.as_ref() else {
+        return None;
+    };
+
+    Some(Arc::new(Expression::Logical(LogicalExpression::Project(
+        Project {
+            columns: top.columns.clone(),
+            children: bottom.children.clone(),
+        },
+    ))))
+}
+
+/// A rule that removes a projection that outputs exactly the columns of its child, in the same
+/// order.
+///
+/// `Project(a, b, A)` is logically equivalent to `A` if `A` outputs `a, b`.
+pub fn project_removal(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let Expression::Logical(LogicalExpression::Project(project)) = expr.as_ref() else {
+        return None;
+    };
+
+    (output_columns(&project.children) == project.columns).then(|| project.children.clone())
+}
+
+/// A rule that simplifies the predicates of a filter, a scan or a join with
+/// [`simplify_conjunction`].
+///
+/// Filters whose predicates are always true are removed, and filters, scans and inner joins whose
+/// predicates can never be true are replaced with an empty relation.
+pub fn predicate_simplification(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let Expression::Logical(logical) = expr.as_ref() else {
+        return None;
+    };
+
+    let empty = || {
+        Some(Arc::new(Expression::Logical(LogicalExpression::Empty(
+            Empty {
+                columns: output_columns(expr),
+            },
+        ))))
+    };
+
+    match logical {
+        LogicalExpression::Filter(filter) => {
+            let Some(filters) = simplify_conjunction(&filter.filters) else {
+                return empty();
+            };
+            if filters.is_empty() {
+                return Some(filter.children.clone());
+            }
+
+            (filters != filter.filters).then(|| {
+                Arc::new(Expression::Logical(LogicalExpression::Filter(Filter {
+                    filters,
+                    children: filter.children.clone(),
+                })))
+            })
+        }
+        LogicalExpression::Scan(scan) => {
+            let Some(filters) = simplify_conjunction(&scan.filters) else {
+                return empty();
+            };
+
+            (filters != scan.filters).then(|| {
+                Arc::new(Expression::Logical(LogicalExpression::Scan(Scan {
+                    filters,
+                    ..scan.clone()
+                })))
+            })
+        }
+        LogicalExpression::Join(join) => {
+            let conditions = match simplify_conjunction(&join.conditions) {
+                Some(conditions) => conditions,
+                // Only inner and semi joins need a pair of matching rows to output a row.
+                None if matches!(join.join_type, JoinType::Inner | JoinType::LeftSemi) => {
+                    return empty();
+                }
+                None => vec![ScalarExpression::literal(false)],
+            };
+
+            (conditions != join.conditions).then(|| {
+                Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+                    conditions,
+                    ..join.clone()
+                })))
+            })
+        }
+        _ => None,
+    }
+}
+
+/// A rule that replaces an expression with an empty relation if its inputs are empty.
+///
+/// `Filter(Empty)`, `Project(Empty)`, `Sort(Empty)` and `Limit(Empty)` are empty, and so are a
+/// limit of zero rows, a union of empty inputs, and a join, an intersection or a difference if one
+/// of the inputs that every output row needs is empty.
+pub fn empty_propagation(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let is_empty = |expr: &Arc<Expression>| {
+        matches!(
+            expr.as_ref(),
+            Expression::Logical(LogicalExpression::Empty(_))
+        )
+    };
+
+    let empty = match expr.as_ref() {
+        Expression::Logical(LogicalExpression::Filter(filter)) => is_empty(&filter.children),
+        Expression::Logical(LogicalExpression::Project(project)) => is_empty(&project.children),
+        Expression::Logical(LogicalExpression::Join(join)) => {
+            let (left, right) = (is_empty(&join.left), is_empty(&join.right));
+            match join.join_type {
+                JoinType::Inner | JoinType::LeftSemi => left || right,
+                JoinType::LeftOuter | JoinType::LeftAnti => left,
+                JoinType::RightOuter => right,
+                JoinType::FullOuter => left && right,
+            }
+        }
+        Expression::Logical(LogicalExpression::Sort(sort)) => is_empty(&sort.children),
+        Expression::Logical(LogicalExpression::Limit(limit)) => {
+            limit.limit == 0 || is_empty(&limit.children)
+        }
+        Expression::Logical(LogicalExpression::Union(union)) => union.inputs.iter().all(is_empty),
+        Expression::Logical(LogicalExpression::Intersect(intersect)) => {
+            is_empty(&intersect.left) || is_empty(&intersect.right)
+        }
+        Expression::Logical(LogicalExpression::Except(except)) => is_empty(&except.left),
+        _ => false,
+    };
+
+    empty.then(|| {
+        Arc::new(Expression::Logical(LogicalExpression::Empty(Empty {
+            columns: output_columns(expr),
+        })))
+    })
+}
+
+/// A rule that adds the predicates implied by the column equivalences of a filter, a scan or an
+/// inner join (see [`infer_predicates`]).
+///
+/// `Filter(a = 5, Join(a = b, A, B))` is logically equivalent to `Filter(a = 5 AND b = 5, Join(a =
+/// b, A, B))`, and the new predicate can be pushed down into `B`.
+pub fn predicate_inference(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let Expression::Logical(logical) = expr.as_ref() else {
+        return None;
+    };
+
+    let inferred = match logical {
+        LogicalExpression::Filter(filter) => {
+            let inferred = infer_predicates(&filter.filters, &equivalences(&filter.children));
+            if inferred.is_empty() {
+                return None;
+            }
+            LogicalExpression::Filter(Filter {
+                filters: [filter.filters.clone(), inferred].concat(),
+                children: filter.children.clone(),
+            })
+        }
+        LogicalExpression::Scan(scan) => {
+            let inferred = infer_predicates(&scan.filters, &[]);
+            if inferred.is_empty() {
+                return None;
+            }
+            LogicalExpression::Scan(Scan {
+                filters: [scan.filters.clone(), inferred].concat(),
+                ..scan.clone()
+            })
+        }
+        LogicalExpression::Join(join) if join.join_type == JoinType::Inner => {
+            let mut input = equivalences(&join.left);
+            input.extend(equivalences(&join.right));
+            let inferred = infer_predicates(&join.conditions, &input);
+            if inferred.is_empty() {
+                return None;
+            }
+            LogicalExpression::Join(Join {
+                conditions: [join.conditions.clone(), inferred].concat(),
+                ..join.clone()
+            })
+        }
+        _ => return None,
+    };
+
+    Some(Arc::new(Expression::Logical(inferred)))
+}
+
+/// A rule that turns an outer join into an inner join (or a full outer join into a one-sided outer
+/// join) if a filter above it rejects the rows that the join pads with `NULL`s.
+///
+/// `Filter(b > 5, LeftOuterJoin(A, B))` is logically equivalent to `Filter(b > 5, Join(A, B))`,
+/// since `b > 5` is never true for the rows of `A` without a match in `B`.
+pub fn outer_join_simplification(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let Expression::Logical(LogicalExpression::Filter(filter)) = expr.as_ref() else {
+        return None;
+    };
+
+    let Expression::Logical(LogicalExpression::Join(join)) = filter.children.as_ref() else {
+        return None;
+    };
+
+    let rejects = |side: &Arc<Expression>| {
+        let columns = output_columns(side).into_iter().collect();
+        filter
+            .filters
+            .iter()
+            .any(|predicate| rejects_nulls(predicate, &columns))
+    };
+
+    let join_type = match join.join_type {
+        JoinType::LeftOuter if rejects(&join.right) => JoinType::Inner,
+        JoinType::RightOuter if rejects(&join.left) => JoinType::Inner,
+        JoinType::FullOuter => match (rejects(&join.left), rejects(&join.right)) {
+            (true, true) => JoinType::Inner,
+            (true, false) => JoinType::LeftOuter,
+            (false, true) => JoinType::RightOuter,
+            (false, false) => return None,
+        },
+        _ => return None,
+    };
+
+    let new_join = Join {
+        join_type,
+        ..join.clone()
+    };
+
+    Some(filtered(
+        &Arc::new(Expression::Logical(LogicalExpression::Join(new_join))),
+        filter.filters.clone(),
+    ))
+}
+
+/// Returns the aggregate if the expression is a logical aggregate in the given phase.
+fn aggregate_of(expr: &Arc<Expression>, phase: AggregatePhase) -> Option<&Aggregate> {
+    match expr.as_ref() {
+        Expression::Logical(LogicalExpression::Aggregate(aggregate))
+            if aggregate.phase == phase =>
+        {
+            Some(aggregate)
+        }
+        _ => None,
+    }
+}
+
+/// Creates the final aggregation that combines the partial results of an aggregation.
+fn final_aggregate(aggregate: &Aggregate, partial: Arc<Expression>) -> Arc<Expression> {
+    let aggregates = aggregate
+        .aggregates
+        .iter()
+        .map(|call| AggregateCall {
+            function: call.function.combine(),
+            argument: Some(call.output),
+            output: call.output,
+        })
+        .collect();
+
+    Arc::new(Expression::Logical(LogicalExpression::Aggregate(
+        Aggregate {
+            phase: AggregatePhase::Final,
+            group_by: aggregate.group_by.clone(),
+            aggregates,
+            children: partial,
+        },
+    )))
+}
+
+/// A rule that splits an aggregation into a partial and a final aggregation.
+///
+/// `Aggregate(g, f(x), A)` is logically equivalent to `Final(g, combine(f)(x'), Partial(g, f(x) as
+/// x', A))`. The partial aggregation can run on every partition of its input separately, and only
+/// its (much smaller) output has to be shipped to a single place for the final aggregation.
+pub fn aggregate_split(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let aggregate = aggregate_of(expr, AggregatePhase::Complete)?;
+
+    let partial = Arc::new(Expression::Logical(LogicalExpression::Aggregate(
+        Aggregate {
+            phase: AggregatePhase::Partial,
+            ..aggregate.clone()
+        },
+    )));
+    Some(final_aggregate(aggregate, partial))
+}
+
+/// A rule that pushes a partial aggregation below an inner join, into the side that all of the
+/// aggregate functions read from (eager aggregation, as in Yan and Larson, "Eager Aggregation and
+/// Lazy Aggregation").
+///
+/// `Aggregate(g, f(b), Join(a = b', A, B))` is logically equivalent to `Final(g, combine(f)(x),
+/// Join(a = b', A, Partial(g_B, b', f(b) as x, B)))`, where `g_B` are the grouping columns of `B`.
+/// Every row of `B` in a partial group joins with the same rows of `A`, so the partial results can
+/// be combined over the join instead of the rows themselves. The original expression (lazy
+/// aggregation) stays in the group, and the cost model picks whichever is cheaper: aggregating
+/// early is worth it if it shrinks `B` by a lot, and the join does not.
+///
+/// A scalar aggregate with a `COUNT` is left alone, since the count of an empty join is `0`, but
+/// the sum of no partial counts is `NULL`. So is an aggregation whose partial aggregation would not
+/// group by anything (below a cross product, for example): a scalar aggregation outputs a row even
+/// if its input is empty, which would then join with every row of the other side.
+pub fn eager_aggregation(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let aggregate = aggregate_of(expr, AggregatePhase::Complete)?;
+    let join = inner_join_of(&aggregate.children)?;
+
+    if aggregate.group_by.is_empty()
+        && aggregate
+            .aggregates
+            .iter()
+            .any(|call| call.function == AggregateFunction::Count)
+    {
+        return None;
+    }
+
+    let arguments: BTreeSet<ColumnId> = aggregate
+        .aggregates
+        .iter()
+        .filter_map(|call| call.argument)
+        .collect();
+    let left_columns: BTreeSet<ColumnId> = output_columns(&join.left).into_iter().collect();
+    let right_columns: BTreeSet<ColumnId> = output_columns(&join.right).into_iter().collect();
+
+    let (side, into_left) = if arguments.is_subset(&right_columns) {
+        (&join.right, false)
+    } else if arguments.is_subset(&left_columns) {
+        (&join.left, true)
+    } else {
+        return None;
+    };
+
+    // The partial groups have to keep apart the rows that the join or the final aggregation tell
+    // apart.
+    let mut needed: BTreeSet<ColumnId> = aggregate.group_by.iter().copied().collect();
+    needed.extend(
+        join.conditions
+            .iter()
+            .flat_map(|condition| condition.columns()),
+    );
+    let group_by: Vec<ColumnId> = output_columns(side)
+        .into_iter()
+        .filter(|column| needed.contains(column))
+        .collect();
+    if group_by.is_empty() {
+        return None;
+    }
+
+    let partial = Arc::new(Expression::Logical(LogicalExpression::Aggregate(
+        Aggregate {
+            phase: AggregatePhase::Partial,
+            group_by,
+            aggregates: aggregate.aggregates.clone(),
+            children: side.clone(),
+        },
+    )));
+    let (left, right) = if into_left {
+        (partial, join.right.clone())
+    } else {
+        (join.left.clone(), partial)
+    };
+    let join = Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+        left,
+        right,
+        ..join.clone()
+    })));
+
+    Some(final_aggregate(aggregate, join))
+}
+
+/// A rule that removes an aggregation whose grouping columns are a unique key of its input, since
+/// every group then consists of a single row.
+///
+/// `Aggregate(k, A)` is logically equivalent to `Project(k, A)` if `k` is unique in `A` and cannot
+/// be `NULL`: a unique key may hold any number of `NULL`s, but they all end up in the same group.
+///
+/// TODO: Aggregations that compute aggregate functions could be replaced as well, but the result
+/// of `MAX(x)` over a single row is `x` under a different column, and projections cannot rename
+/// columns yet.
+pub fn aggregate_removal(expr: &Arc<Expression>, estimator: &Estimator) -> Option<Arc<Expression>> {
+    let Expression::Logical(LogicalExpression::Aggregate(aggregate)) = expr.as_ref() else {
+        return None;
+    };
+
+    // A scalar aggregation outputs a row even if its input is empty.
+    if aggregate.group_by.is_empty() || !aggregate.aggregates.is_empty() {
+        return None;
+    }
+
+    let input = logical_properties(&aggregate.children, estimator);
+    if !input.is_unique(&aggregate.group_by.iter().copied().collect()) {
+        return None;
+    }
+    let nullable = input
+        .schema
+        .iter()
+        .any(|field| field.nullable && aggregate.group_by.contains(&field.id));
+    if nullable {
+        return None;
+    }
+
+    Some(Arc::new(Expression::Logical(LogicalExpression::Project(
+        Project {
+            columns: aggregate.group_by.clone(),
+            children: aggregate.children.clone(),
+        },
+    ))))
+}
+
+/// A rule that removes the joins below a projection or an aggregation that neither contribute any
+/// columns that are used nor change the rows of the other side of the join.
+///
+/// - An inner join to a table that the other side refers to with a foreign key matches every row
+///   of the other side exactly once (unless the key is `NULL`), so `Project(a, Join(a.fk = b.pk,
+///   A, B))` is logically equivalent to `Project(a, Filter(a.fk IS NOT NULL, A))`.
+/// - A left outer join to a side that is unique on the columns it is joined on keeps every row of
+///   the left side exactly once, so `Project(a, LeftOuterJoin(a.x = b.key, A, B))` is logically
+///   equivalent to `Project(a, A)`.
+///
+/// Joins are removed anywhere in a tree of joins and filters, as long as nothing above them uses
+/// the columns of the removed side.
+pub fn join_elimination(expr: &Arc<Expression>, estimator: &Estimator) -> Option<Arc<Expression>> {
+    let Expression::Logical(logical) = expr.as_ref() else {
+        return None;
+    };
+
+    let (required, child) = match logical {
+        LogicalExpression::Project(project) => {
+            (project.columns.iter().copied().collect(), &project.children)
+        }
+        LogicalExpression::Aggregate(aggregate) => (aggregate.input_columns(), &aggregate.children),
+        _ => return None,
+    };
+
+    let child = eliminate_joins(child, &required, estimator)?;
+    Some(Arc::new(expr.with_children(vec![child])))
+}
+
+/// Removes the joins that [`join_elimination`] can remove from a tree of joins and filters, given
+/// the columns that are used above it. Returns `None` if no join can be removed.
+fn eliminate_joins(
+    expr: &Arc<Expression>,
+    required: &BTreeSet<ColumnId>,
+    estimator: &Estimator,
+) -> Option<Arc<Expression>> {
+    let Expression::Logical(logical) = expr.as_ref() else {
+        return None;
+    };
+
+    match logical {
+        LogicalExpression::Filter(filter) => {
+            let mut needed = required.clone();
+            needed.extend(filter.filters.iter().flat_map(|filter| filter.columns()));
+            let child = eliminate_joins(&filter.children, &needed, estimator)?;
+            Some(filtered(&child, filter.filters.clone()))
+        }
+        LogicalExpression::Join(join) => {
+            if let Some(kept) = eliminate_join(join, required, estimator) {
+                return Some(eliminate_joins(&kept, required, estimator).unwrap_or(kept));
+            }
+
+            let mut needed = required.clone();
+            needed.extend(
+                join.conditions
+                    .iter()
+                    .flat_map(|condition| condition.columns()),
+            );
+            let left = eliminate_joins(&join.left, &needed, estimator);
+            let right = eliminate_joins(&join.right, &needed, estimator);
+            if left.is_none() && right.is_none() {
+                return None;
+            }
+
+            Some(Arc::new(Expression::Logical(LogicalExpression::Join(
+                Join {
+                    left: left.unwrap_or_else(|| join.left.clone()),
+                    right: right.unwrap_or_else(|| join.right.clone()),
+                    ..join.clone()
+                },
+            ))))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the side of a join that is left over if the other side can be removed.
+fn eliminate_join(
+    join: &Join,
+    required: &BTreeSet<ColumnId>,
+    estimator: &Estimator,
+) -> Option<Arc<Expression>> {
+    let unused = |side: &Arc<Expression>| {
+        output_columns(side)
+            .iter()
+            .all(|column| !required.contains(column))
+    };
+
+    match join.join_type {
+        JoinType::Inner => {
+            let right = unused(&join.right)
+                .then(|| foreign_key_join(&join.left, &join.right, &join.conditions, estimator))
+                .flatten();
+            right.or_else(|| {
+                unused(&join.left)
+                    .then(|| foreign_key_join(&join.right, &join.left, &join.conditions, estimator))
+                    .flatten()
+            })
+        }
+        JoinType::LeftOuter if unused(&join.right) => {
+            unique_join(&join.left, &join.right, &join.conditions, estimator)
+                .then(|| join.left.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Returns the pairs of columns of `kept` and `dropped` that the conditions of a join compare for
+/// equality, along with the conditions that are not such an equality.
+fn join_equalities(
+    kept: &Arc<Expression>,
+    dropped: &Arc<Expression>,
+    conditions: &[Arc<ScalarExpression>],
+) -> (Vec<(ColumnId, ColumnId)>, Vec<Arc<ScalarExpression>>) {
+    let kept: BTreeSet<ColumnId> = output_columns(kept).into_iter().collect();
+    let dropped: BTreeSet<ColumnId> = output_columns(dropped).into_iter().collect();
+
+    let (mut pairs, mut rest) = (vec![], vec![]);
+    for condition in conditions {
+        match condition.as_column_equality() {
+            Some((left, right)) if kept.contains(&left) && dropped.contains(&right) => {
+                pairs.push((left, right));
+            }
+            Some((left, right)) if kept.contains(&right) && dropped.contains(&left) => {
+                pairs.push((right, left));
+            }
+            _ => rest.push(condition.clone()),
+        }
+    }
+    (pairs, rest)
+}
+
+/// If an inner join matches every row of `kept` with exactly one row of `dropped` through a
+/// foreign key, returns `kept` without the rows whose key is `NULL`.
+fn foreign_key_join(
+    kept: &Arc<Expression>,
+    dropped: &Arc<Expression>,
+    conditions: &[Arc<ScalarExpression>],
+    estimator: &Estimator,
+) -> Option<Arc<Expression>> {
+    // Every row of the referenced table has to be there to be matched.
+    let Expression::Logical(LogicalExpression::Scan(scan)) = dropped.as_ref() else {
+        return None;
+    };
+    if !scan.filters.is_empty() {
+        return None;
+    }
+
+    let (pairs, rest) = join_equalities(kept, dropped, conditions);
+    if !rest.is_empty() {
+        return None;
+    }
+
+    // The foreign key is declared over the columns of the tables, which the scans on both sides
+    // have their own instances of.
+    let catalog = estimator.catalog();
+    let &(first, first_referenced) = pairs.first()?;
+    let table = catalog.table(catalog.table_of(first)?)?;
+    let foreign_key = table.foreign_keys.iter().find(|foreign_key| {
+        foreign_key.referenced_table == scan.table_id
+            && foreign_key.columns.len() == pairs.len()
+            && foreign_key
+                .columns
+                .iter()
+                .zip(&foreign_key.referenced_columns)
+                .all(|(&column, &referenced)| {
+                    pairs.contains(&(
+                        catalog.scan_column(first, column),
+                        catalog.scan_column(first_referenced, referenced),
+                    ))
+                })
+    })?;
+
+    let properties = logical_properties(kept, estimator);
+    let checks = foreign_key
+        .columns
+        .iter()
+        .map(|&column| catalog.scan_column(first, column))
+        .filter(|&column| properties.field(column).is_none_or(|field| field.nullable))
+        .map(|column| {
+            Arc::new(ScalarExpression::IsNotNull(ScalarExpression::column(
+                column,
+            )))
+        })
+        .collect();
+    Some(filtered(kept, checks))
+}
+
+/// Returns `true` if a join matches every row of `kept` with at most one row of `dropped`, because
+/// `dropped` is unique on the columns that the join compares for equality.
+fn unique_join(
+    kept: &Arc<Expression>,
+    dropped: &Arc<Expression>,
+    conditions: &[Arc<ScalarExpression>],
+    estimator: &Estimator,
+) -> bool {
+    let (pairs, _) = join_equalities(kept, dropped, conditions);
+    let matched = pairs.into_iter().map(|(_, column)| column).collect();
+    logical_properties(dropped, estimator).is_unique(&matched)
+}
@@ -0,0 +1,262 @@
+//! Rules that unnest correlated subqueries, turning dependent joins ([`Apply`]) into regular joins.
+//!
+//! A dependent join evaluates its right side once for every row of its left side, which is only
+//! ever executed as a nested loop. Following Neumann and Kemper, "Unnesting Arbitrary Queries"
+//! (BTW 2015), the rules push the dependent join down through the right side until the right side
+//! no longer references any column of the left side, at which point the dependent join is the same
+//! as a regular join:
+//!
+//! - Correlated predicates at the top of the right side become conditions of the dependent join
+//!   ([`correlated_predicate_pullup`]).
+//! - Projections are moved above the dependent join ([`apply_project_pullup`]).
+//! - Aggregates that are correlated through equalities are grouped by the correlated columns
+//!   instead, and joined on them with an outer join ([`apply_aggregate_unnesting`]). This is the
+//!   special case of the general algorithm where the domain of the outer references can be
+//!   replaced by the columns they are equal to.
+//! - A dependent join without any outer references becomes a regular join of the same type
+//!   ([`apply_decorrelation`]).
+//!
+//! `EXISTS` and `IN` subqueries end up as semi joins, `NOT EXISTS` as anti joins, and scalar
+//! subqueries as outer joins with an aggregate on the right side. Dependent joins that the rules
+//! cannot unnest (with correlated predicates below an outer join, for example) are left as they
+//! are and executed with a nested loop.
+
+use super::transformation::filtered;
+use crate::expression::logical::{outer_references, output_columns, AggregateFunction};
+use crate::expression::scalar::ScalarExpression;
+use crate::expression::simplify::rejects_nulls;
+use crate::properties::ColumnSet;
+use crate::{Aggregate, Apply, Expression, Join, JoinType, LogicalExpression, Project, Scan};
+use std::sync::Arc;
+
+/// Returns the dependent join if the expression is one.
+fn apply_of(expr: &Arc<Expression>) -> Option<&Apply> {
+    match expr.as_ref() {
+        Expression::Logical(LogicalExpression::Apply(apply)) => Some(apply),
+        _ => None,
+    }
+}
+
+/// Returns the columns that the left side of a dependent join binds for its right side.
+fn outer_columns(apply: &Apply) -> ColumnSet {
+    output_columns(&apply.left).into_iter().collect()
+}
+
+/// Takes the predicates that reference any of the outer columns out of the top of an expression:
+/// the predicates of a filter or a scan, or the conditions of an inner join.
+///
+/// Returns the expression without those predicates along with the predicates, or `None` if there
+/// are no correlated predicates at the top of the expression.
+fn take_correlated(
+    expr: &Arc<Expression>,
+    outer: &ColumnSet,
+) -> Option<(Arc<Expression>, Vec<Arc<ScalarExpression>>)> {
+    let Expression::Logical(logical) = expr.as_ref() else {
+        return None;
+    };
+
+    let split = |predicates: &[Arc<ScalarExpression>]| {
+        let (correlated, rest): (Vec<_>, Vec<_>) = predicates
+            .iter()
+            .cloned()
+            .partition(|predicate| !predicate.columns().is_disjoint(outer));
+        (!correlated.is_empty()).then_some((correlated, rest))
+    };
+
+    let (rest, correlated) = match logical {
+        LogicalExpression::Filter(filter) => {
+            let (correlated, rest) = split(&filter.filters)?;
+            (filtered(&filter.children, rest), correlated)
+        }
+        LogicalExpression::Scan(scan) => {
+            let (correlated, filters) = split(&scan.filters)?;
+            let scan = Scan {
+                filters,
+                ..scan.clone()
+            };
+            (
+                Arc::new(Expression::Logical(LogicalExpression::Scan(scan))),
+                correlated,
+            )
+        }
+        LogicalExpression::Join(join) if join.join_type == JoinType::Inner => {
+            let (correlated, conditions) = split(&join.conditions)?;
+            let join = Join {
+                conditions,
+                ..join.clone()
+            };
+            (
+                Arc::new(Expression::Logical(LogicalExpression::Join(join))),
+                correlated,
+            )
+        }
+        _ => return None,
+    };
+
+    Some((rest, correlated))
+}
+
+/// A rule that turns a dependent join whose right side does not reference its left side into a
+/// regular join.
+///
+/// `Apply(A, B)` is logically equivalent to `Join(A, B)` if `B` has no outer references to `A`.
+pub fn apply_decorrelation(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let apply = apply_of(expr)?;
+
+    if !outer_references(&apply.right, &outer_columns(apply)).is_empty() {
+        return None;
+    }
+
+    Some(Arc::new(Expression::Logical(LogicalExpression::Join(
+        apply.as_join(),
+    ))))
+}
+
+/// A rule that moves the correlated predicates at the top of the right side of a dependent join
+/// into its conditions.
+///
+/// `Apply(A, Filter(p(A, B) AND q(B), B))` is logically equivalent to `Apply(A, Filter(q, B),
+/// conditions: p)` for every join type, since both only pair a row of `A` with the rows of `B`
+/// that satisfy `p`.
+pub fn correlated_predicate_pullup(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let apply = apply_of(expr)?;
+    let (right, correlated) = take_correlated(&apply.right, &outer_columns(apply))?;
+
+    Some(Arc::new(Expression::Logical(LogicalExpression::Apply(
+        Apply {
+            conditions: [apply.conditions.clone(), correlated].concat(),
+            right,
+            ..apply.clone()
+        },
+    ))))
+}
+
+/// A rule that moves a projection on the right side of a dependent join above it.
+///
+/// `Apply(A, Project(b, B))` is logically equivalent to `Project(a, b, Apply(A, B))`, where `a`
+/// are the columns of `A`. Semi and anti joins only output the columns of `A`, so the projection
+/// is not needed at all.
+pub fn apply_project_pullup(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let apply = apply_of(expr)?;
+
+    let Expression::Logical(LogicalExpression::Project(project)) = apply.right.as_ref() else {
+        return None;
+    };
+
+    let pulled = Arc::new(Expression::Logical(LogicalExpression::Apply(Apply {
+        right: project.children.clone(),
+        ..apply.clone()
+    })));
+
+    if !apply.join_type.outputs_right() {
+        return Some(pulled);
+    }
+
+    Some(Arc::new(Expression::Logical(LogicalExpression::Project(
+        Project {
+            columns: output_columns(expr),
+            children: pulled,
+        },
+    ))))
+}
+
+/// A rule that unnests a dependent join with an aggregate on its right side, if the aggregate is
+/// correlated through equalities between outer columns and columns of its input.
+///
+/// `Apply(A, Aggregate(group by g, Filter(a = b AND p(B), B)))` is logically equivalent to
+/// `Join(A, Aggregate(group by g, b, Filter(p, B)), conditions: a = b)`: instead of aggregating
+/// the rows of `B` that match a row of `A` once for every row of `A`, all of the rows of `B` are
+/// aggregated at once, grouped by the column that they are matched on.
+///
+/// A scalar aggregate (without any grouping columns) outputs a row even for the rows of `A`
+/// without any matches, so it needs a left outer join instead, and only works with aggregate
+/// functions that are `NULL` on empty inputs. `COUNT` is `0` instead (the "count bug"), so
+/// scalar aggregates with a `COUNT` are left alone.
+pub fn apply_aggregate_unnesting(expr: &Arc<Expression>) -> Option<Arc<Expression>> {
+    let apply = apply_of(expr)?;
+
+    let Expression::Logical(LogicalExpression::Aggregate(aggregate)) = apply.right.as_ref() else {
+        return None;
+    };
+
+    let outer = outer_columns(apply);
+    let (input, correlated) = take_correlated(&aggregate.children, &outer)?;
+
+    // Every correlated predicate has to match an outer column with a column of the input, which
+    // the input is then grouped by.
+    let mut group_by = aggregate.group_by.clone();
+    for predicate in &correlated {
+        let (left, right) = predicate.as_column_equality()?;
+        let inner = match (outer.contains(&left), outer.contains(&right)) {
+            (true, false) => right,
+            (false, true) => left,
+            _ => return None,
+        };
+        if !group_by.contains(&inner) {
+            group_by.push(inner);
+        }
+    }
+
+    // The remaining input must not be correlated anymore.
+    if !outer_references(&input, &outer).is_empty() {
+        return None;
+    }
+
+    let join_type = if aggregate.group_by.is_empty() {
+        let counts = aggregate
+            .aggregates
+            .iter()
+            .any(|call| call.function == AggregateFunction::Count);
+        if counts {
+            return None;
+        }
+
+        // The rows of `A` without a match get `NULL` results, which must not change whether the
+        // conditions of the dependent join hold for them.
+        let results: ColumnSet = aggregate
+            .aggregates
+            .iter()
+            .map(|call| call.output)
+            .collect();
+        let rejected = !apply.conditions.is_empty()
+            && apply
+                .conditions
+                .iter()
+                .any(|condition| rejects_nulls(condition, &results));
+        match apply.join_type {
+            JoinType::LeftOuter => JoinType::LeftOuter,
+            JoinType::Inner if apply.conditions.is_empty() => JoinType::LeftOuter,
+            JoinType::Inner | JoinType::LeftSemi | JoinType::LeftAnti if rejected => {
+                apply.join_type
+            }
+            _ => return None,
+        }
+    } else {
+        apply.join_type
+    };
+
+    let grouped = Arc::new(Expression::Logical(LogicalExpression::Aggregate(
+        Aggregate {
+            group_by,
+            children: input,
+            ..aggregate.clone()
+        },
+    )));
+    let join = Arc::new(Expression::Logical(LogicalExpression::Join(Join {
+        join_type,
+        conditions: [correlated, apply.conditions.clone()].concat(),
+        left: apply.left.clone(),
+        right: grouped,
+    })));
+
+    if !join_type.outputs_right() {
+        return Some(join);
+    }
+
+    Some(Arc::new(Expression::Logical(LogicalExpression::Project(
+        Project {
+            columns: output_columns(expr),
+            children: join,
+        },
+    ))))
+}
@@ -0,0 +1,498 @@
+//! Collects [`TableStatistics`] from the rows of a table.
+//!
+//! The builder makes a single pass over the rows. The row count, `NULL` counts, bounds, and widths
+//! are computed exactly, the number of distinct values is estimated with a [`HyperLogLog`] sketch,
+//! and the most common values and histograms are computed from a uniform [`Reservoir`] sample of
+//! the rows. If the whole table fits in the sample, all of the statistics are exact.
+//!
+//! Groups of correlated columns can be declared with [`StatisticsBuilder::with_column_group`], in
+//! which case the same is done for the combinations of their values, and the degrees of the
+//! functional dependencies between them are measured on the sample.
+
+use super::sketch::{HyperLogLog, Reservoir};
+use super::{
+    ColumnGroupStatistics, ColumnStatistics, FunctionalDependency, Histogram, TableStatistics,
+};
+use crate::catalog::{ColumnId, Table};
+use crate::value::Value;
+
+/// The knobs of the [`StatisticsBuilder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatisticsOptions {
+    /// The maximum number of rows kept in the sample.
+    pub sample_size: usize,
+    /// The maximum number of buckets in each histogram.
+    pub histogram_buckets: usize,
+    /// The maximum number of most common values kept for each column.
+    pub most_common_values: usize,
+    /// The precision of the HyperLogLog sketches (see [`HyperLogLog::new`]).
+    pub precision: u8,
+    /// The seed of the sampler, so that statistics are reproducible.
+    pub seed: u64,
+}
+
+impl Default for StatisticsOptions {
+    fn default() -> Self {
+        Self {
+            sample_size: 30_000,
+            histogram_buckets: 100,
+            most_common_values: 100,
+            precision: 12,
+            seed: 0,
+        }
+    }
+}
+
+/// The exact per-column aggregates maintained over every row.
+#[derive(Debug, Clone)]
+struct ColumnAccumulator {
+    nulls: usize,
+    total_width: usize,
+    min: Option<Value>,
+    max: Option<Value>,
+    distinct: HyperLogLog,
+}
+
+impl ColumnAccumulator {
+    fn new(precision: u8) -> Self {
+        Self {
+            nulls: 0,
+            total_width: 0,
+            min: None,
+            max: None,
+            distinct: HyperLogLog::new(precision),
+        }
+    }
+
+    fn add(&mut self, value: &Value) {
+        if value.is_null() {
+            self.nulls += 1;
+            return;
+        }
+
+        self.total_width += value.width();
+        self.distinct.insert(value);
+
+        if self.min.as_ref().is_none_or(|min| value < min) {
+            self.min = Some(value.clone());
+        }
+        if self.max.as_ref().is_none_or(|max| value > max) {
+            self.max = Some(value.clone());
+        }
+    }
+}
+
+/// The exact aggregates maintained over every row for a group of columns.
+#[derive(Debug, Clone)]
+struct GroupAccumulator {
+    /// The positions of the columns of the group in a row.
+    indices: Vec<usize>,
+    /// The number of rows where none of the columns is `NULL`.
+    non_null: usize,
+    distinct: HyperLogLog,
+}
+
+/// Builds the statistics of a table out of its rows.
+///
+/// ```ignore
+/// let mut builder = StatisticsBuilder::for_table(catalog.table(orders).unwrap())
+///     .with_column_group(vec![city, zip_code]);
+/// builder.add_rows(&rows);
+/// catalog.set_statistics(orders, builder.finish());
+/// ```
+#[derive(Debug, Clone)]
+pub struct StatisticsBuilder {
+    columns: Vec<ColumnId>,
+    options: StatisticsOptions,
+    accumulators: Vec<ColumnAccumulator>,
+    groups: Vec<GroupAccumulator>,
+    sample: Reservoir<Vec<Value>>,
+}
+
+impl StatisticsBuilder {
+    /// Creates a builder for rows whose values are the given columns, in order.
+    pub fn new(columns: Vec<ColumnId>) -> Self {
+        Self::with_options(columns, StatisticsOptions::default())
+    }
+
+    /// Creates a builder for rows with a value for every column of the table, in order.
+    pub fn for_table(table: &Table) -> Self {
+        Self::new(table.column_ids())
+    }
+
+    pub fn with_options(columns: Vec<ColumnId>, options: StatisticsOptions) -> Self {
+        Self {
+            accumulators: vec![ColumnAccumulator::new(options.precision); columns.len()],
+            groups: vec![],
+            sample: Reservoir::new(options.sample_size, options.seed),
+            columns,
+            options,
+        }
+    }
+
+    /// Declares a group of correlated columns, whose combined distinct count, most common
+    /// combinations of values, and functional dependencies are collected as well.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the group has fewer than two columns, or if any of them is not a column of the
+    /// builder.
+    pub fn with_column_group(mut self, columns: Vec<ColumnId>) -> Self {
+        assert!(
+            columns.len() >= 2,
+            "a column group needs at least two columns"
+        );
+
+        let indices = columns
+            .iter()
+            .map(|column| {
+                self.columns
+                    .iter()
+                    .position(|candidate| candidate == column)
+                    .expect("every column of a group must be a column of the builder")
+            })
+            .collect();
+
+        self.groups.push(GroupAccumulator {
+            indices,
+            non_null: 0,
+            distinct: HyperLogLog::new(self.options.precision),
+        });
+        self
+    }
+
+    /// Adds a single row.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the row does not have exactly one value per column.
+    pub fn add_row(&mut self, row: &[Value]) {
+        assert_eq!(
+            row.len(),
+            self.columns.len(),
+            "every row needs exactly one value per column"
+        );
+
+        for (accumulator, value) in self.accumulators.iter_mut().zip(row) {
+            accumulator.add(value);
+        }
+        for group in &mut self.groups {
+            let values: Vec<&Value> = group.indices.iter().map(|&index| &row[index]).collect();
+            if values.iter().all(|value| !value.is_null()) {
+                group.non_null += 1;
+                group.distinct.insert(&values);
+            }
+        }
+        self.sample.offer_with(|| row.to_vec());
+    }
+
+    pub fn add_rows<'a>(&mut self, rows: impl IntoIterator<Item = &'a Vec<Value>>) {
+        for row in rows {
+            self.add_row(row);
+        }
+    }
+
+    /// Computes the statistics of all of the rows added so far.
+    pub fn finish(self) -> TableStatistics {
+        let row_count = self.sample.seen() as f64;
+        let complete = self.sample.is_complete();
+        let sample = self.sample.into_items();
+
+        let columns = self
+            .columns
+            .iter()
+            .zip(self.accumulators)
+            .enumerate()
+            .map(|(index, (&column, accumulator))| {
+                let mut values: Vec<Value> = sample
+                    .iter()
+                    .map(|row| row[index].clone())
+                    .filter(|value| !value.is_null())
+                    .collect();
+                values.sort();
+
+                let statistics = column_statistics(
+                    &self.options,
+                    row_count,
+                    sample.len(),
+                    complete,
+                    accumulator,
+                    &values,
+                );
+                (column, statistics)
+            })
+            .collect();
+
+        let column_groups = self
+            .groups
+            .into_iter()
+            .map(|group| {
+                group_statistics(
+                    &self.options,
+                    &self.columns,
+                    row_count,
+                    complete,
+                    group,
+                    &sample,
+                )
+            })
+            .collect();
+
+        TableStatistics {
+            row_count,
+            columns,
+            column_groups,
+        }
+    }
+}
+
+/// Computes the statistics of a single column out of its exact aggregates and the sorted
+/// non-`NULL` values of the sample.
+fn column_statistics(
+    options: &StatisticsOptions,
+    row_count: f64,
+    sample_rows: usize,
+    complete: bool,
+    accumulator: ColumnAccumulator,
+    values: &[Value],
+) -> ColumnStatistics {
+    let non_null = row_count - accumulator.nulls as f64;
+    if non_null <= 0.0 {
+        return ColumnStatistics {
+            null_fraction: if row_count > 0.0 { 1.0 } else { 0.0 },
+            ..ColumnStatistics::default()
+        };
+    }
+
+    let counts = count_sorted(values);
+
+    // If the sample is the whole table, the distinct values can simply be counted. Otherwise the
+    // sketch has seen every row, but it cannot be less than what the sample has already seen.
+    let distinct_count = if complete {
+        counts.len() as f64
+    } else {
+        accumulator
+            .distinct
+            .estimate()
+            .clamp(counts.len() as f64, non_null)
+    };
+
+    ColumnStatistics {
+        null_fraction: accumulator.nulls as f64 / row_count,
+        distinct_count,
+        most_common_values: most_common_values(options, row_count, sample_rows, complete, &counts),
+        histogram: histogram(options, non_null, distinct_count, &counts),
+        average_width: accumulator.total_width as f64 / non_null,
+        min: accumulator.min,
+        max: accumulator.max,
+    }
+}
+
+/// Computes the statistics of a group of columns out of its exact aggregates and the sample.
+fn group_statistics(
+    options: &StatisticsOptions,
+    columns: &[ColumnId],
+    row_count: f64,
+    complete: bool,
+    group: GroupAccumulator,
+    sample: &[Vec<Value>],
+) -> ColumnGroupStatistics {
+    let group_columns: Vec<ColumnId> = group.indices.iter().map(|&index| columns[index]).collect();
+
+    let mut tuples: Vec<Vec<&Value>> = sample
+        .iter()
+        .map(|row| group.indices.iter().map(|&index| &row[index]).collect())
+        .filter(|tuple: &Vec<&Value>| tuple.iter().all(|value| !value.is_null()))
+        .collect();
+    tuples.sort();
+    let counts = count_sorted(&tuples);
+
+    let distinct_count = if complete {
+        counts.len() as f64
+    } else {
+        group
+            .distinct
+            .estimate()
+            .clamp(counts.len() as f64, group.non_null as f64)
+    };
+
+    let most_common_values =
+        most_common_values(options, row_count, sample.len(), complete, &counts)
+            .into_iter()
+            .map(|(tuple, frequency)| (tuple.into_iter().cloned().collect(), frequency))
+            .collect();
+
+    // Every column depends on every other column, and (for larger groups) on all of the others.
+    let positions: Vec<usize> = (0..group_columns.len()).collect();
+    let mut dependencies = vec![];
+    for &dependent in &positions {
+        let others: Vec<usize> = positions
+            .iter()
+            .copied()
+            .filter(|&position| position != dependent)
+            .collect();
+
+        let mut determinants: Vec<Vec<usize>> = others.iter().map(|&other| vec![other]).collect();
+        if others.len() > 1 {
+            determinants.push(others);
+        }
+
+        for determinant in determinants {
+            dependencies.push(FunctionalDependency {
+                determinant: determinant
+                    .iter()
+                    .map(|&position| group_columns[position])
+                    .collect(),
+                dependent: group_columns[dependent],
+                degree: dependency_degree(&tuples, &determinant, dependent),
+            });
+        }
+    }
+
+    ColumnGroupStatistics {
+        columns: group_columns,
+        distinct_count: Some(distinct_count),
+        most_common_values,
+        dependencies,
+    }
+}
+
+/// The fraction of the sampled rows whose `determinant` values always come with the same
+/// `dependent` value (like the functional dependencies of PostgreSQL's extended statistics).
+fn dependency_degree(tuples: &[Vec<&Value>], determinant: &[usize], dependent: usize) -> f64 {
+    if tuples.is_empty() {
+        return 0.0;
+    }
+
+    let key = |tuple: &Vec<&Value>| -> Vec<Value> {
+        determinant
+            .iter()
+            .chain([&dependent])
+            .map(|&position| tuple[position].clone())
+            .collect()
+    };
+    let mut keys: Vec<Vec<Value>> = tuples.iter().map(key).collect();
+    keys.sort();
+
+    // Rows with the same determinant values are now next to each other.
+    let mut supporting = 0;
+    for rows in keys.chunk_by(|left, right| left[..determinant.len()] == right[..determinant.len()])
+    {
+        if rows.iter().all(|row| row.last() == rows[0].last()) {
+            supporting += rows.len();
+        }
+    }
+
+    supporting as f64 / tuples.len() as f64
+}
+
+/// Counts the number of times each distinct value occurs in a sorted slice.
+fn count_sorted<T: PartialEq>(values: &[T]) -> Vec<(&T, usize)> {
+    let mut counts: Vec<(&T, usize)> = vec![];
+    for value in values {
+        match counts.last_mut() {
+            Some((last, count)) if *last == value => *count += 1,
+            _ => counts.push((value, 1)),
+        }
+    }
+    counts
+}
+
+/// Picks the values that are noticeably more common than the average value of the sample.
+fn most_common_values<T: Ord + Clone>(
+    options: &StatisticsOptions,
+    row_count: f64,
+    sample_rows: usize,
+    complete: bool,
+    counts: &[(&T, usize)],
+) -> Vec<(T, f64)> {
+    let mut candidates: Vec<(&T, usize)> = if complete && counts.len() <= options.most_common_values
+    {
+        // Every value is known exactly, so all of them can be kept.
+        counts.to_vec()
+    } else {
+        // Like PostgreSQL, only keep values that occur noticeably more often than the average
+        // value, and often enough that the relative standard error of their sampled frequency
+        // is below 20%, since the frequencies of the others are mostly noise.
+        let sampled: usize = counts.iter().map(|(_, count)| count).sum();
+        let average = sampled as f64 / counts.len() as f64;
+
+        let (n, total) = (sample_rows as f64, row_count);
+        let significant = n * (total - n) / (total - n + 0.04 * n * (total - 1.0));
+
+        counts
+            .iter()
+            .copied()
+            .filter(|&(_, count)| {
+                let count = count as f64;
+                count >= 2.0 && count > 1.25 * average && count >= significant
+            })
+            .collect()
+    };
+
+    // Sort by descending frequency (and by value for ties, to stay deterministic).
+    candidates.sort_by(|(left, left_count), (right, right_count)| {
+        right_count.cmp(left_count).then_with(|| left.cmp(right))
+    });
+    candidates.truncate(options.most_common_values);
+
+    candidates
+        .into_iter()
+        .map(|(value, count)| (value.clone(), count as f64 / sample_rows as f64))
+        .collect()
+}
+
+/// Builds an equi-depth histogram over the sampled values, scaled up to the whole table.
+fn histogram(
+    options: &StatisticsOptions,
+    non_null: f64,
+    distinct_count: f64,
+    counts: &[(&Value, usize)],
+) -> Option<Histogram> {
+    let sampled: usize = counts.iter().map(|(_, count)| count).sum();
+    let buckets = options.histogram_buckets.min(counts.len());
+    if buckets == 0 {
+        return None;
+    }
+
+    // Each sampled row stands for this many rows of the table, and each distinct value of the
+    // sample stands for this many distinct values of the table.
+    let row_scale = non_null / sampled as f64;
+    let distinct_scale = (distinct_count / counts.len() as f64).max(1.0);
+
+    // Split the distinct values so that every bucket holds roughly `sampled / buckets` rows, never
+    // splitting a value across two buckets.
+    let mut bounds = vec![counts[0].0.clone()];
+    let mut bucket_counts = vec![];
+    let mut bucket_distinct = vec![];
+
+    let (mut rows, mut distinct, mut before) = (0, 0, 0);
+    for (index, &(value, count)) in counts.iter().enumerate() {
+        rows += count;
+        distinct += 1;
+
+        let target = sampled * (bucket_counts.len() + 1) / buckets;
+        let last = index + 1 == counts.len();
+        if before + rows >= target || last {
+            bounds.push(value.clone());
+            bucket_counts.push(rows as f64 * row_scale);
+            bucket_distinct.push(distinct as f64 * distinct_scale);
+
+            before += rows;
+            rows = 0;
+            distinct = 0;
+        }
+    }
+
+    Some(Histogram::new(bounds, bucket_counts, bucket_distinct))
+}
+
+impl TableStatistics {
+    /// A shorthand for building the statistics of rows of the given columns with the default
+    /// options.
+    pub fn from_rows(columns: Vec<ColumnId>, rows: &[Vec<Value>]) -> Self {
+        let mut builder = StatisticsBuilder::new(columns);
+        builder.add_rows(rows);
+        builder.finish()
+    }
+}
@@ -0,0 +1,251 @@
+//! Table and column statistics, used for cardinality estimation.
+//!
+//! Statistics are attached to the tables in the [`Catalog`](crate::catalog::Catalog), where both
+//! the logical property derivation and the cost model can find them. They can either be provided
+//! directly, or collected from the rows of a table with a [`StatisticsBuilder`](builder::StatisticsBuilder).
+
+use crate::catalog::ColumnId;
+use crate::value::Value;
+use std::collections::HashMap;
+
+pub mod builder;
+pub mod sketch;
+
+#[cfg(test)]
+mod tests;
+
+/// An equi-depth histogram over the non-`NULL` values of a column.
+///
+/// Bucket `i` covers the values between `bounds[i]` and `bounds[i + 1]`, and every bucket holds
+/// roughly the same number of rows. Within a bucket, values are assumed to be spread uniformly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    bounds: Vec<Value>,
+    /// The number of rows in each bucket.
+    counts: Vec<f64>,
+    /// The number of distinct values in each bucket.
+    distinct: Vec<f64>,
+}
+
+impl Histogram {
+    /// Creates a histogram out of `n + 1` sorted bounds and the row and distinct counts of each of
+    /// the `n` buckets.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the lengths do not match up or the bounds are not sorted.
+    pub fn new(bounds: Vec<Value>, counts: Vec<f64>, distinct: Vec<f64>) -> Self {
+        assert!(!counts.is_empty(), "a histogram needs at least one bucket");
+        assert_eq!(
+            bounds.len(),
+            counts.len() + 1,
+            "need one more bound than buckets"
+        );
+        assert_eq!(
+            counts.len(),
+            distinct.len(),
+            "need a distinct count per bucket"
+        );
+        assert!(
+            bounds.windows(2).all(|pair| pair[0] <= pair[1]),
+            "histogram bounds must be sorted"
+        );
+
+        Self {
+            bounds,
+            counts,
+            distinct,
+        }
+    }
+
+    pub fn bounds(&self) -> &[Value] {
+        &self.bounds
+    }
+
+    pub fn buckets(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// The total number of rows in the histogram.
+    pub fn total(&self) -> f64 {
+        self.counts.iter().sum()
+    }
+
+    /// Estimates the fraction of the rows in the histogram that are less than (or equal to, if
+    /// `inclusive`) the given value.
+    pub fn fraction_below(&self, value: &Value, inclusive: bool) -> f64 {
+        let total = self.total();
+        if total <= 0.0 {
+            return 0.0;
+        }
+
+        let mut below = 0.0;
+        for bucket in 0..self.buckets() {
+            let (low, high) = (&self.bounds[bucket], &self.bounds[bucket + 1]);
+
+            if value < low {
+                break;
+            }
+
+            if value > high {
+                below += self.counts[bucket];
+                continue;
+            }
+
+            // The value is inside of this bucket, so interpolate.
+            let position = match (low.as_f64(), high.as_f64(), value.as_f64()) {
+                (Some(low), Some(high), Some(value)) if high > low => (value - low) / (high - low),
+                _ => 0.5,
+            };
+            below += self.counts[bucket] * position.clamp(0.0, 1.0);
+
+            let equal = self.counts[bucket] / self.distinct[bucket].max(1.0);
+            if inclusive {
+                below += equal;
+            } else if value == high {
+                below -= equal;
+            }
+            break;
+        }
+
+        (below / total).clamp(0.0, 1.0)
+    }
+
+    /// Estimates the fraction of the rows in the histogram that are equal to the given value,
+    /// assuming that every distinct value in a bucket is equally common.
+    pub fn fraction_equal(&self, value: &Value) -> f64 {
+        let total = self.total();
+        if total <= 0.0 {
+            return 0.0;
+        }
+
+        (0..self.buckets())
+            .find(|&bucket| &self.bounds[bucket] <= value && value <= &self.bounds[bucket + 1])
+            .map_or(0.0, |bucket| {
+                self.counts[bucket] / self.distinct[bucket].max(1.0) / total
+            })
+    }
+}
+
+/// Statistics about a single column.
+///
+/// All fractions are relative to the total number of rows in the table (including `NULL`s).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColumnStatistics {
+    /// The fraction of rows where the column is `NULL`.
+    pub null_fraction: f64,
+    /// The number of distinct non-`NULL` values.
+    pub distinct_count: f64,
+    /// The most common values and the fraction of rows that have each of them.
+    pub most_common_values: Vec<(Value, f64)>,
+    /// A histogram over the non-`NULL` values.
+    pub histogram: Option<Histogram>,
+    pub min: Option<Value>,
+    pub max: Option<Value>,
+    /// The average width of a value in bytes.
+    pub average_width: f64,
+}
+
+impl ColumnStatistics {
+    /// Returns the fraction of rows that have the given value, if it is one of the most common
+    /// values.
+    pub fn most_common_frequency(&self, value: &Value) -> Option<f64> {
+        self.most_common_values
+            .iter()
+            .find(|(common, _)| common == value)
+            .map(|&(_, frequency)| frequency)
+    }
+
+    /// The fraction of rows covered by the most common values.
+    pub fn most_common_total(&self) -> f64 {
+        self.most_common_values
+            .iter()
+            .map(|(_, frequency)| frequency)
+            .sum()
+    }
+}
+
+/// A functional dependency between the columns of a table: knowing the values of the
+/// `determinant` columns (mostly) tells you the value of the `dependent` column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionalDependency {
+    pub determinant: Vec<ColumnId>,
+    pub dependent: ColumnId,
+    /// The fraction of rows for which the dependency holds, between 0 (the columns are
+    /// independent) and 1 (the dependent column is fully determined).
+    pub degree: f64,
+}
+
+/// Statistics about a group of columns of the same table, which capture the correlations between
+/// them that the statistics of the individual columns cannot (such as between a city and a zip
+/// code).
+///
+/// All fractions are relative to the total number of rows in the table.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColumnGroupStatistics {
+    /// The columns of the group, in the order of the values in `most_common_values`.
+    pub columns: Vec<ColumnId>,
+    /// The number of distinct combinations of non-`NULL` values of the columns, if known.
+    pub distinct_count: Option<f64>,
+    /// The most common combinations of values and the fraction of rows that have each of them.
+    pub most_common_values: Vec<(Vec<Value>, f64)>,
+    /// The functional dependencies between the columns of the group.
+    pub dependencies: Vec<FunctionalDependency>,
+}
+
+impl ColumnGroupStatistics {
+    /// Returns the fraction of rows that have the given values (in the order of `columns`), if
+    /// they are one of the most common combinations.
+    pub fn most_common_frequency(&self, values: &[Value]) -> Option<f64> {
+        self.most_common_values
+            .iter()
+            .find(|(common, _)| common[..] == *values)
+            .map(|&(_, frequency)| frequency)
+    }
+
+    /// The fraction of rows covered by the most common combinations of values.
+    pub fn most_common_total(&self) -> f64 {
+        self.most_common_values
+            .iter()
+            .map(|(_, frequency)| frequency)
+            .sum()
+    }
+
+    /// Returns a copy of these statistics where every column is replaced by the column it maps to,
+    /// such as the instance of the column in a scan.
+    pub fn rename_columns(&self, rename: impl Fn(ColumnId) -> ColumnId) -> Self {
+        Self {
+            columns: self.columns.iter().map(|&column| rename(column)).collect(),
+            dependencies: self
+                .dependencies
+                .iter()
+                .map(|dependency| FunctionalDependency {
+                    determinant: dependency
+                        .determinant
+                        .iter()
+                        .map(|&column| rename(column))
+                        .collect(),
+                    dependent: rename(dependency.dependent),
+                    degree: dependency.degree,
+                })
+                .collect(),
+            ..self.clone()
+        }
+    }
+}
+
+/// Statistics about a table and its columns.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TableStatistics {
+    /// The number of rows in the table at the time the statistics were collected.
+    pub row_count: f64,
+    pub columns: HashMap<ColumnId, ColumnStatistics>,
+    /// The statistics of the groups of columns that were declared to be correlated.
+    pub column_groups: Vec<ColumnGroupStatistics>,
+}
+
+impl TableStatistics {
+    pub fn column(&self, column_id: ColumnId) -> Option<&ColumnStatistics> {
+        self.columns.get(&column_id)
+    }
+}
@@ -0,0 +1,199 @@
+//! Small streaming data structures used to collect statistics in a single pass over a table.
+//!
+//! None of these need to be cryptographically sound, only fast and reproducible, so that the same
+//! rows and the same seed always produce the same statistics.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// A small, seedable pseudo-random number generator (SplitMix64).
+#[derive(Debug, Clone)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0..bound`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bound` is zero.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        assert!(bound > 0, "the bound must be positive");
+        // Lemire's multiply-shift, which is unbiased enough for sampling.
+        ((self.next_u64() as u128 * bound as u128) >> 64) as u64
+    }
+
+    /// Returns a number in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// A uniform random sample of a stream of items of unknown length (Vitter's Algorithm R).
+#[derive(Debug, Clone)]
+pub struct Reservoir<T> {
+    items: Vec<T>,
+    capacity: usize,
+    /// The number of items offered to the reservoir so far.
+    seen: u64,
+    random: Random,
+}
+
+impl<T> Reservoir<T> {
+    pub fn new(capacity: usize, seed: u64) -> Self {
+        Self {
+            items: Vec::with_capacity(capacity.min(1 << 16)),
+            capacity,
+            seen: 0,
+            random: Random::new(seed),
+        }
+    }
+
+    /// Offers the next item of the stream to the reservoir.
+    ///
+    /// The item is only created if it is actually kept, which avoids cloning every row of a table
+    /// just to throw most of them away.
+    pub fn offer_with(&mut self, item: impl FnOnce() -> T) {
+        self.seen += 1;
+
+        if self.items.len() < self.capacity {
+            self.items.push(item());
+            return;
+        }
+
+        let slot = self.random.below(self.seen) as usize;
+        if slot < self.capacity {
+            self.items[slot] = item();
+        }
+    }
+
+    pub fn offer(&mut self, item: T) {
+        self.offer_with(|| item);
+    }
+
+    /// The number of items offered to the reservoir so far.
+    pub fn seen(&self) -> u64 {
+        self.seen
+    }
+
+    /// Returns `true` if every item offered so far is in the sample.
+    pub fn is_complete(&self) -> bool {
+        self.seen as usize == self.items.len()
+    }
+
+    pub fn into_items(self) -> Vec<T> {
+        self.items
+    }
+}
+
+/// A HyperLogLog sketch that estimates the number of distinct items in a stream in constant
+/// memory.
+///
+/// With a precision of `p`, the sketch uses `2^p` one-byte registers and has a standard error of
+/// roughly `1.04 / sqrt(2^p)` (1.6% for the default precision of 12).
+#[derive(Debug, Clone, PartialEq)]
+pub struct HyperLogLog {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self::new(12)
+    }
+}
+
+impl HyperLogLog {
+    /// # Panics
+    ///
+    /// Panics if the precision is not between 4 and 18.
+    pub fn new(precision: u8) -> Self {
+        assert!(
+            (4..=18).contains(&precision),
+            "the precision must be between 4 and 18"
+        );
+
+        Self {
+            precision,
+            registers: vec![0; 1 << precision],
+        }
+    }
+
+    pub fn insert(&mut self, item: &impl Hash) {
+        // `DefaultHasher::new` always uses the same keys, so the estimates are reproducible.
+        let mut hasher = DefaultHasher::new();
+        item.hash(&mut hasher);
+        self.insert_hash(hasher.finish());
+    }
+
+    /// Inserts an item by its (well-mixed) 64-bit hash.
+    pub fn insert_hash(&mut self, hash: u64) {
+        let p = self.precision as u32;
+        let index = (hash >> (64 - p)) as usize;
+        // The position of the first 1 bit in the remaining bits, capped in case they are all 0.
+        let rank = ((hash << p).leading_zeros() + 1).min(64 - p + 1) as u8;
+
+        let register = &mut self.registers[index];
+        *register = (*register).max(rank);
+    }
+
+    /// Combines another sketch (with the same precision) into this one, so that this sketch
+    /// estimates the number of distinct items in the union of both streams.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the precisions differ.
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        assert_eq!(
+            self.precision, other.precision,
+            "can only merge sketches with the same precision"
+        );
+
+        for (register, &other) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(other);
+        }
+    }
+
+    /// Estimates the number of distinct items inserted so far.
+    pub fn estimate(&self) -> f64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|&register| 2f64.powi(-(register as i32)))
+            .sum();
+        let raw = alpha * m * m / sum;
+
+        // For small cardinalities, linear counting over the empty registers is more accurate.
+        let empty = self
+            .registers
+            .iter()
+            .filter(|&&register| register == 0)
+            .count();
+        if raw <= 2.5 * m && empty > 0 {
+            return m * (m / empty as f64).ln();
+        }
+
+        raw
+    }
+}
@@ -0,0 +1,304 @@
+use crate::catalog::{Catalog, ColumnId, DataType};
+use crate::{Expression, LogicalExpression, Memo, Scan};
+use std::sync::Arc;
+
+use super::builder::{StatisticsBuilder, StatisticsOptions};
+use super::sketch::{HyperLogLog, Random, Reservoir};
+use super::*;
+
+/// A histogram over the integers `0..100` with 4 buckets of 25 rows each.
+fn histogram() -> Histogram {
+    Histogram::new(
+        vec![0, 25, 50, 75, 100]
+            .into_iter()
+            .map(Value::Int64)
+            .collect(),
+        vec![25.0; 4],
+        vec![25.0; 4],
+    )
+}
+
+#[test]
+fn histogram_interpolates_within_buckets() {
+    let histogram = histogram();
+
+    assert_eq!(histogram.total(), 100.0);
+    assert_eq!(histogram.fraction_below(&Value::Int64(-5), false), 0.0);
+    assert_eq!(histogram.fraction_below(&Value::Int64(500), true), 1.0);
+    assert_eq!(histogram.fraction_below(&Value::Int64(50), false), 0.49);
+    assert_eq!(histogram.fraction_below(&Value::Int64(50), true), 0.51);
+
+    let middle = histogram.fraction_below(&Value::Int64(60), false);
+    assert!(
+        (middle - 0.6).abs() < 0.01,
+        "expected roughly 60%, got {middle}"
+    );
+
+    assert_eq!(histogram.fraction_equal(&Value::Int64(30)), 0.01);
+    assert_eq!(histogram.fraction_equal(&Value::Int64(1000)), 0.0);
+}
+
+#[test]
+fn statistics_are_attached_to_the_catalog() {
+    let mut catalog = Catalog::new();
+    let table = catalog.add_table(
+        "t",
+        &[("a", DataType::Int64, true), ("b", DataType::Utf8, false)],
+        10,
+    );
+    let [a, b] = catalog.table(table).unwrap().column_ids()[..] else {
+        unreachable!()
+    };
+
+    let a_statistics = ColumnStatistics {
+        null_fraction: 0.1,
+        distinct_count: 100.0,
+        most_common_values: vec![(Value::Int64(7), 0.2), (Value::Int64(8), 0.1)],
+        histogram: Some(histogram()),
+        min: Some(Value::Int64(0)),
+        max: Some(Value::Int64(100)),
+        average_width: 8.0,
+    };
+    let b_statistics = ColumnStatistics {
+        distinct_count: 1000.0,
+        average_width: 40.0,
+        ..ColumnStatistics::default()
+    };
+
+    catalog.set_statistics(
+        table,
+        TableStatistics {
+            row_count: 1000.0,
+            columns: HashMap::from([(a, a_statistics), (b, b_statistics)]),
+            column_groups: vec![],
+        },
+    );
+
+    let statistics = catalog.column_statistics(a).unwrap();
+    assert_eq!(
+        statistics.most_common_frequency(&Value::Int64(7)),
+        Some(0.2)
+    );
+    assert_eq!(statistics.most_common_frequency(&Value::Int64(9)), None);
+    assert!((statistics.most_common_total() - 0.3).abs() < 1e-9);
+    assert_eq!(catalog.table(table).unwrap().row_width(), 48.0);
+
+    // Logical properties use the row count from the statistics.
+    let catalog = Arc::new(catalog);
+    let memo = Memo::new(catalog.clone());
+    let scan = Arc::new(Expression::Logical(LogicalExpression::Scan(Scan {
+        table_id: table,
+        columns: vec![a, b],
+        filters: vec![],
+    })));
+    let (group, _) = memo.add_expression(&scan, None);
+    assert_eq!(group.row_count(), 1000.0);
+}
+
+#[test]
+fn sketches_are_accurate_and_reproducible() {
+    let mut sketch = HyperLogLog::default();
+    for value in 0..100_000i64 {
+        // Insert every value twice, which must not change the estimate.
+        sketch.insert(&Value::Int64(value));
+        sketch.insert(&Value::Int64(value));
+    }
+    let estimate = sketch.estimate();
+    assert!(
+        (estimate - 100_000.0).abs() < 5_000.0,
+        "expected roughly 100000 distinct values, got {estimate}"
+    );
+
+    let mut small = HyperLogLog::default();
+    (0..10i64).for_each(|value| small.insert(&value));
+    assert_eq!(small.estimate().round(), 10.0);
+
+    // Every item has the same chance of being sampled.
+    let mut hits = [0; 10];
+    for seed in 0..2_000 {
+        let mut reservoir = Reservoir::new(2, seed);
+        (0..10).for_each(|item| reservoir.offer(item));
+        assert_eq!(reservoir.seen(), 10);
+        assert!(!reservoir.is_complete());
+        reservoir
+            .into_items()
+            .into_iter()
+            .for_each(|item| hits[item] += 1);
+    }
+    assert!(
+        hits.iter().all(|&hits| (300..500).contains(&hits)),
+        "{hits:?}"
+    );
+
+    let sample = |seed| {
+        let mut reservoir = Reservoir::new(5, seed);
+        (0..1_000).for_each(|item| reservoir.offer(item));
+        reservoir.into_items()
+    };
+    assert_eq!(sample(7), sample(7));
+}
+
+#[test]
+fn small_tables_have_exact_statistics() {
+    let (a, b) = (ColumnId(0), ColumnId(1));
+    let rows: Vec<Vec<Value>> = [(1, "x"), (2, "yy"), (2, "yy"), (3, "x")]
+        .into_iter()
+        .map(|(a, b)| vec![Value::Int64(a), Value::from(b)])
+        .chain([vec![Value::Null, Value::from("zzz")]])
+        .collect();
+
+    let statistics = TableStatistics::from_rows(vec![a, b], &rows);
+    assert_eq!(statistics.row_count, 5.0);
+
+    let a = statistics.column(a).unwrap();
+    assert_eq!(a.null_fraction, 0.2);
+    assert_eq!(a.distinct_count, 3.0);
+    assert_eq!(a.min, Some(Value::Int64(1)));
+    assert_eq!(a.max, Some(Value::Int64(3)));
+    assert_eq!(a.average_width, 8.0);
+    assert_eq!(
+        a.most_common_values,
+        vec![
+            (Value::Int64(2), 0.4),
+            (Value::Int64(1), 0.2),
+            (Value::Int64(3), 0.2)
+        ]
+    );
+    let histogram = a.histogram.as_ref().unwrap();
+    assert_eq!(histogram.total(), 4.0);
+    assert_eq!(histogram.fraction_equal(&Value::Int64(2)), 0.5);
+
+    let b = statistics.column(b).unwrap();
+    assert_eq!(b.null_fraction, 0.0);
+    assert_eq!(b.distinct_count, 3.0);
+    assert_eq!(b.average_width, 1.8);
+}
+
+#[test]
+fn sampled_statistics_match_the_data() {
+    // 100,000 rows where `uniform` is spread evenly over 0..1000, and `skewed` is NULL 10% of the
+    // time, 7 for half of the rows, and otherwise spread over 0..50,000.
+    let mut random = Random::new(42);
+    let rows: Vec<Vec<Value>> = (0..100_000)
+        .map(|_| {
+            let uniform = Value::Int64(random.below(1_000) as i64);
+            let skewed = match random.next_f64() {
+                p if p < 0.1 => Value::Null,
+                p if p < 0.6 => Value::Int64(7),
+                _ => Value::Int64(random.below(50_000) as i64),
+            };
+            vec![uniform, skewed]
+        })
+        .collect();
+
+    let mut catalog = Catalog::new();
+    let table = catalog.add_table(
+        "t",
+        &[
+            ("uniform", DataType::Int64, false),
+            ("skewed", DataType::Int64, true),
+        ],
+        0,
+    );
+    let [uniform, skewed] = catalog.table(table).unwrap().column_ids()[..] else {
+        unreachable!()
+    };
+
+    let options = StatisticsOptions {
+        sample_size: 10_000,
+        ..StatisticsOptions::default()
+    };
+    let mut builder = StatisticsBuilder::with_options(vec![uniform, skewed], options);
+    builder.add_rows(&rows);
+    catalog.set_statistics(table, builder.finish());
+
+    let close = |actual: f64, expected: f64, tolerance: f64| {
+        assert!(
+            (actual - expected).abs() <= expected * tolerance,
+            "expected roughly {expected}, got {actual}"
+        );
+    };
+
+    assert_eq!(
+        catalog.table(table).unwrap().estimated_row_count(),
+        100_000.0
+    );
+
+    let statistics = catalog.column_statistics(uniform).unwrap();
+    assert_eq!(statistics.null_fraction, 0.0);
+    close(statistics.distinct_count, 1_000.0, 0.05);
+    let histogram = statistics.histogram.as_ref().unwrap();
+    assert_eq!(histogram.buckets(), 100);
+    close(histogram.total(), 100_000.0, 1e-9);
+    close(
+        histogram.fraction_below(&Value::Int64(250), false),
+        0.25,
+        0.05,
+    );
+    close(
+        histogram.fraction_below(&Value::Int64(900), true),
+        0.9,
+        0.05,
+    );
+
+    let statistics = catalog.column_statistics(skewed).unwrap();
+    close(statistics.null_fraction, 0.1, 0.05);
+    close(statistics.distinct_count, 27_534.0, 0.05);
+    let (value, frequency) = &statistics.most_common_values[0];
+    assert_eq!(value, &Value::Int64(7));
+    close(*frequency, 0.5, 0.05);
+    // The rest of the values are too rare to stand out in the sample.
+    assert_eq!(statistics.most_common_values.len(), 1);
+}
+
+#[test]
+fn column_groups_capture_correlations() {
+    // Every zip code is in exactly one of 100 cities.
+    let (city, zip, other) = (ColumnId(0), ColumnId(1), ColumnId(2));
+    let mut random = Random::new(7);
+    let rows: Vec<Vec<Value>> = (0..20_000)
+        .map(|_| {
+            let zip = random.below(1_000) as i64;
+            let other = random.below(1_000) as i64;
+            vec![
+                Value::Int64(zip / 10),
+                Value::Int64(zip),
+                Value::Int64(other),
+            ]
+        })
+        .collect();
+
+    let mut builder = StatisticsBuilder::new(vec![city, zip, other])
+        .with_column_group(vec![city, zip])
+        .with_column_group(vec![zip, other]);
+    builder.add_rows(&rows);
+    let statistics = builder.finish();
+
+    let degree = |group: &ColumnGroupStatistics, determinant: ColumnId, dependent: ColumnId| {
+        group
+            .dependencies
+            .iter()
+            .find(|dependency| {
+                dependency.determinant == [determinant] && dependency.dependent == dependent
+            })
+            .map(|dependency| dependency.degree)
+            .unwrap()
+    };
+
+    let [correlated, independent] = &statistics.column_groups[..] else {
+        panic!("expected two column groups");
+    };
+
+    assert_eq!(correlated.columns, vec![city, zip]);
+    assert_eq!(
+        correlated.distinct_count,
+        Some(statistics.column(zip).unwrap().distinct_count)
+    );
+    assert_eq!(degree(correlated, zip, city), 1.0);
+    assert_eq!(degree(correlated, city, zip), 0.0);
+
+    // With 20 rows per zip code, almost every combination with an unrelated column is unique.
+    let distinct = independent.distinct_count.unwrap();
+    assert!(distinct > 18_000.0, "got {distinct}");
+    assert!(degree(independent, zip, other) < 0.01);
+}
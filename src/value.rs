@@ -0,0 +1,146 @@
+//! Constant values, as they appear in statistics and in scalar expressions.
+
+use crate::catalog::DataType;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+/// A single (possibly `NULL`) value of one of the [`DataType`]s.
+///
+/// Values are totally ordered so that they can be used as histogram bounds. `NULL` sorts before
+/// everything else, and integers and floats are compared numerically with each other.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Null,
+    Boolean(bool),
+    Int64(i64),
+    Float64(f64),
+    Utf8(String),
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    /// Returns the type of this value, or `None` if it is `NULL`.
+    pub fn data_type(&self) -> Option<DataType> {
+        match self {
+            Value::Null => None,
+            Value::Boolean(_) => Some(DataType::Boolean),
+            Value::Int64(_) => Some(DataType::Int64),
+            Value::Float64(_) => Some(DataType::Float64),
+            Value::Utf8(_) => Some(DataType::Utf8),
+        }
+    }
+
+    /// Maps this value onto a number line that preserves the ordering of values of the same type,
+    /// so that statistics can interpolate between two values.
+    ///
+    /// Strings are mapped using their first 8 bytes, which is enough to tell most strings apart.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Null => None,
+            Value::Boolean(value) => Some(*value as u8 as f64),
+            Value::Int64(value) => Some(*value as f64),
+            Value::Float64(value) => Some(*value),
+            Value::Utf8(value) => {
+                let mut prefix = [0u8; 8];
+                for (byte, &value) in prefix.iter_mut().zip(value.as_bytes()) {
+                    *byte = value;
+                }
+                Some(u64::from_be_bytes(prefix) as f64)
+            }
+        }
+    }
+
+    /// The width of this value in bytes.
+    pub fn width(&self) -> usize {
+        match self {
+            Value::Null => 0,
+            Value::Utf8(value) => value.len(),
+            _ => self.data_type().map_or(0, |data_type| data_type.width()),
+        }
+    }
+
+    /// The position of the variant in the ordering of values of different types.
+    fn rank(&self) -> u8 {
+        match self {
+            Value::Null => 0,
+            Value::Boolean(_) => 1,
+            Value::Int64(_) | Value::Float64(_) => 2,
+            Value::Utf8(_) => 3,
+        }
+    }
+}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Value::Null, Value::Null) => Ordering::Equal,
+            (Value::Boolean(left), Value::Boolean(right)) => left.cmp(right),
+            (Value::Int64(left), Value::Int64(right)) => left.cmp(right),
+            (Value::Float64(left), Value::Float64(right)) => left.total_cmp(right),
+            // Numerically equal integers sort before floats so that the ordering is consistent
+            // with equality.
+            (Value::Int64(left), Value::Float64(right)) => {
+                (*left as f64).total_cmp(right).then(Ordering::Less)
+            }
+            (Value::Float64(left), Value::Int64(right)) => {
+                left.total_cmp(&(*right as f64)).then(Ordering::Greater)
+            }
+            (Value::Utf8(left), Value::Utf8(right)) => left.cmp(right),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Value {}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Value::Null => {}
+            Value::Boolean(value) => value.hash(state),
+            Value::Int64(value) => value.hash(state),
+            Value::Float64(value) => value.to_bits().hash(state),
+            Value::Utf8(value) => value.hash(state),
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Boolean(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Int64(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Float64(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Utf8(value.to_string())
+    }
+}